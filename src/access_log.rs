@@ -0,0 +1,353 @@
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::config;
+
+/// File name of the proxy's access log, in ~/.dual/logs.
+pub const ACCESS_LOG_FILENAME: &str = "proxy-access.log";
+
+/// Size at which the access log is rotated to `proxy-access.log.1`.
+pub const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Requests kept in memory for `/_dual/requests`.
+pub const RECENT_CAPACITY: usize = 200;
+
+/// Path of the proxy's access log.
+pub fn access_log_path() -> Option<PathBuf> {
+    config::logs_dir().map(|dir| dir.join(ACCESS_LOG_FILENAME))
+}
+
+/// Path the access log is rotated to.
+pub fn rotated_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".1");
+    PathBuf::from(name)
+}
+
+/// One request through the proxy, logged as a JSON line.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AccessEntry {
+    /// RFC 3339 time the request arrived
+    pub time: String,
+    /// Workspace it was routed to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    pub host: String,
+    pub method: String,
+    /// Path and query (query values redacted in the log file)
+    pub path: String,
+    pub status: u16,
+    /// Milliseconds until the response headers
+    pub latency_ms: u64,
+    /// Response body bytes sent to the client
+    pub bytes: u64,
+}
+
+impl AccessEntry {
+    /// Current time in the log's format.
+    pub fn now() -> String {
+        OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default()
+    }
+
+    /// Display line for `dual proxy log`.
+    pub fn line(&self) -> String {
+        format!(
+            "{} {} {} {} {} {}ms {}B",
+            self.time,
+            self.workspace.as_deref().unwrap_or("-"),
+            self.status,
+            self.method,
+            self.path,
+            self.latency_ms,
+            self.bytes
+        )
+    }
+}
+
+/// Replace every query value in a path with `REDACTED`, keeping the keys:
+/// "/callback?code=abc&state=1" → "/callback?code=REDACTED&state=REDACTED".
+///
+/// Queries often carry OAuth codes and tokens, which shouldn't sit on disk.
+pub fn redact_query(path: &str) -> String {
+    let Some((path, query)) = path.split_once('?') else {
+        return path.to_string();
+    };
+    let params: Vec<String> = query
+        .split('&')
+        .map(|param| match param.split_once('=') {
+            Some((key, _)) => format!("{key}=REDACTED"),
+            None => param.to_string(),
+        })
+        .collect();
+    format!("{path}?{}", params.join("&"))
+}
+
+/// Open a log file for appending, readable only by the owner.
+fn open_private(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let file = options.open(path)?;
+        // Logs created by older versions had the default umask
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        Ok(file)
+    }
+    #[cfg(not(unix))]
+    options.open(path)
+}
+
+/// The access log file, rotated once it reaches `MAX_LOG_BYTES`.
+///
+/// Created 0600, with query values redacted; the in-memory `Recent` keeps them.
+pub struct AccessLog {
+    path: PathBuf,
+    max_bytes: u64,
+    file: Mutex<Option<File>>,
+}
+
+impl AccessLog {
+    /// Open (or create) the log at `path`.
+    pub fn open(path: &Path, max_bytes: u64) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = open_private(path)?;
+        Ok(AccessLog {
+            path: path.to_path_buf(),
+            max_bytes,
+            file: Mutex::new(Some(file)),
+        })
+    }
+
+    /// Append an entry, rotating the file first if it's full.
+    pub fn write(&self, entry: &AccessEntry) -> io::Result<()> {
+        let entry = AccessEntry {
+            path: redact_query(&entry.path),
+            ..entry.clone()
+        };
+        let mut line = serde_json::to_string(&entry).map_err(io::Error::other)?;
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let full = match *file {
+            Some(ref f) => f.metadata()?.len() + line.len() as u64 > self.max_bytes,
+            None => true,
+        };
+        if full {
+            *file = None;
+            let _ = fs::rename(&self.path, rotated_path(&self.path));
+            *file = Some(open_private(&self.path)?);
+        }
+        match *file {
+            Some(ref mut f) => f.write_all(line.as_bytes()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The most recent requests, oldest first.
+pub struct Recent {
+    capacity: usize,
+    entries: Mutex<VecDeque<AccessEntry>>,
+}
+
+impl Recent {
+    pub fn new(capacity: usize) -> Self {
+        Recent {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Record an entry, dropping the oldest once full.
+    pub fn push(&self, entry: AccessEntry) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// The recorded entries as a JSON array.
+    pub fn to_json(&self) -> String {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        serde_json::to_string_pretty(&*entries).unwrap_or_else(|_| "[]".to_string())
+    }
+}
+
+/// Parse a log line, skipping lines that aren't entries.
+pub fn parse_line(line: &str) -> Option<AccessEntry> {
+    serde_json::from_str(line).ok()
+}
+
+/// Entries in the log (rotated file first), optionally only one workspace's.
+pub fn read(path: &Path, workspace: Option<&str>) -> io::Result<Vec<AccessEntry>> {
+    let mut entries = Vec::new();
+    for path in [rotated_path(path), path.to_path_buf()] {
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        for line in BufReader::new(file).lines() {
+            if let Some(entry) = parse_line(&line?)
+                && matches(&entry, workspace)
+            {
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Call `on_entry` for each entry appended to the log, forever.
+///
+/// Starts at the current end of the file and reopens it when it's rotated.
+pub fn follow(
+    path: &Path,
+    workspace: Option<&str>,
+    mut on_entry: impl FnMut(&AccessEntry),
+) -> io::Result<()> {
+    let mut offset = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let mut pending = String::new();
+    loop {
+        let len = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if len < offset {
+            // Rotated: read the new file from the start
+            offset = 0;
+            pending.clear();
+        }
+        if len > offset {
+            let mut file = File::open(path)?;
+            file.seek(SeekFrom::Start(offset))?;
+            let mut reader = BufReader::new(file);
+            loop {
+                let read = reader.read_line(&mut pending)?;
+                if read == 0 {
+                    break;
+                }
+                offset += read as u64;
+                // A partly written line is finished on the next poll
+                if !pending.ends_with('\n') {
+                    break;
+                }
+                if let Some(entry) = parse_line(pending.trim_end())
+                    && matches(&entry, workspace)
+                {
+                    on_entry(&entry);
+                }
+                pending.clear();
+            }
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
+}
+
+fn matches(entry: &AccessEntry, workspace: Option<&str>) -> bool {
+    workspace.is_none_or(|ws| entry.workspace.as_deref() == Some(ws))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(workspace: Option<&str>, path: &str) -> AccessEntry {
+        AccessEntry {
+            time: "2026-01-02T03:04:05Z".to_string(),
+            workspace: workspace.map(str::to_string),
+            host: "app-main.localhost:3000".to_string(),
+            method: "GET".to_string(),
+            path: path.to_string(),
+            status: 200,
+            latency_ms: 12,
+            bytes: 512,
+        }
+    }
+
+    #[test]
+    fn log_rotates_and_reads_back() {
+        let dir = std::env::temp_dir().join("dual-test-access-log");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join(ACCESS_LOG_FILENAME);
+
+        // Room for about two entries per file
+        let line_len = serde_json::to_string(&entry(Some("app-main"), "/a"))
+            .unwrap()
+            .len() as u64
+            + 1;
+        let log = AccessLog::open(&path, line_len * 2).unwrap();
+        log.write(&entry(Some("app-main"), "/a")).unwrap();
+        log.write(&entry(Some("app-feat"), "/b")).unwrap();
+        log.write(&entry(Some("app-main"), "/c")).unwrap();
+        assert!(rotated_path(&path).exists());
+
+        let paths: Vec<_> = read(&path, None)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(paths, vec!["/a", "/b", "/c"]);
+        let paths: Vec<_> = read(&path, Some("app-main"))
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(paths, vec!["/a", "/c"]);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn query_values_are_redacted() {
+        assert_eq!(
+            redact_query("/callback?code=abc&state=xyz&flag"),
+            "/callback?code=REDACTED&state=REDACTED&flag"
+        );
+        assert_eq!(redact_query("/plain"), "/plain");
+    }
+
+    #[test]
+    fn recent_keeps_newest() {
+        let recent = Recent::new(2);
+        for path in ["/a", "/b", "/c"] {
+            recent.push(entry(None, path));
+        }
+        let entries: Vec<AccessEntry> = serde_json::from_str(&recent.to_json()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, "/b");
+        assert_eq!(entries[1].path, "/c");
+    }
+
+    #[test]
+    fn entry_line_format() {
+        assert_eq!(
+            entry(Some("app-main"), "/api?x=1").line(),
+            "2026-01-02T03:04:05Z app-main 200 GET /api?x=1 12ms 512B"
+        );
+        assert_eq!(
+            entry(None, "/").line(),
+            "2026-01-02T03:04:05Z - 200 GET / 12ms 512B"
+        );
+        assert!(parse_line("not json").is_none());
+    }
+}
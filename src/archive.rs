@@ -0,0 +1,470 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::clone::{self, CloneError};
+use crate::config;
+use crate::state::WorkspaceEntry;
+
+const BUNDLE_FILENAME: &str = "repo.bundle";
+const PATCH_FILENAME: &str = "dirty.patch";
+const MANIFEST_FILENAME: &str = "archive.toml";
+
+/// Refs the stash entries are bundled under (`refs/stash` only holds the newest).
+const STASH_REFS: &str = "refs/dual-stash/";
+
+/// Metadata written alongside an archived clone.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ArchiveManifest {
+    /// The state entry the clone belonged to.
+    pub workspace: WorkspaceEntry,
+
+    /// The clone's `origin` remote at archive time, if any.
+    pub origin: Option<String>,
+
+    /// Unix timestamp (seconds) when the archive was written.
+    pub created: u64,
+}
+
+/// Archive a clone into `{archive_root}/{workspace_id}-{timestamp}/` (with a
+/// `-2`, `-3`, … suffix if that archive already exists).
+///
+/// The archive contains:
+/// - `repo.bundle` — every ref in the clone (branches, remotes) and every stash entry
+/// - `dirty.patch` — uncommitted and untracked changes against HEAD (if any)
+/// - `archive.toml` — the workspace entry, for `dual restore`
+///
+/// The clone itself is left untouched.
+pub fn archive_workspace(
+    workspace_dir: &Path,
+    archive_root: &Path,
+    entry: &WorkspaceEntry,
+) -> Result<PathBuf, ArchiveError> {
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let ws_id = config::workspace_id(&entry.repo, &entry.branch);
+    let dir = create_unique_dir(archive_root, &format!("{ws_id}-{created}"))?;
+
+    // Step 1: bundle all refs. Older stash entries only live in the reflog of
+    // refs/stash, so each entry gets a ref of its own while the bundle is written.
+    let stashes = stash_commits(workspace_dir);
+    let stash_refs: Vec<String> = (0..stashes.len())
+        .map(|i| format!("{STASH_REFS}{i}"))
+        .collect();
+    for (stash_ref, commit) in stash_refs.iter().zip(&stashes) {
+        clone::git_output(workspace_dir, &["update-ref", stash_ref, commit])?;
+    }
+    let bundle = dir.join(BUNDLE_FILENAME);
+    let bundled = clone::git_output(
+        workspace_dir,
+        &["bundle", "create", &bundle.to_string_lossy(), "--all"],
+    );
+    for stash_ref in &stash_refs {
+        let _ = clone::git_output(workspace_dir, &["update-ref", "-d", stash_ref]);
+    }
+    bundled?;
+
+    // Step 2: diff the working tree (including untracked files) against HEAD.
+    // A throwaway index keeps the user's staging area untouched.
+    let patch = dirty_patch(workspace_dir, &dir)?;
+    if !patch.is_empty() {
+        let patch_path = dir.join(PATCH_FILENAME);
+        std::fs::write(&patch_path, patch).map_err(|e| ArchiveError::Filesystem(patch_path, e))?;
+    }
+
+    // Step 3: manifest
+    let origin = clone::git_output(workspace_dir, &["remote", "get-url", "origin"])
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    let manifest = ArchiveManifest {
+        workspace: entry.clone(),
+        origin,
+        created,
+    };
+    let manifest_path = dir.join(MANIFEST_FILENAME);
+    let contents = toml::to_string_pretty(&manifest).map_err(ArchiveError::Serialize)?;
+    std::fs::write(&manifest_path, contents)
+        .map_err(|e| ArchiveError::Filesystem(manifest_path, e))?;
+
+    Ok(dir)
+}
+
+/// Create `{parent}/{name}`, or `{name}-2`, `{name}-3`, … if it already exists.
+fn create_unique_dir(parent: &Path, name: &str) -> Result<PathBuf, ArchiveError> {
+    std::fs::create_dir_all(parent).map_err(|e| ArchiveError::Filesystem(parent.into(), e))?;
+    let mut suffix = 1;
+    loop {
+        let dir = match suffix {
+            1 => parent.join(name),
+            n => parent.join(format!("{name}-{n}")),
+        };
+        match std::fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => suffix += 1,
+            Err(e) => return Err(ArchiveError::Filesystem(dir, e)),
+        }
+    }
+}
+
+/// Commits of every stash entry, newest first (`stash@{0}`, `stash@{1}`, …).
+fn stash_commits(workspace_dir: &Path) -> Vec<String> {
+    clone::git_output(
+        workspace_dir,
+        &["reflog", "show", "--format=%H", "refs/stash"],
+    )
+    .map(|out| out.lines().map(str::to_string).collect())
+    .unwrap_or_default()
+}
+
+/// Produce a binary patch of the working tree against HEAD, untracked files included.
+fn dirty_patch(workspace_dir: &Path, scratch_dir: &Path) -> Result<Vec<u8>, ArchiveError> {
+    let index = scratch_dir.join("index.tmp");
+    let run = |args: &[&str]| -> Result<Vec<u8>, ArchiveError> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(workspace_dir)
+            .env("GIT_INDEX_FILE", &index)
+            .output()
+            .map_err(|e| CloneError::GitNotFound(e.to_string()))?;
+        if !output.status.success() {
+            return Err(CloneError::GitCommand {
+                command: args.first().unwrap_or(&"git").to_string(),
+                dir: workspace_dir.to_path_buf(),
+                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            }
+            .into());
+        }
+        Ok(output.stdout)
+    };
+
+    run(&["read-tree", "HEAD"])?;
+    run(&["add", "-A"])?;
+    let patch = run(&["diff", "--cached", "--binary", "HEAD"]);
+    let _ = std::fs::remove_file(&index);
+    patch
+}
+
+/// List archives under `archive_root`, oldest first.
+pub fn list_archives(archive_root: &Path) -> Vec<(PathBuf, ArchiveManifest)> {
+    let Ok(entries) = std::fs::read_dir(archive_root) else {
+        return Vec::new();
+    };
+
+    let mut archives: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter_map(|dir| load_manifest(&dir).ok().map(|m| (dir, m)))
+        .collect();
+    archives.sort_by_key(|(_, m)| m.created);
+    archives
+}
+
+/// Find an archive by directory name, or the newest archive for a workspace id.
+pub fn find_archive(archive_root: &Path, name: &str) -> Option<(PathBuf, ArchiveManifest)> {
+    let archives = list_archives(archive_root);
+
+    if let Some(found) = archives
+        .iter()
+        .find(|(dir, _)| dir.file_name().is_some_and(|n| n == name))
+    {
+        return Some(found.clone());
+    }
+
+    archives
+        .into_iter()
+        .rev()
+        .find(|(_, m)| config::workspace_id(&m.workspace.repo, &m.workspace.branch) == name)
+}
+
+/// Read the manifest of an archive directory.
+pub fn load_manifest(archive_dir: &Path) -> Result<ArchiveManifest, ArchiveError> {
+    let path = archive_dir.join(MANIFEST_FILENAME);
+    let contents =
+        std::fs::read_to_string(&path).map_err(|e| ArchiveError::Filesystem(path.clone(), e))?;
+    toml::from_str(&contents).map_err(|e| ArchiveError::Parse(path, e))
+}
+
+/// Recreate a clone from an archive at `target_dir`.
+///
+/// Fetches every ref from the bundle, rebuilds the stash, checks out the
+/// archived branch and re-applies the dirty-tree patch so uncommitted work
+/// reappears unstaged. On failure `target_dir` is removed again.
+pub fn restore_archive(
+    archive_dir: &Path,
+    target_dir: &Path,
+) -> Result<ArchiveManifest, ArchiveError> {
+    let manifest = load_manifest(archive_dir)?;
+
+    if target_dir.exists() {
+        return Err(ArchiveError::TargetExists(target_dir.to_path_buf()));
+    }
+    std::fs::create_dir_all(target_dir)
+        .map_err(|e| ArchiveError::Filesystem(target_dir.to_path_buf(), e))?;
+
+    if let Err(e) = restore_into(archive_dir, target_dir, &manifest) {
+        let _ = std::fs::remove_dir_all(target_dir);
+        return Err(e);
+    }
+    Ok(manifest)
+}
+
+/// The git steps of `restore_archive`, into an existing empty `target_dir`.
+fn restore_into(
+    archive_dir: &Path,
+    target_dir: &Path,
+    manifest: &ArchiveManifest,
+) -> Result<(), ArchiveError> {
+    let bundle = archive_dir.join(BUNDLE_FILENAME);
+    let branch_ref = format!("refs/heads/{}", manifest.workspace.branch);
+
+    clone::git_output(target_dir, &["init", "--quiet"])?;
+    clone::git_output(
+        target_dir,
+        &[
+            "fetch",
+            "--quiet",
+            "--update-head-ok",
+            &bundle.to_string_lossy(),
+            "refs/*:refs/*",
+        ],
+    )?;
+    restore_stashes(target_dir)?;
+    clone::git_output(target_dir, &["symbolic-ref", "HEAD", &branch_ref])?;
+    clone::git_output(target_dir, &["reset", "--quiet", "--hard"])?;
+
+    if let Some(ref origin) = manifest.origin {
+        clone::git_output(target_dir, &["remote", "add", "origin", origin])?;
+    }
+
+    let patch = archive_dir.join(PATCH_FILENAME);
+    if patch.exists() {
+        clone::git_output(target_dir, &["apply", "--binary", &patch.to_string_lossy()])?;
+    }
+
+    Ok(())
+}
+
+/// Rebuild the stash from the entries bundled under `STASH_REFS`, oldest first.
+fn restore_stashes(target_dir: &Path) -> Result<(), ArchiveError> {
+    let refs = clone::git_output(
+        target_dir,
+        &["for-each-ref", "--format=%(refname)", STASH_REFS],
+    )?;
+    let mut entries: Vec<(usize, &str)> = refs
+        .lines()
+        .filter_map(|r| Some((r.strip_prefix(STASH_REFS)?.parse().ok()?, r)))
+        .collect();
+    if entries.is_empty() {
+        return Ok(());
+    }
+    entries.sort_by_key(|(i, _)| std::cmp::Reverse(*i));
+
+    clone::git_output(target_dir, &["update-ref", "-d", "refs/stash"])?;
+    for (_, stash_ref) in entries {
+        let message = clone::git_output(target_dir, &["log", "-1", "--format=%s", stash_ref])?;
+        clone::git_output(
+            target_dir,
+            &["stash", "store", "-m", message.trim(), stash_ref],
+        )?;
+        clone::git_output(target_dir, &["update-ref", "-d", stash_ref])?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    #[error(transparent)]
+    Git(#[from] CloneError),
+
+    #[error("Filesystem error at {path}: {err}", path = .0.display(), err = .1)]
+    Filesystem(PathBuf, std::io::Error),
+
+    #[error("Failed to parse {path}: {err}", path = .0.display(), err = .1)]
+    Parse(PathBuf, toml::de::Error),
+
+    #[error("Failed to serialize archive manifest: {0}")]
+    Serialize(toml::ser::Error),
+
+    #[error("Restore target {path} already exists", path = .0.display())]
+    TargetExists(PathBuf),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let out = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(
+            out.status.success(),
+            "git {args:?}: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+    }
+
+    fn setup_repo(test_name: &str) -> PathBuf {
+        let base = std::env::temp_dir().join(format!("dual-test-archive-{test_name}"));
+        let _ = fs::remove_dir_all(&base);
+        let repo = base.join("repo");
+        fs::create_dir_all(&repo).unwrap();
+        git(&repo, &["init", "--quiet", "--initial-branch", "feat/x"]);
+        git(&repo, &["config", "user.email", "test@dual.dev"]);
+        git(&repo, &["config", "user.name", "Dual Test"]);
+        fs::write(repo.join("a.txt"), "one\n").unwrap();
+        git(&repo, &["add", "."]);
+        git(&repo, &["commit", "--quiet", "-m", "initial"]);
+        base
+    }
+
+    fn entry() -> WorkspaceEntry {
+        WorkspaceEntry {
+            repo: "app".to_string(),
+            url: "/tmp/app".to_string(),
+            branch: "feat/x".to_string(),
+            path: None,
+        }
+    }
+
+    #[test]
+    fn archive_and_restore_roundtrip() {
+        let base = setup_repo("roundtrip");
+        let repo = base.join("repo");
+
+        // Dirty the tree: modify a tracked file and add an untracked one
+        fs::write(repo.join("a.txt"), "two\n").unwrap();
+        fs::write(repo.join("new.txt"), "untracked\n").unwrap();
+
+        let archive_root = base.join("archive");
+        let archive_dir = archive_workspace(&repo, &archive_root, &entry()).unwrap();
+        assert!(archive_dir.join(BUNDLE_FILENAME).exists());
+        assert!(archive_dir.join(PATCH_FILENAME).exists());
+
+        // The user's index is untouched: new.txt is still untracked
+        let status = clone::git_output(&repo, &["status", "--porcelain"]).unwrap();
+        assert!(status.contains("?? new.txt"));
+
+        let target = base.join("restored");
+        let manifest = restore_archive(&archive_dir, &target).unwrap();
+        assert_eq!(manifest.workspace, entry());
+        assert_eq!(fs::read_to_string(target.join("a.txt")).unwrap(), "two\n");
+        assert_eq!(
+            fs::read_to_string(target.join("new.txt")).unwrap(),
+            "untracked\n"
+        );
+        let head = clone::git_output(&target, &["rev-parse", "--abbrev-ref", "HEAD"]).unwrap();
+        assert_eq!(head.trim(), "feat/x");
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn every_stash_entry_is_restored() {
+        let base = setup_repo("stashes");
+        let repo = base.join("repo");
+        for (i, contents) in ["first\n", "second\n"].iter().enumerate() {
+            fs::write(repo.join("a.txt"), contents).unwrap();
+            git(
+                &repo,
+                &["stash", "push", "--quiet", "-m", &format!("stash {i}")],
+            );
+        }
+
+        let archive_dir = archive_workspace(&repo, &base.join("archive"), &entry()).unwrap();
+        // The temporary stash refs are gone from the clone again
+        let refs = clone::git_output(&repo, &["for-each-ref", STASH_REFS]).unwrap();
+        assert!(refs.is_empty());
+
+        let target = base.join("restored");
+        restore_archive(&archive_dir, &target).unwrap();
+        let list = clone::git_output(&target, &["stash", "list", "--format=%gs"]).unwrap();
+        let messages: Vec<&str> = list.lines().collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].ends_with("stash 1"));
+        assert!(messages[1].ends_with("stash 0"));
+        let oldest = clone::git_output(&target, &["show", "stash@{1}:a.txt"]).unwrap();
+        assert_eq!(oldest, "first\n");
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn archives_in_the_same_second_get_their_own_dir() {
+        let base = setup_repo("same-second");
+        let archive_root = base.join("archive");
+        let ws_id = config::workspace_id("app", "feat/x");
+        fs::create_dir_all(&archive_root).unwrap();
+
+        let first = create_unique_dir(&archive_root, &format!("{ws_id}-1")).unwrap();
+        let second = create_unique_dir(&archive_root, &format!("{ws_id}-1")).unwrap();
+        assert_ne!(first, second);
+        assert!(second.ends_with(format!("{ws_id}-1-2")));
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn failed_restore_removes_target() {
+        let base = setup_repo("failed-restore");
+        let archive_dir =
+            archive_workspace(&base.join("repo"), &base.join("archive"), &entry()).unwrap();
+        fs::write(archive_dir.join(BUNDLE_FILENAME), "not a bundle").unwrap();
+
+        let target = base.join("restored");
+        assert!(restore_archive(&archive_dir, &target).is_err());
+        assert!(!target.exists());
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn clean_tree_writes_no_patch() {
+        let base = setup_repo("clean");
+        let archive_dir =
+            archive_workspace(&base.join("repo"), &base.join("archive"), &entry()).unwrap();
+        assert!(!archive_dir.join(PATCH_FILENAME).exists());
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn find_archive_by_workspace_id() {
+        let base = setup_repo("find");
+        let archive_root = base.join("archive");
+        let archive_dir = archive_workspace(&base.join("repo"), &archive_root, &entry()).unwrap();
+
+        let dir_name = archive_dir
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let ws_id = config::workspace_id("app", "feat/x");
+        assert_eq!(find_archive(&archive_root, &ws_id).unwrap().0, archive_dir);
+        assert_eq!(
+            find_archive(&archive_root, &dir_name).unwrap().0,
+            archive_dir
+        );
+        assert!(find_archive(&archive_root, "other-main").is_none());
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn restore_refuses_existing_target() {
+        let base = setup_repo("exists");
+        let archive_dir =
+            archive_workspace(&base.join("repo"), &base.join("archive"), &entry()).unwrap();
+        let err = restore_archive(&archive_dir, &base.join("repo")).unwrap_err();
+        assert!(matches!(err, ArchiveError::TargetExists(_)));
+
+        let _ = fs::remove_dir_all(&base);
+    }
+}
@@ -28,11 +28,19 @@ pub trait MultiplexerBackend {
     /// Destroy a session and all its windows/panes.
     fn destroy(&self, session_name: &str) -> Result<(), BackendError>;
 
+    /// Rename an existing session.
+    fn rename_session(&self, session_name: &str, new_name: &str) -> Result<(), BackendError>;
+
     /// Check if a session exists and has running processes.
     fn is_alive(&self, session_name: &str) -> bool;
 
-    /// List all Dual-managed sessions (filtered by `dual-` prefix).
-    fn list_sessions(&self) -> Vec<String>;
+    /// Tag a session with the workspace it belongs to, so it can be matched
+    /// back to its workspace regardless of its name.
+    fn tag_session(&self, session_name: &str, workspace_id: &str) -> Result<(), BackendError>;
+
+    /// List all Dual-managed sessions (those tagged by `tag_session`) as
+    /// (session name, workspace id).
+    fn list_sessions(&self) -> Vec<(String, String)>;
 
     /// Send keystrokes to a session's active pane.
     fn send_keys(&self, session_name: &str, keys: &str) -> Result<(), BackendError>;
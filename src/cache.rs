@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::config::{CacheEntry, CacheScope};
+use crate::container::{self, BindMount, ContainerError};
+
+/// Label naming the cache a volume holds.
+const CACHE_LABEL: &str = "dev.dual.cache";
+
+/// Label naming the repo of a per-repo cache (absent on global caches).
+const CACHE_REPO_LABEL: &str = "dev.dual.cache.repo";
+
+/// Small image used to empty cache volumes.
+const CLEAR_IMAGE: &str = "busybox";
+
+/// Env vars pointing a tool at its cache directory.
+type PresetEnv = &'static [(&'static str, &'static str)];
+
+/// Built-in caches: name, directory in the container, env vars.
+const PRESETS: &[(&str, &str, PresetEnv)] = &[
+    (
+        "pnpm",
+        "/cache/pnpm",
+        &[("npm_config_store_dir", "/cache/pnpm")],
+    ),
+    ("npm", "/cache/npm", &[("npm_config_cache", "/cache/npm")]),
+    ("pip", "/cache/pip", &[("PIP_CACHE_DIR", "/cache/pip")]),
+    ("uv", "/cache/uv", &[("UV_CACHE_DIR", "/cache/uv")]),
+    // Cargo can't move its registry without moving CARGO_HOME (and the
+    // toolchain's bin/ with it), so mount where the official rust images keep it.
+    ("cargo", "/usr/local/cargo/registry", &[]),
+    (
+        "go",
+        "/cache/go",
+        &[
+            ("GOMODCACHE", "/cache/go/mod"),
+            ("GOCACHE", "/cache/go/build"),
+        ],
+    ),
+];
+
+/// A cache directory in the container and the env vars that point tools at it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cache {
+    pub name: String,
+    pub path: String,
+    pub env: Vec<(String, String)>,
+}
+
+impl Cache {
+    /// Resolve a `caches` entry from `.dual.toml`.
+    pub fn from_entry(entry: &CacheEntry) -> Result<Self, CacheError> {
+        match entry {
+            CacheEntry::Preset(name) => PRESETS
+                .iter()
+                .find(|(preset, _, _)| preset == name)
+                .map(|(name, path, env)| Cache {
+                    name: name.to_string(),
+                    path: path.to_string(),
+                    env: env
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                })
+                .ok_or_else(|| CacheError::UnknownPreset(name.clone())),
+            CacheEntry::Custom { name, path, env } => {
+                let mut env: Vec<_> = env.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+                env.sort();
+                Ok(Cache {
+                    name: name.clone(),
+                    path: path.clone(),
+                    env,
+                })
+            }
+        }
+    }
+
+    /// Volume holding this cache: `dual-cache-{name}` or `dual-cache-{repo}-{name}`.
+    pub fn volume_name(&self, scope: CacheScope, repo: &str) -> String {
+        match scope {
+            CacheScope::Global => container::volume_name("dual-cache", &self.name),
+            CacheScope::Repo => {
+                container::volume_name(&container::volume_name("dual-cache", repo), &self.name)
+            }
+        }
+    }
+
+    /// Mount of this cache's volume into a container.
+    pub fn mount(&self, scope: CacheScope, repo: &str) -> BindMount {
+        BindMount {
+            source: PathBuf::from(self.volume_name(scope, repo)),
+            target: self.path.clone(),
+            read_only: false,
+        }
+    }
+
+    /// Create the cache's volume (labelled with the cache and repo) if missing.
+    pub fn ensure_volume(&self, scope: CacheScope, repo: &str) -> Result<(), ContainerError> {
+        let mut labels = vec![(CACHE_LABEL, self.name.as_str())];
+        if scope == CacheScope::Repo {
+            labels.push((CACHE_REPO_LABEL, repo));
+        }
+        container::create_volume(&self.volume_name(scope, repo), &labels)
+    }
+}
+
+/// Env vars for a set of caches.
+pub fn env(caches: &[Cache]) -> HashMap<String, String> {
+    caches
+        .iter()
+        .flat_map(|cache| cache.env.iter().cloned())
+        .collect()
+}
+
+/// A cache volume, as listed by `list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheVolume {
+    pub name: String,
+    /// Cache name (e.g. "pnpm").
+    pub cache: String,
+    /// Owning repo for per-repo caches; None for global ones.
+    pub repo: Option<String>,
+    /// Disk usage as reported by Docker, if known.
+    pub size: Option<String>,
+}
+
+/// List cache volumes with their disk usage.
+pub fn list() -> Vec<CacheVolume> {
+    let output = Command::new("docker")
+        .args([
+            "volume",
+            "ls",
+            "--filter",
+            &format!("label={CACHE_LABEL}"),
+            "--format",
+            &format!(
+                "{{{{.Name}}}}\t{{{{.Label \"{CACHE_LABEL}\"}}}}\t{{{{.Label \"{CACHE_REPO_LABEL}\"}}}}"
+            ),
+        ])
+        .output();
+
+    let stdout = match output {
+        Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout).to_string(),
+        _ => return Vec::new(),
+    };
+
+    let sizes = container::volume_sizes();
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('\t');
+            let name = parts.next()?.to_string();
+            let cache = parts.next().unwrap_or("").to_string();
+            let repo = parts.next().filter(|r| !r.is_empty()).map(str::to_string);
+            let size = sizes.get(&name).cloned();
+            Some(CacheVolume {
+                name,
+                cache,
+                repo,
+                size,
+            })
+        })
+        .collect()
+}
+
+/// Empty a cache volume. Works while containers are using it, so the volume
+/// itself (and its mounts) stay in place.
+pub fn clear(volume: &str) -> Result<(), ContainerError> {
+    let output = Command::new("docker")
+        .args(build_clear_args(volume))
+        .output()
+        .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(ContainerError::Failed {
+            operation: "cache clear".to_string(),
+            name: volume.to_string(),
+            stderr,
+        });
+    }
+
+    Ok(())
+}
+
+/// Build docker run arguments for `clear` (for testing).
+pub fn build_clear_args(volume: &str) -> Vec<String> {
+    vec![
+        "run".to_string(),
+        "--rm".to_string(),
+        "-v".to_string(),
+        format!("{volume}:/cache"),
+        CLEAR_IMAGE.to_string(),
+        "find".to_string(),
+        "/cache".to_string(),
+        "-mindepth".to_string(),
+        "1".to_string(),
+        "-delete".to_string(),
+    ]
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error("unknown cache preset '{0}' (expected one of pnpm, npm, pip, uv, cargo, go)")]
+    UnknownPreset(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_resolve_with_env() {
+        let pnpm = Cache::from_entry(&CacheEntry::Preset("pnpm".to_string())).unwrap();
+        assert_eq!(pnpm.path, "/cache/pnpm");
+        assert_eq!(
+            pnpm.env,
+            vec![(
+                "npm_config_store_dir".to_string(),
+                "/cache/pnpm".to_string()
+            )]
+        );
+
+        let go = Cache::from_entry(&CacheEntry::Preset("go".to_string())).unwrap();
+        let env = env(&[pnpm, go]);
+        assert_eq!(env["GOMODCACHE"], "/cache/go/mod");
+        assert_eq!(env["GOCACHE"], "/cache/go/build");
+        assert_eq!(env["npm_config_store_dir"], "/cache/pnpm");
+
+        assert!(matches!(
+            Cache::from_entry(&CacheEntry::Preset("yarnn".to_string())),
+            Err(CacheError::UnknownPreset(_))
+        ));
+    }
+
+    #[test]
+    fn volume_names_by_scope() {
+        let cache = Cache::from_entry(&CacheEntry::Custom {
+            name: "bun".to_string(),
+            path: "/root/.bun/install/cache".to_string(),
+            env: HashMap::new(),
+        })
+        .unwrap();
+
+        assert_eq!(
+            cache.volume_name(CacheScope::Global, "app"),
+            "dual-cache-bun"
+        );
+        assert_eq!(
+            cache.volume_name(CacheScope::Repo, "My App"),
+            format!(
+                "dual-cache-My_App--{}-bun",
+                crate::config::name_tag("My App")
+            )
+        );
+
+        let mount = cache.mount(CacheScope::Global, "app");
+        assert_eq!(mount.source, PathBuf::from("dual-cache-bun"));
+        assert_eq!(mount.target, "/root/.bun/install/cache");
+        assert!(!mount.read_only);
+    }
+
+    #[test]
+    fn clear_args_empty_the_volume() {
+        let args = build_clear_args("dual-cache-pnpm");
+        assert!(args.contains(&"dual-cache-pnpm:/cache".to_string()));
+        assert_eq!(
+            &args[args.len() - 4..],
+            ["/cache", "-mindepth", "1", "-delete"]
+        );
+    }
+}
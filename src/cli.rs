@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(
@@ -34,6 +34,10 @@ pub enum Command {
     Launch {
         /// Workspace to launch (auto-detected from cwd if omitted)
         workspace: Option<String>,
+
+        /// Wait for the workspace's ports to be ready before attaching
+        #[arg(long)]
+        wait: bool,
     },
 
     /// List all workspaces and their status
@@ -43,12 +47,61 @@ pub enum Command {
     Destroy {
         /// Workspace to destroy (auto-detected from cwd if omitted)
         workspace: Option<String>,
+
+        /// Destroy even if the clone has uncommitted, unpushed or stashed work,
+        /// or a `pre_destroy`/`pre_stop` hook fails with `on_failure = "abort"`
+        #[arg(long)]
+        force: bool,
+
+        /// Archive the clone to ~/.dual/archive/ before removing it
+        #[arg(long)]
+        archive: bool,
+    },
+
+    /// Rename a workspace's branch (git branch, clone dir, container, tmux session)
+    Rename {
+        /// Workspace to rename (e.g. lightfast-main)
+        workspace: String,
+
+        /// New branch name
+        new_branch: String,
+    },
+
+    /// Move workspaces created by older versions to the current naming scheme
+    Migrate,
+
+    /// Recreate a destroyed workspace from its archive
+    Restore {
+        /// Archive name or workspace id (newest archive wins; lists archives if omitted)
+        archive: Option<String>,
     },
 
     /// Open all services for a workspace in the browser
     Open {
         /// Workspace to open (defaults to current)
         workspace: Option<String>,
+
+        /// Open only the port with this name, even if it sets `open = false`
+        #[arg(long)]
+        service: Option<String>,
+
+        /// Open right away instead of waiting for the ports to be ready
+        #[arg(long)]
+        no_wait: bool,
+    },
+
+    /// Wait until a workspace's ports pass their readiness checks
+    Wait {
+        /// Workspace to wait for (detected from current directory if omitted)
+        workspace: Option<String>,
+
+        /// Only wait for the port with this name
+        #[arg(long)]
+        service: Option<String>,
+
+        /// Seconds to wait before failing
+        #[arg(long, default_value_t = 120)]
+        timeout: u64,
     },
 
     /// List running workspace URLs
@@ -61,10 +114,96 @@ pub enum Command {
     Sync {
         /// Workspace to sync (detected from current directory if omitted)
         workspace: Option<String>,
+
+        /// List which workspaces have out-of-date, changed or conflicting shared files
+        #[arg(long)]
+        status: bool,
+
+        /// Push files changed in the workspace back to the shared store
+        #[arg(long)]
+        push: bool,
+
+        /// Show a diff of every shared file that differs from the store, without syncing
+        #[arg(long)]
+        diff: bool,
+
+        /// Show the resolved shared file set and what would be synced, without syncing
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Resolve conflicts: take the shared copy, or the workspace copy with --push
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Recreate a workspace's container to apply .dual.toml changes (volumes are kept)
+    Recreate {
+        /// Workspace to recreate (auto-detected from cwd if omitted)
+        workspace: Option<String>,
+
+        /// Recreate even if the container already matches the config
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Run a workspace's setup steps (only failed or changed ones by default)
+    Setup {
+        /// Workspace to set up (auto-detected from cwd if omitted)
+        workspace: Option<String>,
+
+        /// Run every step, even those that already succeeded
+        #[arg(long)]
+        rerun: bool,
+    },
+
+    /// List workspace volumes or seed them from another workspace
+    Volumes {
+        #[command(subcommand)]
+        action: Option<VolumesAction>,
+    },
+
+    /// List or clear shared package-manager caches
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Show live CPU, memory and process usage per workspace container
+    Top {
+        /// Column to sort by (highest first; name sorts alphabetically)
+        #[arg(long, value_enum, default_value_t = TopSort::Cpu)]
+        sort: TopSort,
+
+        /// Seconds between refreshes
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+
+        /// Print a single snapshot instead of refreshing
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// Print a workspace's effective container environment (secrets masked)
+    Env {
+        /// Workspace to inspect (auto-detected from cwd if omitted)
+        workspace: Option<String>,
+
+        /// Print masked values (from env_files and secrets) in the clear
+        #[arg(long)]
+        reveal: bool,
+    },
+
+    /// Manage encrypted secret variables passed to a repo's containers
+    Secrets {
+        #[command(subcommand)]
+        action: SecretsAction,
     },
 
     /// Start the reverse proxy for browser access
-    Proxy,
+    Proxy {
+        #[command(subcommand)]
+        action: Option<ProxyAction>,
+    },
 
     /// Output shell RC for a container (used internally)
     #[command(name = "shell-rc", hide = true)]
@@ -73,3 +212,103 @@ pub enum Command {
         container: String,
     },
 }
+
+#[derive(Subcommand)]
+pub enum CacheAction {
+    /// List cache volumes with their sizes
+    Ls,
+
+    /// Empty cache volumes (all of them unless a cache name is given)
+    Clear {
+        /// Cache to clear, e.g. "pnpm"
+        name: Option<String>,
+
+        /// Only clear this repo's per-repo caches
+        #[arg(long)]
+        repo: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum VolumesAction {
+    /// List volumes with their sizes (the default)
+    Ls {
+        /// Only show this workspace's volumes
+        workspace: Option<String>,
+    },
+
+    /// Copy isolated directories (e.g. node_modules) into a workspace's volumes
+    Seed {
+        /// Workspace to seed
+        workspace: String,
+
+        /// Workspace to copy from (defaults to the repo's main workspace)
+        #[arg(long)]
+        from: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ProxyAction {
+    /// Print the proxy's access log
+    Log {
+        /// Only show this workspace's requests
+        workspace: Option<String>,
+
+        /// Keep printing requests as they arrive
+        #[arg(short, long)]
+        follow: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SecretsAction {
+    /// Set a variable (value read from stdin if omitted)
+    Set {
+        /// Variable name
+        name: String,
+
+        /// Value (omit to read from stdin and keep it out of shell history)
+        value: Option<String>,
+
+        /// Repo name (auto-detected from cwd if omitted)
+        #[arg(long)]
+        repo: Option<String>,
+    },
+
+    /// Print a variable's value
+    Get {
+        /// Variable name
+        name: String,
+
+        /// Repo name (auto-detected from cwd if omitted)
+        #[arg(long)]
+        repo: Option<String>,
+    },
+
+    /// List variable names
+    List {
+        /// Repo name (auto-detected from cwd if omitted)
+        #[arg(long)]
+        repo: Option<String>,
+    },
+
+    /// Remove a variable
+    Rm {
+        /// Variable name
+        name: String,
+
+        /// Repo name (auto-detected from cwd if omitted)
+        #[arg(long)]
+        repo: Option<String>,
+    },
+}
+
+/// Sort order for `dual top`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum TopSort {
+    Cpu,
+    Mem,
+    Pids,
+    Name,
+}
@@ -137,6 +137,123 @@ pub fn clone_from_local(
     Ok(target_dir.to_path_buf())
 }
 
+/// Rename a branch in a clone (`git branch -m`).
+pub fn rename_branch(dir: &Path, branch: &str, new_branch: &str) -> Result<(), CloneError> {
+    git_output(dir, &["branch", "-m", branch, new_branch]).map(|_| ())
+}
+
+/// Work in a clone that would be lost if the clone were deleted.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UnsavedWork {
+    /// Tracked files with staged or unstaged modifications.
+    pub uncommitted: Vec<String>,
+    /// Untracked files (respecting .gitignore).
+    pub untracked: Vec<String>,
+    /// Commits on local branches that are not on any remote.
+    pub unpushed: usize,
+    /// Number of stash entries.
+    pub stashes: usize,
+}
+
+impl UnsavedWork {
+    /// True if nothing would be lost by deleting the clone.
+    pub fn is_clean(&self) -> bool {
+        self.uncommitted.is_empty()
+            && self.untracked.is_empty()
+            && self.unpushed == 0
+            && self.stashes == 0
+    }
+
+    /// One line per category of unsaved work, for display before refusing a destroy.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if !self.uncommitted.is_empty() {
+            lines.push(format!(
+                "{} uncommitted change(s): {}",
+                self.uncommitted.len(),
+                preview(&self.uncommitted)
+            ));
+        }
+        if !self.untracked.is_empty() {
+            lines.push(format!(
+                "{} untracked file(s): {}",
+                self.untracked.len(),
+                preview(&self.untracked)
+            ));
+        }
+        if self.unpushed > 0 {
+            lines.push(format!("{} unpushed commit(s)", self.unpushed));
+        }
+        if self.stashes > 0 {
+            lines.push(format!("{} stash entr(ies)", self.stashes));
+        }
+        lines
+    }
+}
+
+/// Show at most a few paths, followed by an ellipsis.
+fn preview(paths: &[String]) -> String {
+    const MAX: usize = 3;
+    let mut shown = paths
+        .iter()
+        .take(MAX)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if paths.len() > MAX {
+        shown.push_str(", ...");
+    }
+    shown
+}
+
+/// Inspect a clone for uncommitted changes, untracked files, unpushed commits and stashes.
+pub fn check_unsaved_work(dir: &Path) -> Result<UnsavedWork, CloneError> {
+    let mut work = UnsavedWork::default();
+
+    let status = git_output(dir, &["status", "--porcelain", "--untracked-files=all"])?;
+    for line in status.lines() {
+        if line.len() < 4 {
+            continue;
+        }
+        let (code, path) = line.split_at(3);
+        if code.starts_with("??") {
+            work.untracked.push(path.to_string());
+        } else {
+            work.uncommitted.push(path.to_string());
+        }
+    }
+
+    let unpushed = git_output(
+        dir,
+        &["rev-list", "--count", "--branches", "--not", "--remotes"],
+    )?;
+    work.unpushed = unpushed.trim().parse().unwrap_or(0);
+
+    let stashes = git_output(dir, &["stash", "list"])?;
+    work.stashes = stashes.lines().filter(|l| !l.is_empty()).count();
+
+    Ok(work)
+}
+
+/// Run a git command in `dir` and return its stdout.
+pub(crate) fn git_output(dir: &Path, args: &[&str]) -> Result<String, CloneError> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| CloneError::GitNotFound(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(CloneError::GitCommand {
+            command: args.first().unwrap_or(&"git").to_string(),
+            dir: dir.to_path_buf(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
 /// Build the git clone --local arguments (for testing).
 pub fn build_local_clone_args(main_workspace_path: &Path, target: &Path) -> Vec<String> {
     vec![
@@ -175,6 +292,13 @@ pub enum CloneError {
         stderr: String,
     },
 
+    #[error("git {command} failed in {dir}: {stderr}", dir = .dir.display())]
+    GitCommand {
+        command: String,
+        dir: PathBuf,
+        stderr: String,
+    },
+
     #[error("filesystem error at {path}: {err}", path = .0.display(), err = .1)]
     Filesystem(PathBuf, std::io::Error),
 }
@@ -259,4 +383,62 @@ mod tests {
             ]
         );
     }
+
+    fn init_repo(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dual-test-clone-{test_name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for args in [
+            &["init", "--quiet", "--initial-branch", "main"][..],
+            &["config", "user.email", "test@dual.dev"],
+            &["config", "user.name", "Dual Test"],
+        ] {
+            git_output(&dir, args).unwrap();
+        }
+        std::fs::write(dir.join("a.txt"), "one\n").unwrap();
+        git_output(&dir, &["add", "."]).unwrap();
+        git_output(&dir, &["commit", "--quiet", "-m", "initial"]).unwrap();
+        dir
+    }
+
+    #[test]
+    fn unsaved_work_detects_each_category() {
+        let dir = init_repo("unsaved");
+
+        // No remote: the initial commit counts as unpushed
+        let work = check_unsaved_work(&dir).unwrap();
+        assert_eq!(work.unpushed, 1);
+        assert!(work.uncommitted.is_empty());
+        assert!(work.untracked.is_empty());
+
+        std::fs::write(dir.join("a.txt"), "two\n").unwrap();
+        git_output(&dir, &["stash", "--quiet"]).unwrap();
+        std::fs::write(dir.join("a.txt"), "three\n").unwrap();
+        std::fs::write(dir.join("b.txt"), "new\n").unwrap();
+
+        let work = check_unsaved_work(&dir).unwrap();
+        assert_eq!(work.uncommitted, vec!["a.txt"]);
+        assert_eq!(work.untracked, vec!["b.txt"]);
+        assert_eq!(work.stashes, 1);
+        assert!(!work.is_clean());
+        assert_eq!(work.summary().len(), 4);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn unsaved_work_clean_after_push() {
+        let origin = init_repo("pushed-origin");
+        let dir = std::env::temp_dir().join("dual-test-clone-pushed");
+        let _ = std::fs::remove_dir_all(&dir);
+        clone_from_local(&origin, &dir, "feat/x").unwrap();
+
+        // A fresh branch off origin/main has nothing unpushed
+        let work = check_unsaved_work(&dir).unwrap();
+        assert!(work.is_clean(), "{work:?}");
+        assert!(work.summary().is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_dir_all(&origin);
+    }
 }
@@ -0,0 +1,476 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde_norway::Value;
+
+use crate::config::ServiceConfig;
+use crate::dotenv;
+
+/// Services imported from a compose file.
+#[derive(Debug, Default, PartialEq)]
+pub struct ComposeImport {
+    pub services: BTreeMap<String, ServiceConfig>,
+
+    /// Services or settings that could not be imported, for the user to review.
+    pub skipped: Vec<String>,
+}
+
+/// Read services from a compose file.
+///
+/// Variables are interpolated as `docker compose` does: from the host
+/// environment, then the `.env` file next to the compose file.
+pub fn import(path: &Path) -> Result<ComposeImport, ComposeError> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| ComposeError::Read(path.to_path_buf(), e))?;
+
+    let mut vars = HashMap::new();
+    let mut notes = Vec::new();
+    let env_file = path.with_file_name(".env");
+    if let Ok(env_contents) = std::fs::read_to_string(&env_file) {
+        match dotenv::parse(&env_contents, &HashMap::new()) {
+            Ok(pairs) => vars.extend(pairs),
+            Err(e) => notes.push(format!("{}: {e}", env_file.display())),
+        }
+    }
+    vars.extend(std::env::vars());
+
+    let mut import =
+        parse(&contents, &vars).map_err(|e| ComposeError::Parse(path.to_path_buf(), e))?;
+    import.skipped.splice(0..0, notes);
+    Ok(import)
+}
+
+/// Parse the `services` of compose file contents, interpolating `vars`.
+///
+/// Imported per service: `image`, `environment` (map or `KEY=value` list; a
+/// bare `KEY` takes its value from `vars`), `command` (list, or a string split
+/// on whitespace and quotes) and named volumes (their target paths become
+/// per-workspace volumes). Services without an `image` and bind mounts are
+/// skipped. `ports` are not needed: services share the workspace container's
+/// network, so they are reachable on localhost. Anchors, aliases and `<<`
+/// merge keys are resolved first.
+pub fn parse(
+    contents: &str,
+    vars: &HashMap<String, String>,
+) -> Result<ComposeImport, serde_norway::Error> {
+    let mut doc: Value = match serde_norway::from_str(contents)? {
+        Value::Null => return Ok(ComposeImport::default()),
+        doc => doc,
+    };
+    doc.apply_merge()?;
+
+    let mut import = ComposeImport::default();
+    let mut unset = BTreeSet::new();
+    interpolate_all(&mut doc, vars, &mut unset);
+    for name in unset {
+        import
+            .skipped
+            .push(format!("variable {name} is not set; using an empty string"));
+    }
+
+    let Some(services) = doc.get("services").and_then(Value::as_mapping) else {
+        return Ok(import);
+    };
+
+    for (name, def) in services {
+        let Some(name) = scalar(name) else {
+            continue;
+        };
+        let Some(image) = def.get("image").and_then(scalar) else {
+            import.skipped.push(format!(
+                "service '{name}': no image (build-only services are not supported)"
+            ));
+            continue;
+        };
+
+        let mut env = HashMap::new();
+        match def.get("environment") {
+            Some(Value::Mapping(entries)) => {
+                for (key, value) in entries {
+                    let Some(key) = scalar(key) else { continue };
+                    // `KEY:` with no value is passed through from the environment
+                    let value = match value {
+                        Value::Null => vars.get(&key).cloned(),
+                        value => scalar(value),
+                    };
+                    match value {
+                        Some(value) => {
+                            env.insert(key, value);
+                        }
+                        None => import
+                            .skipped
+                            .push(format!("service '{name}': {key} is not set")),
+                    }
+                }
+            }
+            Some(Value::Sequence(entries)) => {
+                for entry in entries.iter().filter_map(scalar) {
+                    match entry.split_once('=') {
+                        Some((key, value)) => {
+                            env.insert(key.to_string(), value.to_string());
+                        }
+                        None => match vars.get(&entry) {
+                            Some(value) => {
+                                env.insert(entry, value.clone());
+                            }
+                            None => import
+                                .skipped
+                                .push(format!("service '{name}': {entry} is not set")),
+                        },
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let command = match def.get("command") {
+            Some(Value::Sequence(args)) => args.iter().filter_map(scalar).collect(),
+            Some(cmd) => scalar(cmd).map(|c| split_command(&c)).unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        let mut volumes = Vec::new();
+        let specs = def
+            .get("volumes")
+            .and_then(Value::as_sequence)
+            .map(Vec::as_slice)
+            .unwrap_or(&[]);
+        for volume in specs {
+            let target = match volume {
+                // Short syntax: [source:]target[:mode]
+                Value::String(spec) => {
+                    let parts: Vec<&str> = spec.split(':').collect();
+                    match parts.as_slice() {
+                        [target] => Some(target.to_string()),
+                        [source, target, ..] if !is_host_path(source) => Some(target.to_string()),
+                        _ => None,
+                    }
+                }
+                // Long syntax: { type: volume, source, target }
+                Value::Mapping(_) => volume
+                    .get("target")
+                    .and_then(scalar)
+                    .filter(|_| volume.get("type").and_then(Value::as_str) != Some("bind")),
+                _ => None,
+            };
+            match target {
+                Some(target) => volumes.push(target),
+                None => import
+                    .skipped
+                    .push(format!("service '{name}': bind mount {}", describe(volume))),
+            }
+        }
+
+        import.services.insert(
+            name,
+            ServiceConfig {
+                image,
+                env,
+                command,
+                volumes,
+            },
+        );
+    }
+
+    Ok(import)
+}
+
+/// A string, number or boolean as text; None for anything else.
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Short description of a volume entry, for messages.
+fn describe(volume: &Value) -> String {
+    match volume {
+        Value::Mapping(_) => volume
+            .get("source")
+            .and_then(scalar)
+            .unwrap_or_else(|| "(unnamed)".to_string()),
+        value => scalar(value).unwrap_or_else(|| "(unknown)".to_string()),
+    }
+}
+
+/// Interpolate every string in a document, collecting variables that aren't set.
+fn interpolate_all(
+    value: &mut Value,
+    vars: &HashMap<String, String>,
+    unset: &mut BTreeSet<String>,
+) {
+    match value {
+        Value::String(s) => *s = interpolate(s, vars, unset),
+        Value::Sequence(items) => {
+            for item in items {
+                interpolate_all(item, vars, unset);
+            }
+        }
+        Value::Mapping(entries) => {
+            for (_, item) in entries.iter_mut() {
+                interpolate_all(item, vars, unset);
+            }
+        }
+        Value::Tagged(tagged) => interpolate_all(&mut tagged.value, vars, unset),
+        _ => {}
+    }
+}
+
+/// Expand `$$`, `$VAR`, `${VAR}`, `${VAR:-default}` and `${VAR-default}` in a
+/// compose value. `:-` also applies the default to empty variables.
+fn interpolate(s: &str, vars: &HashMap<String, String>, unset: &mut BTreeSet<String>) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let tail = &rest[pos + 1..];
+
+        if let Some(after) = tail.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+        } else if let Some(braced) = tail.strip_prefix('{') {
+            let Some(end) = braced.find('}') else {
+                out.push_str(&rest[pos..]);
+                return out;
+            };
+            let expr = &braced[..end];
+            let (name, default, empty_is_unset) = match expr.split_once(":-") {
+                Some((name, default)) => (name, Some(default), true),
+                None => match expr.split_once('-') {
+                    Some((name, default)) => (name, Some(default), false),
+                    None => (expr, None, false),
+                },
+            };
+            let value = vars.get(name).filter(|v| !(empty_is_unset && v.is_empty()));
+            match (value, default) {
+                (Some(value), _) => out.push_str(value),
+                (None, Some(default)) => out.push_str(default),
+                (None, None) => {
+                    unset.insert(name.to_string());
+                }
+            }
+            rest = &braced[end + 1..];
+        } else {
+            let len = tail
+                .char_indices()
+                .find(|(i, c)| {
+                    !(c.is_ascii_alphanumeric() || *c == '_') || (*i == 0 && c.is_ascii_digit())
+                })
+                .map(|(i, _)| i)
+                .unwrap_or(tail.len());
+            if len == 0 {
+                out.push('$');
+            } else {
+                match vars.get(&tail[..len]) {
+                    Some(value) => out.push_str(value),
+                    None => {
+                        unset.insert(tail[..len].to_string());
+                    }
+                }
+            }
+            rest = &tail[len..];
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Whether a volume source is a host path rather than a volume name.
+fn is_host_path(source: &str) -> bool {
+    source.starts_with(['.', '/', '~', '$'])
+}
+
+/// Split a command string into arguments on whitespace, honouring quotes.
+fn split_command(cmd: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut in_arg = false;
+    for c in cmd.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ComposeError {
+    #[error("Failed to read {path}: {err}", path = .0.display(), err = .1)]
+    Read(PathBuf, std::io::Error),
+
+    #[error("Failed to parse {path}: {err}", path = .0.display(), err = .1)]
+    Parse(PathBuf, serde_norway::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPOSE: &str = r#"
+version: "3.8"
+
+services:
+  postgres:
+    image: postgres:16   # pinned
+    environment:
+      POSTGRES_USER: app
+      POSTGRES_PASSWORD: "dev # not a comment"
+    ports:
+      - "5432:5432"
+    volumes:
+      - pgdata:/var/lib/postgresql/data
+      - ./init.sql:/docker-entrypoint-initdb.d/init.sql:ro
+
+  redis:
+    image: 'redis:7-alpine'
+    command: redis-server --appendonly yes --requirepass "s3cret pass"
+    environment:
+    - REDIS_ARGS=--save 60 1
+    volumes:
+      - type: volume
+        source: redisdata
+        target: /data
+
+  web:
+    build: .
+    depends_on: [postgres, redis]
+
+volumes:
+  pgdata: {}
+  redisdata:
+"#;
+
+    fn no_vars() -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    #[test]
+    fn imports_services_with_images() {
+        let import = parse(COMPOSE, &no_vars()).unwrap();
+        let names: Vec<_> = import.services.keys().collect();
+        assert_eq!(names, vec!["postgres", "redis"]);
+
+        let pg = &import.services["postgres"];
+        assert_eq!(pg.image, "postgres:16");
+        assert_eq!(pg.env["POSTGRES_USER"], "app");
+        assert_eq!(pg.env["POSTGRES_PASSWORD"], "dev # not a comment");
+        assert_eq!(pg.volumes, vec!["/var/lib/postgresql/data"]);
+        assert!(pg.command.is_empty());
+
+        let redis = &import.services["redis"];
+        assert_eq!(redis.image, "redis:7-alpine");
+        assert_eq!(
+            redis.command,
+            vec![
+                "redis-server",
+                "--appendonly",
+                "yes",
+                "--requirepass",
+                "s3cret pass"
+            ]
+        );
+        assert_eq!(redis.env["REDIS_ARGS"], "--save 60 1");
+        assert_eq!(redis.volumes, vec!["/data"]);
+    }
+
+    #[test]
+    fn reports_what_was_skipped() {
+        let import = parse(COMPOSE, &no_vars()).unwrap();
+        assert_eq!(import.skipped.len(), 2);
+        assert!(import.skipped[0].contains("init.sql"));
+        assert!(import.skipped[1].contains("'web'"));
+    }
+
+    #[test]
+    fn resolves_anchors_and_merge_keys() {
+        let compose = r#"
+x-common: &common
+  image: postgres:16
+  environment: &env
+    POSTGRES_PASSWORD: dev
+    PGPORT: 5432
+
+services:
+  db:
+    <<: *common
+  replica:
+    <<: *common
+    image: postgres:17
+    environment:
+      <<: *env
+      REPLICA: true
+"#;
+        let import = parse(compose, &no_vars()).unwrap();
+        assert_eq!(import.services["db"].image, "postgres:16");
+        assert_eq!(import.services["db"].env["PGPORT"], "5432");
+        let replica = &import.services["replica"];
+        assert_eq!(replica.image, "postgres:17");
+        assert_eq!(replica.env["POSTGRES_PASSWORD"], "dev");
+        assert_eq!(replica.env["REPLICA"], "true");
+    }
+
+    #[test]
+    fn interpolates_variables() {
+        let compose = r#"
+services:
+  db:
+    image: postgres:${PG_VERSION:-16}
+    environment:
+      - POSTGRES_PASSWORD
+      - POSTGRES_DB=${DB_NAME}
+      - PRICE=$$5
+      - MISSING_BARE
+    command: ["postgres", "-c", "max_connections=$MAX"]
+"#;
+        let vars = HashMap::from([
+            ("POSTGRES_PASSWORD".to_string(), "s3cret".to_string()),
+            ("DB_NAME".to_string(), "app".to_string()),
+        ]);
+        let import = parse(compose, &vars).unwrap();
+        let db = &import.services["db"];
+        assert_eq!(db.image, "postgres:16");
+        assert_eq!(db.env["POSTGRES_PASSWORD"], "s3cret");
+        assert_eq!(db.env["POSTGRES_DB"], "app");
+        assert_eq!(db.env["PRICE"], "$5");
+        assert!(!db.env.contains_key("MISSING_BARE"));
+        assert_eq!(db.command[2], "max_connections=");
+        assert_eq!(
+            import.skipped,
+            vec![
+                "variable MAX is not set; using an empty string",
+                "service 'db': MISSING_BARE is not set",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        let err = parse("services:\n  db:\n    image: x\n   bad: y\n", &no_vars()).unwrap_err();
+        assert!(err.location().is_some());
+        assert!(parse("", &no_vars()).unwrap().services.is_empty());
+    }
+}
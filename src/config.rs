@@ -1,17 +1,397 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 const HINTS_FILENAME: &str = ".dual.toml";
 const DEFAULT_IMAGE: &str = "node:20";
 
+/// How a branch workspace receives a shared file.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SharedMode {
+    /// Independent copy in the clone, kept in step by `dual sync`.
+    #[default]
+    Copy,
+    /// Symlink into ~/.dual/shared/{repo}, like the main workspace.
+    Symlink,
+    /// Read-only bind mount into the container at /workspace/<file>.
+    Mount,
+}
+
 /// Shared configuration file propagation settings.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
 pub struct SharedConfig {
     /// Files and directories to share across workspaces.
     /// e.g. [".vercel", ".env.local", ".env"]
     #[serde(default)]
     pub files: Vec<String>,
+
+    /// Default mode for branch workspaces (default: copy)
+    #[serde(default)]
+    pub mode: SharedMode,
+
+    /// Per-file overrides of `mode`, e.g. { ".env.local" = "mount" }
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub modes: HashMap<String, SharedMode>,
+
+    /// Encrypt the shared store at rest with the key in ~/.dual/secrets.key.
+    /// Workspaces always receive decrypted copies (`mode` is ignored).
+    #[serde(default)]
+    pub encrypted: bool,
+}
+
+impl SharedConfig {
+    /// The mode a file is shared with in branch workspaces.
+    pub fn mode_for(&self, file: &str) -> SharedMode {
+        self.modes.get(file).copied().unwrap_or(self.mode)
+    }
+
+    /// Entries shared with the given mode, in list order, plus every
+    /// `!` exclusion (exclusions apply to all modes).
+    pub fn files_with_mode(&self, mode: SharedMode) -> Vec<String> {
+        self.files
+            .iter()
+            .filter(|f| f.starts_with('!') || self.mode_for(f) == mode)
+            .cloned()
+            .collect()
+    }
+
+    /// Whether any (non-exclusion) entry uses the given mode.
+    pub fn uses_mode(&self, mode: SharedMode) -> bool {
+        self.files
+            .iter()
+            .any(|f| !f.starts_with('!') && self.mode_for(f) == mode)
+    }
+}
+
+/// A package-manager cache mounted into every container of a repo.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum CacheEntry {
+    /// A preset: "pnpm", "npm", "pip", "uv", "cargo" or "go".
+    Preset(String),
+    /// Any other cache directory, with env vars that point tools at it.
+    Custom {
+        name: String,
+        path: String,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        env: HashMap<String, String>,
+    },
+}
+
+/// Which containers share a cache volume.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheScope {
+    /// One volume per cache for every repo (dual-cache-{name}).
+    #[default]
+    Global,
+    /// One volume per cache and repo (dual-cache-{repo}-{name}).
+    Repo,
+}
+
+impl CacheScope {
+    fn is_global(&self) -> bool {
+        *self == CacheScope::Global
+    }
+}
+
+/// Container resource limits. Unset fields fall back to the global defaults
+/// in ~/.dual/workspaces.toml, and from there to Docker's (unlimited).
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Resources {
+    /// CPUs the container may use, e.g. 2 or 1.5 (`--cpus`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<f64>,
+
+    /// Memory limit, e.g. "4g" (`--memory`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+
+    /// Memory plus swap limit, e.g. "6g", or "-1" for unlimited swap (`--memory-swap`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_swap: Option<String>,
+
+    /// Maximum number of processes (`--pids-limit`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pids_limit: Option<i64>,
+
+    /// Size of /dev/shm, e.g. "1g" for headless browsers (`--shm-size`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shm_size: Option<String>,
+}
+
+impl Resources {
+    /// These limits, with unset fields taken from `defaults`.
+    pub fn or(&self, defaults: &Resources) -> Resources {
+        Resources {
+            cpus: self.cpus.or(defaults.cpus),
+            memory: self.memory.clone().or_else(|| defaults.memory.clone()),
+            memory_swap: self
+                .memory_swap
+                .clone()
+                .or_else(|| defaults.memory_swap.clone()),
+            pids_limit: self.pids_limit.or(defaults.pids_limit),
+            shm_size: self.shm_size.clone().or_else(|| defaults.shm_size.clone()),
+        }
+    }
+
+    /// Whether no limit is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Resources::default()
+    }
+}
+
+/// A named setup step, run in the container on launch.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct SetupStep {
+    /// Step name, used for its status and log file
+    pub name: String,
+
+    /// Shell command to run in /workspace
+    pub run: String,
+
+    /// Workspace files whose changes re-run the step (e.g. ["pnpm-lock.yaml"])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub watch: Vec<String>,
+}
+
+/// Where a hook command runs.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum HookTarget {
+    /// On the host, in the workspace directory. Only runs for repos listed in
+    /// `host_hooks` in ~/.dual/workspaces.toml.
+    Host,
+    /// In the workspace's container, in /workspace.
+    #[default]
+    Container,
+}
+
+/// What a failing hook does to the operation that triggered it.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum HookFailure {
+    /// Log a warning and carry on.
+    #[default]
+    Warn,
+    /// Stop the operation (e.g. cancel `dual destroy`).
+    Abort,
+}
+
+/// A lifecycle hook command.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Hook {
+    /// Shell command to run
+    pub run: String,
+
+    /// Where to run it (default: container)
+    #[serde(default)]
+    pub target: HookTarget,
+
+    /// Seconds before the command is killed (default: 300)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+
+    /// What happens when it fails or times out (default: warn)
+    #[serde(default)]
+    pub on_failure: HookFailure,
+}
+
+/// Lifecycle hooks, each a list of commands run in order.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Hooks {
+    /// After a new clone is created and shared files are copied in
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_clone: Vec<Hook>,
+
+    /// After a new container is created and its setup steps have run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_create: Vec<Hook>,
+
+    /// Before `dual launch` creates or starts the container
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_launch: Vec<Hook>,
+
+    /// After the tmux session is ready, before attaching
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_launch: Vec<Hook>,
+
+    /// Before a running container is stopped (by `dual destroy` or `dual recreate`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_stop: Vec<Hook>,
+
+    /// Before `dual destroy` tears anything down
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_destroy: Vec<Hook>,
+
+    /// After `dual sync` pulls shared files into the workspace
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_sync: Vec<Hook>,
+}
+
+impl Hooks {
+    /// Whether no hook is configured.
+    pub fn is_empty(&self) -> bool {
+        *self == Hooks::default()
+    }
+}
+
+/// A sidecar service (e.g. postgres) run next to the workspace container and
+/// sharing its network, so it is reachable on `localhost` inside the workspace.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ServiceConfig {
+    /// Docker image (e.g. "postgres:16")
+    pub image: String,
+
+    /// Environment variables for the service
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+
+    /// Command overriding the image's default
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+
+    /// Paths in the service kept in per-workspace volumes (e.g. its data directory)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volumes: Vec<String>,
+}
+
+/// A port served through the proxy, as a bare port or
+/// `{ port, name, path, open, http2, health }`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(untagged)]
+pub enum PortEntry {
+    Port(u16),
+    Service {
+        port: u16,
+        /// Routes `{name}.{workspace}.localhost` and labels the port in `dual urls`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        /// Path appended to the URL `dual urls` and `dual open` show (default: "/")
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+        /// Whether `dual open` opens it without `--service` (default: true)
+        #[serde(default = "default_open", skip_serializing_if = "is_true")]
+        open: bool,
+        /// Whether the container speaks HTTP/2 without TLS (h2c), e.g. gRPC
+        #[serde(default, skip_serializing_if = "is_false")]
+        http2: bool,
+        /// HTTP readiness check (default: the port accepts connections)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        health: Option<HealthCheck>,
+    },
+}
+
+/// An HTTP readiness check for a port: `{ path, status }`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct HealthCheck {
+    /// Path requested inside the container network, e.g. "/healthz"
+    pub path: String,
+    /// Expected status (default: any 2xx or 3xx)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+}
+
+fn default_open() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl PortEntry {
+    /// The port inside the container.
+    pub fn port(&self) -> u16 {
+        match self {
+            PortEntry::Port(port) | PortEntry::Service { port, .. } => *port,
+        }
+    }
+
+    /// The service name, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            PortEntry::Port(_) => None,
+            PortEntry::Service { name, .. } => name.as_deref(),
+        }
+    }
+
+    /// The URL path, always starting with '/'.
+    pub fn path(&self) -> String {
+        match self {
+            PortEntry::Service {
+                path: Some(path), ..
+            } if !path.is_empty() => {
+                if path.starts_with('/') {
+                    path.clone()
+                } else {
+                    format!("/{path}")
+                }
+            }
+            _ => "/".to_string(),
+        }
+    }
+
+    /// Whether `dual open` opens it by default.
+    pub fn open(&self) -> bool {
+        match self {
+            PortEntry::Port(_) => true,
+            PortEntry::Service { open, .. } => *open,
+        }
+    }
+
+    /// Whether the proxy talks HTTP/2 to the container.
+    pub fn http2(&self) -> bool {
+        match self {
+            PortEntry::Port(_) => false,
+            PortEntry::Service { http2, .. } => *http2,
+        }
+    }
+
+    /// The HTTP readiness check, if one is configured.
+    pub fn health(&self) -> Option<&HealthCheck> {
+        match self {
+            PortEntry::Port(_) => None,
+            PortEntry::Service { health, .. } => health.as_ref(),
+        }
+    }
+}
+
+impl From<u16> for PortEntry {
+    fn from(port: u16) -> Self {
+        PortEntry::Port(port)
+    }
+}
+
+/// A raw TCP port published on the host (e.g. a database), as a bare port
+/// or `{ port, name }`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(untagged)]
+pub enum TcpPort {
+    Port(u16),
+    Named { port: u16, name: String },
+}
+
+impl TcpPort {
+    /// The port inside the container.
+    pub fn port(&self) -> u16 {
+        match self {
+            TcpPort::Port(port) | TcpPort::Named { port, .. } => *port,
+        }
+    }
+
+    /// The label shown by `dual urls`: its name, or the port number.
+    pub fn name(&self) -> String {
+        match self {
+            TcpPort::Port(port) => port.to_string(),
+            TcpPort::Named { name, .. } => name.clone(),
+        }
+    }
 }
 
 /// Per-repo runtime hints, read from .dual.toml in a workspace directory.
@@ -21,30 +401,130 @@ pub struct RepoHints {
     #[serde(default = "default_image")]
     pub image: String,
 
-    /// Ports that services bind to inside the container
+    /// Ports that services bind to inside the container, optionally named
     #[serde(default)]
-    pub ports: Vec<u16>,
+    pub ports: Vec<PortEntry>,
+
+    /// Non-HTTP ports forwarded from a stable host port on 127.0.0.1
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tcp_ports: Vec<TcpPort>,
 
     /// Setup command to run after container creation (e.g. "pnpm install")
     pub setup: Option<String>,
 
+    /// Named setup steps, run in order after `setup`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub setup_steps: Vec<SetupStep>,
+
+    /// Dev server command, started in the background when the proxy wakes the workspace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dev: Option<String>,
+
     /// Environment variables for the container
     #[serde(default)]
     pub env: HashMap<String, String>,
 
+    /// Dotenv files loaded into the container environment, in order
+    /// (e.g. [".env", ".env.local"]). Read from the workspace, falling back
+    /// to the shared store.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_files: Vec<String>,
+
     /// Additional commands to route to the container (merged with defaults)
     #[serde(default)]
     pub extra_commands: Vec<String>,
 
-    /// Directories to isolate with anonymous Docker volumes
+    /// Directories to isolate with per-workspace Docker volumes
     #[serde(default = "default_anonymous_volumes")]
     pub anonymous_volumes: Vec<String>,
 
+    /// Package-manager caches shared across containers (e.g. ["pnpm"])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub caches: Vec<CacheEntry>,
+
+    /// Whether cache volumes are shared by all repos or per repo (default: global)
+    #[serde(default, skip_serializing_if = "CacheScope::is_global")]
+    pub cache_scope: CacheScope,
+
+    /// Container resource limits
+    #[serde(default, skip_serializing_if = "Resources::is_empty")]
+    pub resources: Resources,
+
+    /// Lifecycle hooks
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+
+    /// Compose file to import sidecar services from (e.g. "docker-compose.yml")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compose_file: Option<String>,
+
+    /// Sidecar services, by name; these override compose services of the same name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub services: BTreeMap<String, ServiceConfig>,
+
     /// Shared files to propagate across workspaces
     #[serde(skip_serializing_if = "Option::is_none")]
     pub shared: Option<SharedConfig>,
 }
 
+impl RepoHints {
+    /// All setup steps: `setup` (as a step named "setup"), then `setup_steps`.
+    pub fn setup_plan(&self) -> Vec<SetupStep> {
+        let command = self
+            .setup
+            .as_ref()
+            .filter(|cmd| !cmd.trim().is_empty())
+            .map(|cmd| SetupStep {
+                name: "setup".to_string(),
+                run: cmd.clone(),
+                watch: Vec::new(),
+            });
+        command
+            .into_iter()
+            .chain(self.setup_steps.iter().cloned())
+            .collect()
+    }
+
+    /// Check what the TOML types can't: setup step names must be non-empty
+    /// and unique, and "setup" is taken by the `setup` command's step; port
+    /// names become `{name}.{workspace}.localhost`, so they must be lowercase
+    /// DNS labels (and not numbers, which address ports).
+    fn validate(&self) -> Result<(), String> {
+        for name in self.ports.iter().filter_map(PortEntry::name) {
+            let valid = !name.is_empty()
+                && name.len() <= 63
+                && !name.starts_with('-')
+                && !name.ends_with('-')
+                && !name.bytes().all(|b| b.is_ascii_digit())
+                && name
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+            if !valid {
+                return Err(format!(
+                    "ports: name \"{name}\" must be a lowercase DNS label (a-z, 0-9 and -)"
+                ));
+            }
+        }
+
+        let mut names = HashSet::new();
+        for step in &self.setup_steps {
+            if step.name.trim().is_empty() {
+                return Err("setup_steps: a step has an empty name".to_string());
+            }
+            if step.name == "setup" {
+                return Err("setup_steps: the name \"setup\" is reserved for `setup`".to_string());
+            }
+            if !names.insert(step.name.as_str()) {
+                return Err(format!(
+                    "setup_steps: duplicate step name \"{}\"",
+                    step.name
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
 fn default_image() -> String {
     DEFAULT_IMAGE.to_string()
 }
@@ -58,10 +538,20 @@ impl Default for RepoHints {
         Self {
             image: DEFAULT_IMAGE.to_string(),
             ports: Vec::new(),
+            tcp_ports: Vec::new(),
             setup: None,
+            setup_steps: Vec::new(),
+            dev: None,
             env: HashMap::new(),
+            env_files: Vec::new(),
             extra_commands: Vec::new(),
             anonymous_volumes: default_anonymous_volumes(),
+            caches: Vec::new(),
+            cache_scope: CacheScope::Global,
+            resources: Resources::default(),
+            hooks: Hooks::default(),
+            compose_file: None,
+            services: BTreeMap::new(),
             shared: None,
         }
     }
@@ -72,6 +562,31 @@ pub fn shared_dir(repo: &str) -> Option<PathBuf> {
     dirs::home_dir().map(|home| home.join(".dual").join("shared").join(repo))
 }
 
+/// Get the archive directory for destroyed workspaces: ~/.dual/archive/
+pub fn archive_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".dual").join("archive"))
+}
+
+/// Get the directory for encrypted secret variables: ~/.dual/secrets/
+pub fn secrets_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".dual").join("secrets"))
+}
+
+/// Get the path of the key protecting secrets and encrypted shared stores: ~/.dual/secrets.key
+pub fn secrets_key_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".dual").join("secrets.key"))
+}
+
+/// Get the directory for per-workspace logs: ~/.dual/logs/
+pub fn logs_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".dual").join("logs"))
+}
+
+/// Get the directory for the proxy's local CA and certificates: ~/.dual/certs/
+pub fn certs_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".dual").join("certs"))
+}
+
 /// Load RepoHints from a workspace directory's .dual.toml.
 /// Returns default hints if the file doesn't exist.
 pub fn load_hints(workspace_dir: &Path) -> Result<RepoHints, HintsError> {
@@ -84,7 +599,8 @@ pub fn load_hints(workspace_dir: &Path) -> Result<RepoHints, HintsError> {
     let contents =
         std::fs::read_to_string(&path).map_err(|e| HintsError::ReadError(path.clone(), e))?;
     let hints: RepoHints =
-        toml::from_str(&contents).map_err(|e| HintsError::ParseError(path, e))?;
+        toml::from_str(&contents).map_err(|e| HintsError::ParseError(path.clone(), e))?;
+    hints.validate().map_err(|e| HintsError::Invalid(path, e))?;
     Ok(hints)
 }
 
@@ -105,14 +621,35 @@ pub fn write_default_hints(repo_root: &Path) -> Result<(), HintsError> {
 # Docker image for the container runtime
 image = "node:20"
 
-# Ports your dev server uses (for reverse proxy routing)
-# Example: ports = [3000, 3001]
+# Ports your dev server uses (for reverse proxy routing). A table names the
+# port, routing {name}.{workspace}.localhost; `open = false` leaves it out of `dual open`
+# Example: ports = [3000, { port = 3001, name = "app", path = "/dashboard" }]
+# `http2 = true` proxies to a port serving cleartext HTTP/2 (h2c), e.g. a gRPC server.
+# A port is ready once it accepts connections, or with `health = { path = "/healthz" }`
+# once that path answers 2xx/3xx (or `status`); see `dual wait`
 # ports = []
 
+# Non-HTTP ports (databases, gRPC, debuggers), each forwarded by `dual proxy`
+# from a host port on 127.0.0.1 that stays the same for the workspace; see `dual urls`
+# Example: tcp_ports = [9229, { port = 5432, name = "postgres" }]
+# tcp_ports = []
+
 # Shell command to run after container creation (e.g., dependency install)
 # Example: setup = "pnpm install"
 # setup = ""
 
+# Named setup steps, run in order after `setup`. On launch, only steps that
+# failed or changed (command or watched files) run again; see `dual setup`.
+# [[setup_steps]]
+# name = "install"
+# run = "pnpm install"
+# watch = ["pnpm-lock.yaml"]
+
+# Dev server command, started in the background when `dual proxy` wakes a
+# stopped workspace (with `wake = true` under [proxy] in ~/.dual/workspaces.toml)
+# Example: dev = "pnpm dev"
+# dev = ""
+
 # Environment variables passed to the container
 # Example:
 # [env]
@@ -123,11 +660,38 @@ image = "node:20"
 # Example: extra_commands = ["cargo", "go", "ruby"]
 # extra_commands = []
 
-# Directories to isolate with anonymous Docker volumes
+# Directories to isolate with per-workspace Docker volumes
 # These directories get their own volume so they don't sync between host/container
 # Example: anonymous_volumes = ["node_modules", ".next", "target"]
 # anonymous_volumes = ["node_modules"]
 
+# Package-manager caches shared across containers (pnpm, npm, pip, uv, cargo, go)
+# caches = ["pnpm"]
+# cache_scope = "global"  # or "repo" for one cache per repo
+
+# Container resource limits (defaults can be set in ~/.dual/workspaces.toml)
+# [resources]
+# cpus = 2
+# memory = "4g"
+# shm_size = "1g"
+
+# Lifecycle hooks: post_clone, post_create, pre_launch, post_launch, pre_stop,
+# pre_destroy, post_sync. Each runs in the container (default, skipped while it
+# isn't running) or, for repos listed in host_hooks in ~/.dual/workspaces.toml,
+# on the host with target = "host". DUAL_* workspace variables are set;
+# on_failure is "warn" (default) or "abort".
+# [hooks]
+# post_create = [{ run = "pnpm db:seed" }]
+# pre_destroy = [{ run = "pg_dump app > dump.sql", timeout = 120, on_failure = "abort" }]
+
+# Sidecar services sharing the container's network (reachable on localhost),
+# imported from a compose file and/or declared here
+# compose_file = "docker-compose.yml"
+# [services.postgres]
+# image = "postgres:16"
+# env = { POSTGRES_PASSWORD = "dev" }
+# volumes = ["/var/lib/postgresql/data"]
+
 # Files to share across all workspaces of this repo
 # These are gitignored files that should be available in every branch workspace
 # [shared]
@@ -142,13 +706,49 @@ image = "node:20"
 pub fn parse_hints(toml_str: &str) -> Result<RepoHints, HintsError> {
     let hints: RepoHints = toml::from_str(toml_str)
         .map_err(|e| HintsError::ParseError(PathBuf::from("<string>"), e))?;
+    hints
+        .validate()
+        .map_err(|e| HintsError::Invalid(PathBuf::from("<string>"), e))?;
     Ok(hints)
 }
 
+/// Prefix shared by container and tmux session names.
+pub const NAME_PREFIX: &str = "dual-";
+
+/// Longest workspace id whose container name (`dual-{id}`) still fits in a
+/// single DNS label / Docker's 63-character hostname limit.
+const MAX_ID_LEN: usize = 63 - NAME_PREFIX.len();
+
+/// Hex digits of SHA-256 used to tag non-canonical or truncated names.
+const TAG_LEN: usize = 8;
+
 /// Compute the workspace identifier from repo + branch.
-/// e.g. ("lightfast", "feat/auth") → "lightfast-feat__auth"
+/// e.g. ("lightfast", "fix-auth") → "lightfast-fix-auth",
+/// ("agent-os", "main") → "agent-os---main"
+///
+/// The boundary between repo and branch is unambiguous: a repo whose encoding
+/// has no `-` is followed by a single `-` (the first one in the id), and any
+/// other repo by `---`, which no encoded name contains. So ("a-b", "c") and
+/// ("a", "b-c") get different ids.
+///
+/// The id is a valid DNS label of at most 58 characters, so `dual-{id}` and
+/// `{id}.localhost` are always valid. Ids that would be longer are truncated
+/// and tagged with a hash of the full repo + branch.
 pub fn workspace_id(repo: &str, branch: &str) -> String {
-    format!("{}-{}", repo, encode_branch(branch))
+    let encoded_repo = encode_name(repo);
+    let separator = if encoded_repo.contains('-') {
+        "---"
+    } else {
+        "-"
+    };
+    let id = format!("{encoded_repo}{separator}{}", encode_branch(branch));
+    if id.len() <= MAX_ID_LEN {
+        return id;
+    }
+
+    let keep = MAX_ID_LEN - TAG_LEN - 2;
+    let head = id[..keep].trim_end_matches('-');
+    format!("{head}--{}", name_tag(&format!("{repo}/{branch}")))
 }
 
 /// Get the workspace directory for a repo + branch combination.
@@ -158,9 +758,15 @@ pub fn workspace_dir(workspace_root: &Path, repo: &str, branch: &str) -> PathBuf
 }
 
 /// Compute the container name for a repo + branch combination.
-/// Pattern: dual-{repo}-{encoded_branch}
+/// Pattern: dual-{workspace_id}
 pub fn container_name(repo: &str, branch: &str) -> String {
-    format!("dual-{}-{}", repo, encode_branch(branch))
+    format!("{NAME_PREFIX}{}", workspace_id(repo, branch))
+}
+
+/// Compute the Docker network name for a repo + branch combination.
+/// Pattern: dual-net-{workspace_id}
+pub fn network_name(repo: &str, branch: &str) -> String {
+    format!("{NAME_PREFIX}net-{}", workspace_id(repo, branch))
 }
 
 /// Compute the tmux session name for a repo + branch combination.
@@ -169,18 +775,106 @@ pub fn session_name(repo: &str, branch: &str) -> String {
     container_name(repo, branch)
 }
 
-/// Encode a branch name for filesystem use.
-/// Replaces `/` with `__` (double underscore).
-/// e.g. "feat/auth" → "feat__auth"
+/// Encode a branch name for use in directories, container/session names and DNS labels.
+///
+/// Scheme:
+/// - A branch that is already canonical — only `[a-z0-9-]`, not starting or
+///   ending with `-`, and without `--` — encodes to itself ("main", "fix-leak").
+/// - Anything else is slugged (lowercased, every other character replaced by
+///   `-`, runs of `-` collapsed, ends trimmed) and tagged with `--` plus the
+///   first 8 hex digits of the SHA-256 of the original name
+///   ("feat/auth" → "feat-auth--…").
+///
+/// Canonical names never contain `--`, so they can't collide with tagged ones,
+/// and two tagged names collide only if their hashes do. No encoded name
+/// contains `---` (see [`workspace_id`]).
+///
+/// Unlike the old `/` → `__` scheme this is not reversible, and there is no
+/// `decode_branch` any more: the original repo and branch always come from
+/// `WorkspaceState` (ids are resolved with `WorkspaceState::resolve_workspace`)
+/// or from a container's `dev.dual.repo` / `dev.dual.branch` labels.
 pub fn encode_branch(branch: &str) -> String {
+    encode_name(branch)
+}
+
+/// Encode a repo or branch name with the scheme described on [`encode_branch`].
+fn encode_name(name: &str) -> String {
+    if is_canonical(name) {
+        return name.to_string();
+    }
+
+    let mut slug = String::with_capacity(name.len());
+    for c in name.chars() {
+        let c = c.to_ascii_lowercase();
+        if c.is_ascii_lowercase() || c.is_ascii_digit() {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    let tag = name_tag(name);
+
+    if slug.is_empty() {
+        tag
+    } else {
+        format!("{slug}--{tag}")
+    }
+}
+
+/// True if a name is already a valid, untagged DNS label fragment.
+fn is_canonical(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+        && !name.contains("--")
+}
+
+/// Short, stable hash of a name (first 8 hex digits of SHA-256).
+pub fn name_tag(name: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(name.as_bytes());
+    digest
+        .iter()
+        .take(TAG_LEN / 2)
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Branch encoding used before identifiers became DNS-safe: `/` → `__`.
+/// Only used to find workspaces created by older versions (see `dual migrate`).
+pub fn legacy_encode_branch(branch: &str) -> String {
     branch.replace('/', "__")
 }
 
-/// Decode an encoded branch name back to the original.
-/// Replaces `__` with `/`.
-/// e.g. "feat__auth" → "feat/auth"
-pub fn decode_branch(encoded: &str) -> String {
-    encoded.replace("__", "/")
+/// Workspace directory under the legacy encoding.
+pub fn legacy_workspace_dir(workspace_root: &Path, repo: &str, branch: &str) -> PathBuf {
+    workspace_root.join(repo).join(legacy_encode_branch(branch))
+}
+
+/// Container (and tmux session) name under the legacy encoding.
+pub fn legacy_container_name(repo: &str, branch: &str) -> String {
+    format!("{NAME_PREFIX}{repo}-{}", legacy_encode_branch(branch))
+}
+
+/// Container name and workspace directory a workspace had under the legacy
+/// scheme, or None if its container name hasn't changed since.
+///
+/// Besides branches with `/`, this covers every workspace of a repo whose name
+/// contains `-`: the legacy `dual-agent-os-main` is now `dual-agent-os---main`.
+pub fn legacy_workspace(
+    workspace_root: &Path,
+    repo: &str,
+    branch: &str,
+) -> Option<(String, PathBuf)> {
+    let old_name = legacy_container_name(repo, branch);
+    if old_name == container_name(repo, branch) {
+        return None;
+    }
+    Some((old_name, legacy_workspace_dir(workspace_root, repo, branch)))
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -196,6 +890,9 @@ pub enum HintsError {
 
     #[error("Failed to serialize hints: {0}")]
     SerializeError(toml::ser::Error),
+
+    #[error("Invalid {path}: {err}", path = .0.display(), err = .1)]
+    Invalid(PathBuf, String),
 }
 
 #[cfg(test)]
@@ -203,16 +900,125 @@ mod tests {
     use super::*;
 
     #[test]
-    fn encode_branch_with_slash() {
-        assert_eq!(encode_branch("feat/auth"), "feat__auth");
-        assert_eq!(encode_branch("fix/memory-leak"), "fix__memory-leak");
+    fn encode_branch_canonical_names_unchanged() {
         assert_eq!(encode_branch("main"), "main");
+        assert_eq!(encode_branch("fix-memory-leak"), "fix-memory-leak");
+        assert_eq!(encode_branch("v2-rewrite"), "v2-rewrite");
+    }
+
+    #[test]
+    fn encode_branch_tags_non_canonical_names() {
+        assert_eq!(encode_branch("feat/auth"), "feat-auth--cc945ab1");
+        let encoded = encode_branch("Feat/Auth@2+x#1");
+        assert!(encoded.starts_with("feat-auth-2-x-1--"));
+        assert_eq!(encoded.len(), "feat-auth-2-x-1--".len() + TAG_LEN);
     }
 
     #[test]
-    fn decode_branch_roundtrip() {
-        let original = "feat/auth";
-        assert_eq!(decode_branch(&encode_branch(original)), original);
+    fn encode_branch_is_collision_free() {
+        let branches = [
+            "feat/x", "feat__x", "feat-x", "Feat/X", "feat--x", "feat/-x", "feat.x", "_", "-",
+        ];
+        let encoded: std::collections::HashSet<_> =
+            branches.iter().map(|b| encode_branch(b)).collect();
+        assert_eq!(encoded.len(), branches.len());
+    }
+
+    #[test]
+    fn encode_branch_is_dns_safe() {
+        for branch in [
+            "feat/auth",
+            "Release_1.2",
+            "@@",
+            "über/ß",
+            "-lead",
+            "trail-",
+        ] {
+            let encoded = encode_branch(branch);
+            let valid = encoded
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+                && !encoded.starts_with('-')
+                && !encoded.ends_with('-');
+            assert!(valid, "{branch} → {encoded}");
+        }
+    }
+
+    #[test]
+    fn workspace_id_fits_label_limit() {
+        let long_branch = "feature/".repeat(20);
+        let id = workspace_id("lightfast", &long_branch);
+        assert!(id.len() <= MAX_ID_LEN);
+        assert!(container_name("lightfast", &long_branch).len() <= 63);
+        // Different long branches with the same prefix stay distinct
+        let other = format!("{long_branch}x");
+        assert_ne!(id, workspace_id("lightfast", &other));
+    }
+
+    #[test]
+    fn workspace_id_boundary_is_unambiguous() {
+        assert_ne!(workspace_id("a-b", "c"), workspace_id("a", "b-c"));
+        assert_eq!(workspace_id("a-b", "c"), "a-b---c");
+        assert_eq!(workspace_id("a", "b-c"), "a-b-c");
+        assert_ne!(
+            container_name("agent-os", "main"),
+            container_name("agent", "os-main")
+        );
+        // Tagged repo names contain `-` too
+        assert!(workspace_id("MyApp", "main").ends_with("---main"));
+    }
+
+    #[test]
+    fn workspace_id_encodes_repo() {
+        assert_eq!(
+            workspace_id("MyApp", "main"),
+            format!("myapp--{}---main", name_tag("MyApp"))
+        );
+    }
+
+    #[test]
+    fn legacy_names() {
+        assert_eq!(legacy_encode_branch("feat/auth"), "feat__auth");
+        assert_eq!(
+            legacy_container_name("lightfast", "feat/auth"),
+            "dual-lightfast-feat__auth"
+        );
+        assert_eq!(
+            legacy_workspace_dir(Path::new("/tmp/ws"), "lightfast", "feat/auth"),
+            PathBuf::from("/tmp/ws/lightfast/feat__auth")
+        );
+    }
+
+    #[test]
+    fn legacy_workspaces_to_migrate() {
+        let root = Path::new("/tmp/ws");
+        assert_eq!(legacy_workspace(root, "lightfast", "main"), None);
+        assert_eq!(
+            legacy_workspace(root, "lightfast", "feat/auth"),
+            Some((
+                "dual-lightfast-feat__auth".to_string(),
+                PathBuf::from("/tmp/ws/lightfast/feat__auth")
+            ))
+        );
+        // The `---` separator renames every workspace of a dashed repo, but
+        // canonical branches keep their clone directory
+        assert_eq!(
+            legacy_workspace(root, "agent-os", "main"),
+            Some((
+                "dual-agent-os-main".to_string(),
+                workspace_dir(root, "agent-os", "main")
+            ))
+        );
+        assert_eq!(container_name("agent-os", "main"), "dual-agent-os---main");
+    }
+
+    #[test]
+    fn network_name_format() {
+        assert_eq!(network_name("lightfast", "main"), "dual-net-lightfast-main");
+        assert_eq!(
+            network_name("lightfast", "feat/auth"),
+            "dual-net-lightfast-feat-auth--cc945ab1"
+        );
     }
 
     #[test]
@@ -220,14 +1026,14 @@ mod tests {
         assert_eq!(container_name("lightfast", "main"), "dual-lightfast-main");
         assert_eq!(
             container_name("lightfast", "feat/auth"),
-            "dual-lightfast-feat__auth"
+            "dual-lightfast-feat-auth--cc945ab1"
         );
     }
 
     #[test]
     fn workspace_dir_format() {
         let dir = workspace_dir(Path::new("/tmp/ws"), "lightfast", "feat/auth");
-        assert_eq!(dir, PathBuf::from("/tmp/ws/lightfast/feat__auth"));
+        assert_eq!(dir, PathBuf::from("/tmp/ws/lightfast/feat-auth--cc945ab1"));
     }
 
     #[test]
@@ -235,7 +1041,7 @@ mod tests {
         assert_eq!(workspace_id("lightfast", "main"), "lightfast-main");
         assert_eq!(
             workspace_id("lightfast", "feat/auth"),
-            "lightfast-feat__auth"
+            "lightfast-feat-auth--cc945ab1"
         );
     }
 
@@ -269,7 +1075,7 @@ NODE_ENV = "development"
 "#;
         let hints = parse_hints(toml).unwrap();
         assert_eq!(hints.image, "python:3.12");
-        assert_eq!(hints.ports, vec![3000, 3001]);
+        assert_eq!(hints.ports, vec![3000.into(), 3001.into()]);
         assert_eq!(hints.setup.as_deref(), Some("pnpm install"));
         assert_eq!(hints.env.get("NODE_ENV").unwrap(), "development");
     }
@@ -279,10 +1085,55 @@ NODE_ENV = "development"
         let toml = r#"ports = [8080]"#;
         let hints = parse_hints(toml).unwrap();
         assert_eq!(hints.image, "node:20");
-        assert_eq!(hints.ports, vec![8080]);
+        assert_eq!(hints.ports, vec![8080.into()]);
         assert!(hints.setup.is_none());
     }
 
+    #[test]
+    fn parse_hints_port_tables() {
+        let toml = r#"
+ports = [
+    3000,
+    { port = 3001, name = "app", path = "dashboard" },
+    { port = 3002, name = "docs", open = false },
+    { port = 50051, name = "grpc", http2 = true, health = { path = "/healthz", status = 204 } },
+]
+"#;
+        let hints = parse_hints(toml).unwrap();
+        let ports: Vec<u16> = hints.ports.iter().map(PortEntry::port).collect();
+        assert_eq!(ports, vec![3000, 3001, 3002, 50051]);
+
+        assert_eq!(hints.ports[0].name(), None);
+        assert_eq!(hints.ports[0].path(), "/");
+        assert!(hints.ports[0].open());
+        assert_eq!(hints.ports[1].name(), Some("app"));
+        assert_eq!(hints.ports[1].path(), "/dashboard");
+        assert!(hints.ports[1].open());
+        assert_eq!(hints.ports[2].name(), Some("docs"));
+        assert!(!hints.ports[2].open());
+        assert!(!hints.ports[2].http2());
+        assert!(hints.ports[3].http2());
+        assert_eq!(hints.ports[0].health(), None);
+        assert_eq!(
+            hints.ports[3].health(),
+            Some(&HealthCheck {
+                path: "/healthz".to_string(),
+                status: Some(204),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_hints_tcp_ports() {
+        let toml = r#"tcp_ports = [9229, { port = 5432, name = "postgres" }]"#;
+        let hints = parse_hints(toml).unwrap();
+        assert_eq!(hints.tcp_ports.len(), 2);
+        assert_eq!(hints.tcp_ports[0].port(), 9229);
+        assert_eq!(hints.tcp_ports[0].name(), "9229");
+        assert_eq!(hints.tcp_ports[1].port(), 5432);
+        assert_eq!(hints.tcp_ports[1].name(), "postgres");
+    }
+
     #[test]
     fn load_hints_from_missing_file() {
         let hints = load_hints(Path::new("/tmp/dual-test-nonexistent")).unwrap();
@@ -297,11 +1148,72 @@ NODE_ENV = "development"
 
         let hints = RepoHints {
             image: "rust:latest".to_string(),
-            ports: vec![8080, 9090],
+            ports: vec![
+                8080.into(),
+                PortEntry::Service {
+                    port: 9090,
+                    name: Some("api".to_string()),
+                    path: Some("/docs".to_string()),
+                    open: false,
+                    http2: true,
+                    health: Some(HealthCheck {
+                        path: "/health".to_string(),
+                        status: None,
+                    }),
+                },
+            ],
+            tcp_ports: vec![
+                TcpPort::Port(9229),
+                TcpPort::Named {
+                    port: 5432,
+                    name: "postgres".to_string(),
+                },
+            ],
             setup: Some("cargo build".to_string()),
+            setup_steps: vec![SetupStep {
+                name: "migrate".to_string(),
+                run: "cargo run --bin migrate".to_string(),
+                watch: vec!["migrations/schema.sql".to_string()],
+            }],
+            dev: Some("cargo run".to_string()),
             env: HashMap::from([("RUST_LOG".to_string(), "debug".to_string())]),
+            env_files: vec![".env".to_string(), ".env.local".to_string()],
             extra_commands: vec!["cargo".to_string()],
             anonymous_volumes: vec!["node_modules".to_string(), "target".to_string()],
+            caches: vec![
+                CacheEntry::Preset("cargo".to_string()),
+                CacheEntry::Custom {
+                    name: "sccache".to_string(),
+                    path: "/cache/sccache".to_string(),
+                    env: HashMap::from([("SCCACHE_DIR".to_string(), "/cache/sccache".to_string())]),
+                },
+            ],
+            cache_scope: CacheScope::Repo,
+            resources: Resources {
+                cpus: Some(1.5),
+                memory: Some("4g".to_string()),
+                shm_size: Some("1g".to_string()),
+                ..Resources::default()
+            },
+            hooks: Hooks {
+                pre_destroy: vec![Hook {
+                    run: "./scripts/dump-db.sh".to_string(),
+                    target: HookTarget::Container,
+                    timeout: Some(120),
+                    on_failure: HookFailure::Abort,
+                }],
+                ..Hooks::default()
+            },
+            compose_file: Some("docker-compose.yml".to_string()),
+            services: BTreeMap::from([(
+                "redis".to_string(),
+                ServiceConfig {
+                    image: "redis:7".to_string(),
+                    env: HashMap::new(),
+                    command: vec!["redis-server".to_string(), "--save".to_string()],
+                    volumes: vec!["/data".to_string()],
+                },
+            )]),
             shared: None,
         };
 
@@ -312,6 +1224,177 @@ NODE_ENV = "development"
         let _ = std::fs::remove_dir_all(&dir);
     }
 
+    #[test]
+    fn parse_hints_caches() {
+        let toml = r#"
+caches = ["pnpm", { name = "bun", path = "/root/.bun/install/cache" }]
+cache_scope = "repo"
+"#;
+        let hints = parse_hints(toml).unwrap();
+        assert_eq!(hints.caches[0], CacheEntry::Preset("pnpm".to_string()));
+        assert_eq!(
+            hints.caches[1],
+            CacheEntry::Custom {
+                name: "bun".to_string(),
+                path: "/root/.bun/install/cache".to_string(),
+                env: HashMap::new(),
+            }
+        );
+        assert_eq!(hints.cache_scope, CacheScope::Repo);
+
+        let hints = parse_hints("").unwrap();
+        assert!(hints.caches.is_empty());
+        assert_eq!(hints.cache_scope, CacheScope::Global);
+    }
+
+    #[test]
+    fn parse_hints_hooks() {
+        let toml = r#"
+[hooks]
+post_create = [{ run = "pnpm db:seed" }]
+pre_destroy = [
+    { run = "pg_dump app > dump.sql", timeout = 60, on_failure = "abort" },
+    { run = "echo bye", target = "host" },
+]
+"#;
+        let hints = parse_hints(toml).unwrap();
+        let seed = &hints.hooks.post_create[0];
+        assert_eq!(seed.target, HookTarget::Container);
+        assert_eq!(seed.timeout, None);
+        assert_eq!(seed.on_failure, HookFailure::Warn);
+
+        let dump = &hints.hooks.pre_destroy[0];
+        assert_eq!(dump.timeout, Some(60));
+        assert_eq!(dump.on_failure, HookFailure::Abort);
+        assert_eq!(hints.hooks.pre_destroy[1].target, HookTarget::Host);
+        assert!(hints.hooks.post_sync.is_empty());
+
+        assert!(parse_hints("").unwrap().hooks.is_empty());
+    }
+
+    #[test]
+    fn parse_hints_services() {
+        let toml = r#"
+compose_file = "docker-compose.yml"
+
+[services.postgres]
+image = "postgres:16"
+env = { POSTGRES_PASSWORD = "dev" }
+volumes = ["/var/lib/postgresql/data"]
+
+[services.redis]
+image = "redis:7"
+command = ["redis-server", "--appendonly", "yes"]
+"#;
+        let hints = parse_hints(toml).unwrap();
+        assert_eq!(hints.compose_file.as_deref(), Some("docker-compose.yml"));
+        let names: Vec<_> = hints.services.keys().collect();
+        assert_eq!(names, vec!["postgres", "redis"]);
+        let pg = &hints.services["postgres"];
+        assert_eq!(pg.env["POSTGRES_PASSWORD"], "dev");
+        assert!(pg.command.is_empty());
+        assert_eq!(hints.services["redis"].command.len(), 3);
+    }
+
+    #[test]
+    fn parse_hints_setup_steps() {
+        let toml = r#"
+setup = "pnpm install"
+
+[[setup_steps]]
+name = "db"
+run = "pnpm db:migrate"
+watch = ["prisma/schema.prisma"]
+
+[[setup_steps]]
+name = "build"
+run = "pnpm build"
+"#;
+        let hints = parse_hints(toml).unwrap();
+        let plan = hints.setup_plan();
+        let names: Vec<_> = plan.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["setup", "db", "build"]);
+        assert_eq!(plan[0].run, "pnpm install");
+        assert_eq!(plan[1].watch, vec!["prisma/schema.prisma".to_string()]);
+        assert!(plan[2].watch.is_empty());
+
+        // A blank `setup` is not a step
+        let hints = parse_hints("setup = \"\"").unwrap();
+        assert!(hints.setup_plan().is_empty());
+    }
+
+    #[test]
+    fn port_names_are_dns_labels() {
+        let named =
+            |name: &str| parse_hints(&format!("ports = [{{ port = 3000, name = \"{name}\" }}]"));
+        assert!(named("api").is_ok());
+        assert!(named("web-2").is_ok());
+        for bad in ["", "API", "api.v2", "-api", "api_v2", "3001"] {
+            assert!(
+                matches!(named(bad), Err(HintsError::Invalid(..))),
+                "{bad:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn setup_step_names_are_unique() {
+        let steps = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| format!("[[setup_steps]]\nname = \"{name}\"\nrun = \"true\"\n"))
+                .collect::<String>()
+        };
+        assert!(parse_hints(&steps(&["db:migrate", "db_migrate"])).is_ok());
+        assert!(matches!(
+            parse_hints(&steps(&["db", "build", "db"])),
+            Err(HintsError::Invalid(..))
+        ));
+        assert!(matches!(
+            parse_hints(&steps(&["setup"])),
+            Err(HintsError::Invalid(..))
+        ));
+        assert!(matches!(
+            parse_hints(&steps(&[""])),
+            Err(HintsError::Invalid(..))
+        ));
+    }
+
+    #[test]
+    fn parse_hints_resources() {
+        let toml = r#"
+[resources]
+cpus = 2
+memory = "4g"
+pids_limit = 512
+"#;
+        let hints = parse_hints(toml).unwrap();
+        assert_eq!(hints.resources.cpus, Some(2.0));
+        assert_eq!(hints.resources.memory.as_deref(), Some("4g"));
+        assert_eq!(hints.resources.pids_limit, Some(512));
+        assert!(hints.resources.shm_size.is_none());
+        assert!(parse_hints("").unwrap().resources.is_empty());
+    }
+
+    #[test]
+    fn resources_fall_back_to_defaults() {
+        let repo = Resources {
+            cpus: Some(4.0),
+            shm_size: Some("2g".to_string()),
+            ..Resources::default()
+        };
+        let defaults = Resources {
+            cpus: Some(2.0),
+            memory: Some("4g".to_string()),
+            ..Resources::default()
+        };
+        let merged = repo.or(&defaults);
+        assert_eq!(merged.cpus, Some(4.0));
+        assert_eq!(merged.memory.as_deref(), Some("4g"));
+        assert_eq!(merged.shm_size.as_deref(), Some("2g"));
+        assert!(merged.pids_limit.is_none());
+    }
+
     #[test]
     fn write_default_hints_has_comments() {
         let dir = std::env::temp_dir().join("dual-test-default-hints");
@@ -418,11 +1501,11 @@ files = []
         assert_eq!(session_name("lightfast", "main"), "dual-lightfast-main");
         assert_eq!(
             session_name("lightfast", "feat/auth"),
-            "dual-lightfast-feat__auth"
+            "dual-lightfast-feat-auth--cc945ab1"
         );
         assert_eq!(
             session_name("agent-os", "v2-rewrite"),
-            "dual-agent-os-v2-rewrite"
+            "dual-agent-os---v2-rewrite"
         );
     }
 
@@ -443,6 +1526,7 @@ files = []
         let hints = RepoHints {
             shared: Some(SharedConfig {
                 files: vec![".env".to_string()],
+                ..Default::default()
             }),
             ..Default::default()
         };
@@ -450,4 +1534,32 @@ files = []
         assert!(toml_str.contains("[shared]"));
         assert!(toml_str.contains(".env"));
     }
+
+    #[test]
+    fn parse_shared_modes() {
+        let toml_str = r#"
+[shared]
+files = [".vercel", ".env.local", ".env"]
+mode = "symlink"
+
+[shared.modes]
+".env.local" = "mount"
+".env" = "copy"
+"#;
+        let hints: RepoHints = toml::from_str(toml_str).unwrap();
+        let shared = hints.shared.unwrap();
+        assert_eq!(shared.mode_for(".vercel"), SharedMode::Symlink);
+        assert_eq!(shared.mode_for(".env.local"), SharedMode::Mount);
+        assert_eq!(shared.mode_for(".env"), SharedMode::Copy);
+        assert_eq!(
+            shared.files_with_mode(SharedMode::Mount),
+            vec![".env.local"]
+        );
+    }
+
+    #[test]
+    fn shared_mode_defaults_to_copy() {
+        let hints: RepoHints = toml::from_str("[shared]\nfiles = [\".env\"]").unwrap();
+        assert_eq!(hints.shared.unwrap().mode_for(".env"), SharedMode::Copy);
+    }
 }
@@ -1,8 +1,117 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-const WORKSPACE_MOUNT: &str = "/workspace";
+use crate::config::Resources;
+
+/// Where the workspace is mounted inside containers.
+pub const WORKSPACE_MOUNT: &str = "/workspace";
+
+/// Label holding a hash of the arguments a container was created with.
+pub const FINGERPRINT_LABEL: &str = "dev.dual.config_hash";
+
+/// Label with the repo a container belongs to.
+pub const REPO_LABEL: &str = "dev.dual.repo";
+
+/// Label with the branch a container belongs to.
+pub const BRANCH_LABEL: &str = "dev.dual.branch";
+
+/// Label with the workspace id; its presence marks a container as dual-managed.
+pub const WORKSPACE_LABEL: &str = "dev.dual.workspace_id";
+
+/// Label naming the sidecar service a container runs (absent on workspace containers).
+pub const SERVICE_LABEL: &str = "dev.dual.service";
+
+/// Label with the dual version that created a container.
+pub const VERSION_LABEL: &str = "dev.dual.version";
+
+/// Label naming the container a volume was created for.
+pub const VOLUME_CONTAINER_LABEL: &str = "dev.dual.container";
+
+/// Label holding the directory a volume isolates.
+pub const VOLUME_DIR_LABEL: &str = "dev.dual.dir";
+
+/// An extra host path (or named volume) mounted into a container.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindMount {
+    /// Absolute host path, or the name of a Docker volume.
+    pub source: PathBuf,
+    /// Absolute path inside the container.
+    pub target: String,
+    pub read_only: bool,
+}
+
+impl BindMount {
+    /// Mount a host path at `/workspace/<rel>`.
+    pub fn in_workspace(source: PathBuf, rel: &str, read_only: bool) -> Self {
+        Self {
+            source,
+            target: format!("{WORKSPACE_MOUNT}/{rel}"),
+            read_only,
+        }
+    }
+
+    /// `-v` argument for this mount.
+    fn to_arg(&self) -> String {
+        let mut arg = format!("{}:{}", self.source.display(), self.target);
+        if self.read_only {
+            arg.push_str(":ro");
+        }
+        arg
+    }
+}
+
+/// What a workspace container is created with, besides its name and workspace.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContainerSpec {
+    pub image: String,
+    pub env: HashMap<String, String>,
+    /// Workspace directories isolated in named volumes (e.g. "node_modules").
+    pub volumes: Vec<String>,
+    /// Extra mounts: shared files and caches.
+    pub mounts: Vec<BindMount>,
+    pub resources: Resources,
+    /// The workspace's own network (see `config::network_name`), created if missing.
+    pub network: Option<String>,
+    /// Identifying labels (see `workspace_labels`); not part of the fingerprint.
+    pub labels: Vec<(String, String)>,
+}
+
+/// A dual-managed container, as found by `list_all`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManagedContainer {
+    pub name: String,
+    pub running: bool,
+    pub repo: String,
+    pub branch: String,
+    pub workspace_id: String,
+    /// The sidecar service this container runs, if it is one.
+    pub service: Option<String>,
+}
+
+/// A dual-managed volume, as listed by `list_volumes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolumeInfo {
+    pub name: String,
+    /// Container the volume was created for.
+    pub container: String,
+    /// Workspace directory the volume isolates (e.g. "node_modules").
+    pub dir: String,
+    /// Disk usage as reported by Docker, if known.
+    pub size: Option<String>,
+}
+
+/// A live resource usage sample for one container.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerStats {
+    pub name: String,
+    /// CPU usage in percent of one core (may exceed 100).
+    pub cpu_percent: f64,
+    /// Memory usage and limit as reported by Docker, e.g. "512MiB / 4GiB".
+    pub memory: String,
+    pub memory_percent: f64,
+    pub pids: u64,
+}
 
 /// Container status.
 #[derive(Debug, Clone, PartialEq)]
@@ -15,25 +124,25 @@ pub enum ContainerStatus {
 /// Create a new Docker container for a workspace.
 ///
 /// - Bind mounts workspace dir to /workspace
-/// - Anonymous volumes for directory isolation (configurable)
+/// - Named volumes for directory isolation (configurable), created if missing
 /// - Sets working directory to /workspace
 /// - Passes environment variables via -e flags
+/// - Applies resource limits (CPU, memory, pids, /dev/shm)
 /// - Uses bridge network (default) for network namespace isolation
 pub fn create(
     name: &str,
     workspace_dir: &Path,
-    image: &str,
-    env: &HashMap<String, String>,
-    anonymous_volumes: &[String],
+    spec: &ContainerSpec,
 ) -> Result<String, ContainerError> {
+    for vol in &spec.volumes {
+        ensure_volume(name, vol)?;
+    }
+    if let Some(ref network) = spec.network {
+        ensure_network(network, &spec.labels)?;
+    }
+
     let output = Command::new("docker")
-        .args(build_create_args(
-            name,
-            workspace_dir,
-            image,
-            env,
-            anonymous_volumes,
-        ))
+        .args(build_create_args(name, workspace_dir, spec))
         .output()
         .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
 
@@ -106,13 +215,13 @@ pub fn status(name: &str) -> ContainerStatus {
     }
 }
 
-/// Get the IP address of a running container on the Docker bridge network.
-pub fn get_ip(name: &str) -> Option<String> {
+/// Get the IP address of a running container on a specific network.
+pub fn get_ip(name: &str, network: &str) -> Option<String> {
     let output = Command::new("docker")
         .args([
             "inspect",
             "--format",
-            "{{range .NetworkSettings.Networks}}{{.IPAddress}}{{end}}",
+            &format!("{{{{with index .NetworkSettings.Networks \"{network}\"}}}}{{{{.IPAddress}}}}{{{{end}}}}"),
             name,
         ])
         .output()
@@ -126,58 +235,789 @@ pub fn get_ip(name: &str) -> Option<String> {
     if ip.is_empty() { None } else { Some(ip) }
 }
 
-/// List all dual-managed containers (name and running status).
-pub fn list_all() -> Vec<(String, bool)> {
+/// Docker's default network, which containers without a workspace network are on.
+const DEFAULT_NETWORK: &str = "bridge";
+
+/// The address the proxy reaches a workspace container at: its IP on the
+/// workspace network, or on Docker's default network for older containers.
+pub fn workspace_ip(name: &str, network: &str) -> Option<String> {
+    get_ip(name, network).or_else(|| get_ip(name, DEFAULT_NETWORK))
+}
+
+/// List all dual-managed containers (workspace containers and their sidecars),
+/// found by their workspace label.
+pub fn list_all() -> Vec<ManagedContainer> {
+    parse_managed(&list_filtered(&format!("label={WORKSPACE_LABEL}")))
+}
+
+/// List the containers of `known` (repo, branch) workspaces that were created
+/// before dual labelled them, found by their container name.
+pub fn list_unlabelled(known: &[(String, String)]) -> Vec<ManagedContainer> {
+    parse_unlabelled(
+        &list_filtered(&format!("name=^{}", crate::config::NAME_PREFIX)),
+        known,
+    )
+}
+
+/// `docker ps -a` for one filter, in the format read by `parse_managed`.
+fn list_filtered(filter: &str) -> String {
     let output = Command::new("docker")
         .args([
             "ps",
             "-a",
             "--filter",
-            "name=dual-",
+            filter,
             "--format",
-            "{{.Names}}\t{{.State}}",
+            &format!(
+                "{{{{.Names}}}}\t{{{{.State}}}}\t{{{{.Label \"{WORKSPACE_LABEL}\"}}}}\t{{{{.Label \"{SERVICE_LABEL}\"}}}}\t{{{{.Label \"{REPO_LABEL}\"}}}}\t{{{{.Label \"{BRANCH_LABEL}\"}}}}"
+            ),
         ])
         .output();
 
     match output {
-        Ok(out) if out.status.success() => {
-            let stdout = String::from_utf8_lossy(&out.stdout);
-            stdout
-                .lines()
-                .filter(|line| !line.is_empty())
-                .filter_map(|line| {
-                    let mut parts = line.splitn(2, '\t');
-                    let name = parts.next()?.to_string();
-                    let state = parts.next().unwrap_or("");
-                    Some((name, state == "running"))
-                })
-                .collect()
+        Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout).into_owned(),
+        _ => String::new(),
+    }
+}
+
+/// Parse `docker ps` output in the format used by `list_all`.
+pub fn parse_managed(output: &str) -> Vec<ManagedContainer> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('\t');
+            let name = parts.next()?.to_string();
+            let running = parts.next()? == "running";
+            let workspace_id = parts.next().filter(|id| !id.is_empty())?.to_string();
+            let service = parts.next().filter(|s| !s.is_empty()).map(str::to_string);
+            let repo = parts.next().unwrap_or("").to_string();
+            // A branch may contain anything but a tab or newline
+            let branch = parts.next().unwrap_or("").to_string();
+            Some(ManagedContainer {
+                name,
+                running,
+                repo,
+                branch,
+                workspace_id,
+                service,
+            })
+        })
+        .collect()
+}
+
+/// Parse `docker ps` output for `list_unlabelled`: containers without a
+/// workspace label whose name is the container name of a `known` workspace.
+pub fn parse_unlabelled(output: &str, known: &[(String, String)]) -> Vec<ManagedContainer> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('\t');
+            let name = parts.next()?;
+            let running = parts.next()? == "running";
+            if parts.next().is_some_and(|id| !id.is_empty()) {
+                return None;
+            }
+            let (repo, branch) = known
+                .iter()
+                .find(|(repo, branch)| crate::config::container_name(repo, branch) == name)?;
+            Some(ManagedContainer {
+                name: name.to_string(),
+                running,
+                repo: repo.clone(),
+                branch: branch.clone(),
+                workspace_id: crate::config::workspace_id(repo, branch),
+                service: None,
+            })
+        })
+        .collect()
+}
+
+/// Identifying labels for a workspace's container.
+pub fn workspace_labels(repo: &str, branch: &str) -> Vec<(String, String)> {
+    vec![
+        (REPO_LABEL.to_string(), repo.to_string()),
+        (BRANCH_LABEL.to_string(), branch.to_string()),
+        (
+            WORKSPACE_LABEL.to_string(),
+            crate::config::workspace_id(repo, branch),
+        ),
+        (
+            VERSION_LABEL.to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        ),
+    ]
+}
+
+/// Sample resource usage of the given running containers.
+pub fn stats(names: &[String]) -> Vec<ContainerStats> {
+    if names.is_empty() {
+        return Vec::new();
+    }
+
+    let output = Command::new("docker")
+        .args([
+            "stats",
+            "--no-stream",
+            "--format",
+            "{{.Name}}\t{{.CPUPerc}}\t{{.MemUsage}}\t{{.MemPerc}}\t{{.PIDs}}",
+        ])
+        .args(names)
+        .output();
+
+    match output {
+        Ok(out) if out.status.success() => parse_stats(&String::from_utf8_lossy(&out.stdout)),
+        _ => Vec::new(),
+    }
+}
+
+/// Parse `docker stats` output in the format used by `stats`.
+pub fn parse_stats(output: &str) -> Vec<ContainerStats> {
+    let percent = |s: &str| s.trim().trim_end_matches('%').parse::<f64>().unwrap_or(0.0);
+    output
+        .lines()
+        .filter_map(|line| {
+            let cols: Vec<_> = line.split('\t').collect();
+            let [name, cpu, memory, memory_percent, pids] = cols[..] else {
+                return None;
+            };
+            Some(ContainerStats {
+                name: name.to_string(),
+                cpu_percent: percent(cpu),
+                memory: memory.to_string(),
+                memory_percent: percent(memory_percent),
+                pids: pids.trim().parse().unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+/// List the Docker volumes mounted into a container as (volume name, destination).
+///
+/// Only `volume` mounts are returned; the workspace bind mount is skipped.
+pub fn volume_mounts(name: &str) -> Vec<(String, String)> {
+    let output = Command::new("docker")
+        .args([
+            "inspect",
+            "--format",
+            "{{range .Mounts}}{{if eq .Type \"volume\"}}{{.Name}}\t{{.Destination}}\n{{end}}{{end}}",
+            name,
+        ])
+        .output();
+
+    match output {
+        Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .filter_map(|line| {
+                let (vol, dest) = line.split_once('\t')?;
+                Some((vol.to_string(), dest.to_string()))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Recreate a container under a new name, reattaching its existing volumes.
+///
+/// Docker can't change a container's bind mount, so a rename that also moves
+/// the workspace directory needs a fresh container. The old container's
+/// volumes are mounted into the new one, so isolated directories like
+/// `node_modules` survive (and keep their original volume names). The new container is started if
+/// the old one was running. On failure the old container is left in place.
+pub fn rename(
+    old_name: &str,
+    new_name: &str,
+    workspace_dir: &Path,
+    spec: &ContainerSpec,
+) -> Result<(), ContainerError> {
+    let was_running = status(old_name) == ContainerStatus::Running;
+    let mounts = volume_mounts(old_name);
+
+    if was_running {
+        stop(old_name)?;
+    }
+
+    for vol in &spec.volumes {
+        let dest = format!("{WORKSPACE_MOUNT}/{vol}");
+        if !mounts.iter().any(|(_, d)| *d == dest) {
+            ensure_volume(new_name, vol)?;
+        }
+    }
+    if let Some(ref network) = spec.network {
+        ensure_network(network, &spec.labels)?;
+    }
+
+    let args = reattach_volumes(build_create_args(new_name, workspace_dir, spec), &mounts);
+    let output = Command::new("docker")
+        .args(&args)
+        .output()
+        .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
+
+    if !output.status.success() {
+        if was_running {
+            let _ = start(old_name);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(ContainerError::Failed {
+            operation: "create".to_string(),
+            name: new_name.to_string(),
+            stderr,
+        });
+    }
+
+    // `docker rm` without `-v` keeps the volumes now referenced by the new container
+    if let Err(e) = destroy(old_name) {
+        let _ = destroy(new_name);
+        if was_running {
+            let _ = start(old_name);
         }
+        return Err(e);
+    }
+
+    if was_running {
+        start(new_name)?;
+    }
+
+    Ok(())
+}
+
+/// Recreate a container in place with new settings, keeping its volumes.
+///
+/// The old container is renamed aside and replaced through `rename`, so
+/// isolated directories like `node_modules` are reattached rather than
+/// reinstalled. On failure the old container is restored under its name.
+pub fn recreate(
+    name: &str,
+    workspace_dir: &Path,
+    spec: &ContainerSpec,
+) -> Result<(), ContainerError> {
+    let aside = format!("{name}-recreating");
+    docker_rename(name, &aside)?;
+
+    if let Err(e) = rename(&aside, name, workspace_dir, spec) {
+        let _ = docker_rename(&aside, name);
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// The fingerprint label of an existing container, if it has one.
+pub fn fingerprint(name: &str) -> Option<String> {
+    let output = Command::new("docker")
+        .args([
+            "inspect",
+            "--format",
+            &format!("{{{{index .Config.Labels \"{FINGERPRINT_LABEL}\"}}}}"),
+            name,
+        ])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if value.is_empty() || value == "<no value>" {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// The fingerprint embedded in arguments from `build_create_args`.
+pub fn args_fingerprint(args: &[String]) -> Option<&str> {
+    let prefix = format!("{FINGERPRINT_LABEL}=");
+    args.iter().find_map(|arg| arg.strip_prefix(&prefix))
+}
+
+/// Hash of the create arguments that define a container's configuration.
+///
+/// The container name (and volume names derived from it) is left out, but the
+/// bind-mounted workspace directory and the network are hashed: both change
+/// when a workspace is renamed, so a rename changes the fingerprint too (and
+/// `rename` recreates the container anyway).
+fn compute_fingerprint(settings: &[String]) -> String {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    for arg in settings {
+        hasher.update(arg.as_bytes());
+        hasher.update([0]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .take(8)
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Rewrite volume args (`-v vol:/workspace/x`) to mount existing volumes at the same destination.
+pub fn reattach_volumes(args: Vec<String>, mounts: &[(String, String)]) -> Vec<String> {
+    args.into_iter()
+        .map(|arg| {
+            let existing = mounts.iter().find(|(_, dest)| {
+                *dest == arg
+                    || arg
+                        .strip_suffix(dest.as_str())
+                        .is_some_and(|v| v.ends_with(':'))
+            });
+            match existing {
+                Some((vol, dest)) => format!("{vol}:{dest}"),
+                None => arg,
+            }
+        })
+        .collect()
+}
+
+/// Name of the volume isolating `dir` in a container: `{container}-{dir}`.
+///
+/// Characters Docker doesn't allow in volume names (including `/` in nested
+/// directories) become `_`, and the name is then tagged with `--` and a hash of
+/// `dir`, as in `config::encode_branch`, so `a/b` and `a_b` get different volumes.
+pub fn volume_name(container: &str, dir: &str) -> String {
+    let dir = dir.trim_matches('/');
+    let safe: String = dir
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if safe == dir && !dir.contains("--") {
+        format!("{container}-{dir}")
+    } else {
+        format!("{container}-{safe}--{}", crate::config::name_tag(dir))
+    }
+}
+
+/// Create a container's volume for `dir` unless it already exists.
+fn ensure_volume(container: &str, dir: &str) -> Result<(), ContainerError> {
+    create_volume(
+        &volume_name(container, dir),
+        &[(VOLUME_CONTAINER_LABEL, container), (VOLUME_DIR_LABEL, dir)],
+    )
+}
+
+/// Create a labelled volume unless one with that name already exists.
+pub fn create_volume(name: &str, labels: &[(&str, &str)]) -> Result<(), ContainerError> {
+    let exists = Command::new("docker")
+        .args(["volume", "inspect", name])
+        .output()
+        .map(|out| out.status.success())
+        .unwrap_or(false);
+    if exists {
+        return Ok(());
+    }
+
+    let output = Command::new("docker")
+        .args(build_volume_create_args(name, labels))
+        .output()
+        .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(ContainerError::Failed {
+            operation: "volume create".to_string(),
+            name: name.to_string(),
+            stderr,
+        });
+    }
+
+    Ok(())
+}
+
+/// Build docker volume create arguments (for testing).
+pub fn build_volume_create_args(name: &str, labels: &[(&str, &str)]) -> Vec<String> {
+    let mut args = vec!["volume".to_string(), "create".to_string()];
+    for (key, value) in labels {
+        args.push("--label".to_string());
+        args.push(format!("{key}={value}"));
+    }
+    args.push(name.to_string());
+    args
+}
+
+/// Number of /26 subnets (62 addresses each) in 10.213.0.0/16, the range
+/// workspace networks are allocated from. Docker's default pools give every
+/// network a /16 or /20 and run out after about 30 networks.
+const NETWORK_SUBNETS: u32 = 1024;
+
+/// Subnets tried for a new network before falling back to Docker's pools.
+const NETWORK_SUBNET_ATTEMPTS: u32 = 64;
+
+/// Create a labelled user-defined bridge network unless it already exists.
+///
+/// It gets a /26 in 10.213.0.0/16, starting from one picked by its name and
+/// moving on while Docker reports an overlap with an existing network.
+pub fn ensure_network(name: &str, labels: &[(String, String)]) -> Result<(), ContainerError> {
+    let exists = Command::new("docker")
+        .args(["network", "inspect", name])
+        .output()
+        .map(|out| out.status.success())
+        .unwrap_or(false);
+    if exists {
+        return Ok(());
+    }
+
+    let first = name.bytes().fold(0u32, |hash, b| {
+        hash.wrapping_mul(31).wrapping_add(u32::from(b))
+    });
+    let subnets = (0..NETWORK_SUBNET_ATTEMPTS).map(|i| Some(network_subnet(first.wrapping_add(i))));
+    let mut stderr = String::new();
+    for subnet in subnets.chain([None]) {
+        let output = Command::new("docker")
+            .args(build_network_create_args(name, labels, subnet.as_deref()))
+            .output()
+            .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
+        if output.status.success() {
+            return Ok(());
+        }
+        stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        if !stderr.contains("overlap") {
+            break;
+        }
+    }
+
+    Err(ContainerError::Failed {
+        operation: "network create".to_string(),
+        name: name.to_string(),
+        stderr,
+    })
+}
+
+/// The `index`th /26 of 10.213.0.0/16, wrapping around.
+pub fn network_subnet(index: u32) -> String {
+    let offset = (index % NETWORK_SUBNETS) * 64;
+    format!("10.213.{}.{}/26", offset / 256, offset % 256)
+}
+
+/// Build docker network create arguments (for testing). Without a `subnet`,
+/// Docker picks one from its default address pools.
+pub fn build_network_create_args(
+    name: &str,
+    labels: &[(String, String)],
+    subnet: Option<&str>,
+) -> Vec<String> {
+    let mut args = vec![
+        "network".to_string(),
+        "create".to_string(),
+        "--driver".to_string(),
+        "bridge".to_string(),
+    ];
+    if let Some(subnet) = subnet {
+        args.push("--subnet".to_string());
+        args.push(subnet.to_string());
+    }
+    for (key, value) in labels {
+        args.push("--label".to_string());
+        args.push(format!("{key}={value}"));
+    }
+    args.push(name.to_string());
+    args
+}
+
+/// Remove a network. Fails if a container is still connected to it.
+pub fn remove_network(name: &str) -> Result<(), ContainerError> {
+    let output = Command::new("docker")
+        .args(["network", "rm", name])
+        .output()
+        .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(ContainerError::Failed {
+            operation: "network rm".to_string(),
+            name: name.to_string(),
+            stderr,
+        });
+    }
+
+    Ok(())
+}
+
+/// Connect a container to a network; already being connected is not an error.
+pub fn connect_network(network: &str, container: &str) -> Result<(), ContainerError> {
+    let output = Command::new("docker")
+        .args(["network", "connect", network, container])
+        .output()
+        .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    if !output.status.success() && !stderr.contains("already exists") {
+        return Err(ContainerError::Failed {
+            operation: "network connect".to_string(),
+            name: container.to_string(),
+            stderr,
+        });
+    }
+
+    Ok(())
+}
+
+/// Remove a volume. Fails if a container still uses it.
+pub fn remove_volume(name: &str) -> Result<(), ContainerError> {
+    let output = Command::new("docker")
+        .args(["volume", "rm", name])
+        .output()
+        .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(ContainerError::Failed {
+            operation: "volume rm".to_string(),
+            name: name.to_string(),
+            stderr,
+        });
+    }
+
+    Ok(())
+}
+
+/// Names of the volumes created for workspace containers, found by label.
+///
+/// With `container`, only the volumes labelled with that container (including
+/// its sidecars' volumes); otherwise every one. Shared caches carry no container
+/// label and are never returned.
+pub fn owned_volumes(container: Option<&str>) -> Vec<String> {
+    let output = Command::new("docker")
+        .args(build_owned_volumes_args(container))
+        .output();
+
+    match output {
+        Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
         _ => Vec::new(),
     }
 }
 
-/// Execute a setup command inside a running container.
+/// Build docker volume ls arguments for `owned_volumes` (for testing).
+pub fn build_owned_volumes_args(container: Option<&str>) -> Vec<String> {
+    let filter = match container {
+        Some(name) => format!("label={VOLUME_CONTAINER_LABEL}={name}"),
+        None => format!("label={VOLUME_CONTAINER_LABEL}"),
+    };
+    vec![
+        "volume".to_string(),
+        "ls".to_string(),
+        "--quiet".to_string(),
+        "--filter".to_string(),
+        filter,
+    ]
+}
+
+/// List dual-managed volumes with their disk usage.
+pub fn list_volumes() -> Vec<VolumeInfo> {
+    let output = Command::new("docker")
+        .args([
+            "volume",
+            "ls",
+            "--filter",
+            &format!("label={VOLUME_CONTAINER_LABEL}"),
+            "--format",
+            &format!(
+                "{{{{.Name}}}}\t{{{{.Label \"{VOLUME_CONTAINER_LABEL}\"}}}}\t{{{{.Label \"{VOLUME_DIR_LABEL}\"}}}}"
+            ),
+        ])
+        .output();
+
+    let stdout = match output {
+        Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout).to_string(),
+        _ => return Vec::new(),
+    };
+
+    let sizes = volume_sizes();
+
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('\t');
+            let name = parts.next()?.to_string();
+            let container = parts.next().unwrap_or("").to_string();
+            let dir = parts.next().unwrap_or("").to_string();
+            let size = sizes.get(&name).cloned();
+            Some(VolumeInfo {
+                name,
+                container,
+                dir,
+                size,
+            })
+        })
+        .collect()
+}
+
+/// Disk usage of every volume, keyed by name (empty if Docker can't say).
+pub fn volume_sizes() -> HashMap<String, String> {
+    Command::new("docker")
+        .args(["system", "df", "-v"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| parse_volume_sizes(&String::from_utf8_lossy(&out.stdout)))
+        .unwrap_or_default()
+}
+
+/// Parse volume sizes from the "Local Volumes space usage" table of `docker system df -v`.
+pub fn parse_volume_sizes(output: &str) -> HashMap<String, String> {
+    let mut sizes = HashMap::new();
+    let mut in_volumes = false;
+    for line in output.lines() {
+        if line.starts_with("Local Volumes space usage") {
+            in_volumes = true;
+            continue;
+        }
+        if !in_volumes || line.starts_with("VOLUME NAME") {
+            continue;
+        }
+        if line.trim().is_empty() {
+            if !sizes.is_empty() {
+                break;
+            }
+            continue;
+        }
+        // VOLUME NAME   LINKS   SIZE
+        let cols: Vec<_> = line.split_whitespace().collect();
+        if let [name, _links, size] = cols[..] {
+            sizes.insert(name.to_string(), size.to_string());
+        }
+    }
+    sizes
+}
+
+/// Copy the contents of one volume into another using a throwaway container.
 ///
-/// Runs `docker exec <name> sh -c "<setup_cmd>"` and waits for completion.
-pub fn exec_setup(name: &str, setup_cmd: &str) -> Result<(), ContainerError> {
+/// `image` only needs `sh` and `cp`; the workspace's own image is used so no
+/// extra pull is needed. Existing files in the target are overwritten.
+pub fn seed_volume(image: &str, from: &str, to: &str) -> Result<(), ContainerError> {
     let output = Command::new("docker")
-        .args(build_exec_setup_args(name, setup_cmd))
+        .args(build_seed_volume_args(image, from, to))
         .output()
         .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(ContainerError::Failed {
+            operation: "volume seed".to_string(),
+            name: to.to_string(),
+            stderr,
+        });
+    }
+
+    Ok(())
+}
+
+/// Build docker run arguments for `seed_volume` (for testing).
+pub fn build_seed_volume_args(image: &str, from: &str, to: &str) -> Vec<String> {
+    vec![
+        "run".to_string(),
+        "--rm".to_string(),
+        "-v".to_string(),
+        format!("{from}:/from:ro"),
+        "-v".to_string(),
+        format!("{to}:/to"),
+        image.to_string(),
+        "sh".to_string(),
+        "-c".to_string(),
+        "cp -a /from/. /to/".to_string(),
+    ]
+}
+
+/// Make sure a workspace's volume for `dir` exists and return its name.
+///
+/// Uses the volume already mounted into the container at that directory if
+/// there is one (e.g. after a rename), otherwise the container's named volume.
+pub fn workspace_volume(container: &str, dir: &str) -> Result<String, ContainerError> {
+    let dest = format!("{WORKSPACE_MOUNT}/{dir}");
+    if let Some((vol, _)) = volume_mounts(container)
+        .into_iter()
+        .find(|(_, d)| *d == dest)
+    {
+        return Ok(vol);
+    }
+    ensure_volume(container, dir)?;
+    Ok(volume_name(container, dir))
+}
+
+/// Execute a setup command inside a running container.
+///
+/// Runs `docker exec <name> sh -c "<setup_cmd>"`, streaming its output to the
+/// terminal as it arrives and copying it to `log`, and waits for completion.
+pub fn exec_setup(name: &str, setup_cmd: &str, log: std::fs::File) -> Result<(), ContainerError> {
+    use std::process::Stdio;
+
+    let mut child = Command::new("docker")
+        .args(build_exec_setup_args(name, setup_cmd))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
+
+    let log = std::sync::Mutex::new(log);
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    std::thread::scope(|s| {
+        if let Some(out) = stdout {
+            s.spawn(|| tee_lines(out, &mut std::io::stdout(), &log));
+        }
+        if let Some(err) = stderr {
+            s.spawn(|| tee_lines(err, &mut std::io::stderr(), &log));
+        }
+    });
+
+    let status = child
+        .wait()
+        .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
+    if !status.success() {
         return Err(ContainerError::Failed {
             operation: "exec setup".to_string(),
             name: name.to_string(),
-            stderr,
+            stderr: status.to_string(),
         });
     }
 
     Ok(())
 }
 
+/// Copy `reader` line by line to `console` and `log` until it closes.
+fn tee_lines(
+    reader: impl std::io::Read,
+    console: &mut dyn std::io::Write,
+    log: &std::sync::Mutex<std::fs::File>,
+) {
+    use std::io::{BufRead, Write};
+
+    let mut reader = std::io::BufReader::new(reader);
+    let mut line = Vec::new();
+    while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+        let _ = console.write_all(&line);
+        let _ = console.flush();
+        if let Ok(mut log) = log.lock() {
+            let _ = log.write_all(&line);
+        }
+        line.clear();
+    }
+}
+
+/// Docker's ID for a container, if it exists.
+pub fn id(name: &str) -> Option<String> {
+    let output = Command::new("docker")
+        .args(["inspect", "--format", "{{.Id}}", name])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!id.is_empty()).then_some(id)
+}
+
 /// Build docker exec setup arguments (for testing).
 pub fn build_exec_setup_args(name: &str, setup_cmd: &str) -> Vec<String> {
     vec![
@@ -191,46 +1031,130 @@ pub fn build_exec_setup_args(name: &str, setup_cmd: &str) -> Vec<String> {
     ]
 }
 
+/// Start a command in a running container without waiting for it (e.g. a dev server).
+pub fn exec_detached(name: &str, cmd: &str) -> Result<(), ContainerError> {
+    let output = Command::new("docker")
+        .args(build_exec_detached_args(name, cmd))
+        .output()
+        .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
+    if !output.status.success() {
+        return Err(ContainerError::Failed {
+            operation: "exec".to_string(),
+            name: name.to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Build detached docker exec arguments (for testing).
+pub fn build_exec_detached_args(name: &str, cmd: &str) -> Vec<String> {
+    vec![
+        "exec".to_string(),
+        "-d".to_string(),
+        "-w".to_string(),
+        WORKSPACE_MOUNT.to_string(),
+        name.to_string(),
+        "sh".to_string(),
+        "-c".to_string(),
+        cmd.to_string(),
+    ]
+}
+
 /// Build the docker create arguments (for testing).
-pub fn build_create_args(
-    name: &str,
-    workspace_dir: &Path,
-    image: &str,
-    env: &HashMap<String, String>,
-    anonymous_volumes: &[String],
-) -> Vec<String> {
+pub fn build_create_args(name: &str, workspace_dir: &Path, spec: &ContainerSpec) -> Vec<String> {
     let mut args = vec![
-        "create".to_string(),
-        "--name".to_string(),
-        name.to_string(),
         // Bind mount workspace
         "-v".to_string(),
         format!("{}:{WORKSPACE_MOUNT}", workspace_dir.display()),
     ];
 
-    // Anonymous volumes for directory isolation
-    for vol in anonymous_volumes {
+    // Volumes for directory isolation (named below, after fingerprinting)
+    for vol in &spec.volumes {
         args.push("-v".to_string());
         args.push(format!("{WORKSPACE_MOUNT}/{vol}"));
     }
 
-    // Environment variables
+    // Extra mounts (shared files, caches)
+    for mount in &spec.mounts {
+        args.push("-v".to_string());
+        args.push(mount.to_arg());
+    }
+
+    // Environment variables, sorted so the fingerprint is stable
+    let mut env: Vec<_> = spec.env.iter().collect();
+    env.sort();
     for (key, value) in env {
         args.push("-e".to_string());
         args.push(format!("{key}={value}"));
     }
 
+    // Resource limits
+    args.extend(resource_args(&spec.resources));
+
+    // The workspace's own network, instead of the shared default bridge
+    if let Some(ref network) = spec.network {
+        args.push("--network".to_string());
+        args.push(network.clone());
+    }
+
     // Working directory
     args.push("-w".to_string());
     args.push(WORKSPACE_MOUNT.to_string());
 
     // Image
-    args.push(image.to_string());
+    args.push(spec.image.to_string());
 
     // Keep container running for docker exec
     args.push("sleep".to_string());
     args.push("infinity".to_string());
 
+    let fingerprint = compute_fingerprint(&args);
+    let named: Vec<_> = spec
+        .volumes
+        .iter()
+        .map(|vol| (volume_name(name, vol), format!("{WORKSPACE_MOUNT}/{vol}")))
+        .collect();
+    let args = reattach_volumes(args, &named);
+
+    let mut create = vec![
+        "create".to_string(),
+        "--name".to_string(),
+        name.to_string(),
+        "--label".to_string(),
+        format!("{FINGERPRINT_LABEL}={fingerprint}"),
+    ];
+    for (key, value) in &spec.labels {
+        create.push("--label".to_string());
+        create.push(format!("{key}={value}"));
+    }
+    create.extend(args);
+    create
+}
+
+/// `docker create` flags for resource limits; unset limits add nothing.
+fn resource_args(resources: &Resources) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(cpus) = resources.cpus {
+        args.push("--cpus".to_string());
+        args.push(cpus.to_string());
+    }
+    if let Some(ref memory) = resources.memory {
+        args.push("--memory".to_string());
+        args.push(memory.clone());
+    }
+    if let Some(ref swap) = resources.memory_swap {
+        args.push("--memory-swap".to_string());
+        args.push(swap.clone());
+    }
+    if let Some(pids) = resources.pids_limit {
+        args.push("--pids-limit".to_string());
+        args.push(pids.to_string());
+    }
+    if let Some(ref shm) = resources.shm_size {
+        args.push("--shm-size".to_string());
+        args.push(shm.clone());
+    }
     args
 }
 
@@ -247,6 +1171,24 @@ pub fn build_exec_args(name: &str, cmd: &[&str], tty: bool) -> Vec<String> {
     args
 }
 
+fn docker_rename(from: &str, to: &str) -> Result<(), ContainerError> {
+    let output = Command::new("docker")
+        .args(["rename", from, to])
+        .output()
+        .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(ContainerError::Failed {
+            operation: "rename".to_string(),
+            name: from.to_string(),
+            stderr,
+        });
+    }
+
+    Ok(())
+}
+
 fn docker_simple(operation: &str, name: &str) -> Result<(), ContainerError> {
     let output = Command::new("docker")
         .args([operation, name])
@@ -282,6 +1224,23 @@ pub enum ContainerError {
 mod tests {
     use super::*;
 
+    fn spec(
+        image: &str,
+        env: &HashMap<String, String>,
+        volumes: &[String],
+        mounts: &[BindMount],
+    ) -> ContainerSpec {
+        ContainerSpec {
+            image: image.to_string(),
+            env: env.clone(),
+            volumes: volumes.to_vec(),
+            mounts: mounts.to_vec(),
+            resources: Resources::default(),
+            network: None,
+            labels: Vec::new(),
+        }
+    }
+
     #[test]
     fn create_args_correct() {
         let env = HashMap::new();
@@ -289,22 +1248,101 @@ mod tests {
         let args = build_create_args(
             "dual-lightfast-main",
             Path::new("/home/user/dual-workspaces/lightfast/main"),
-            "node:20",
-            &env,
-            &volumes,
+            &spec("node:20", &env, &volumes, &[]),
         );
         assert_eq!(args[0], "create");
         assert_eq!(args[1], "--name");
         assert_eq!(args[2], "dual-lightfast-main");
-        assert_eq!(args[3], "-v");
-        assert!(args[4].contains("/home/user/dual-workspaces/lightfast/main:/workspace"));
+        assert_eq!(args[3], "--label");
+        assert!(args[4].starts_with("dev.dual.config_hash="));
         assert_eq!(args[5], "-v");
-        assert_eq!(args[6], "/workspace/node_modules");
-        assert_eq!(args[7], "-w");
-        assert_eq!(args[8], "/workspace");
-        assert_eq!(args[9], "node:20");
-        assert_eq!(args[10], "sleep");
-        assert_eq!(args[11], "infinity");
+        assert!(args[6].contains("/home/user/dual-workspaces/lightfast/main:/workspace"));
+        assert_eq!(args[7], "-v");
+        assert_eq!(
+            args[8],
+            "dual-lightfast-main-node_modules:/workspace/node_modules"
+        );
+        assert_eq!(args[9], "-w");
+        assert_eq!(args[10], "/workspace");
+        assert_eq!(args[11], "node:20");
+        assert_eq!(args[12], "sleep");
+        assert_eq!(args[13], "infinity");
+    }
+
+    #[test]
+    fn create_args_join_workspace_network() {
+        let on_bridge = spec("node:20", &HashMap::new(), &[], &[]);
+        let mut own = on_bridge.clone();
+        own.network = Some("dual-net-lightfast-main".to_string());
+        let args = build_create_args("dual-lightfast-main", Path::new("/tmp/ws"), &own);
+        assert!(
+            args.windows(2)
+                .any(|w| w == ["--network", "dual-net-lightfast-main"])
+        );
+
+        // Moving a container onto its own network is config drift
+        let on_bridge = build_create_args("dual-lightfast-main", Path::new("/tmp/ws"), &on_bridge);
+        assert_ne!(args_fingerprint(&args), args_fingerprint(&on_bridge));
+    }
+
+    #[test]
+    fn network_create_args_correct() {
+        let labels = workspace_labels("lightfast", "main");
+        let args =
+            build_network_create_args("dual-net-lightfast-main", &labels, Some("10.213.0.64/26"));
+        assert_eq!(&args[..4], &["network", "create", "--driver", "bridge"]);
+        assert!(args.windows(2).any(|w| w == ["--subnet", "10.213.0.64/26"]));
+        assert!(args.contains(&"dev.dual.workspace_id=lightfast-main".to_string()));
+        assert_eq!(args.last().unwrap(), "dual-net-lightfast-main");
+
+        let args = build_network_create_args("dual-net-lightfast-main", &labels, None);
+        assert!(!args.contains(&"--subnet".to_string()));
+    }
+
+    #[test]
+    fn network_subnets_are_small_and_wrap() {
+        assert_eq!(network_subnet(0), "10.213.0.0/26");
+        assert_eq!(network_subnet(5), "10.213.1.64/26");
+        assert_eq!(network_subnet(1023), "10.213.255.192/26");
+        assert_eq!(network_subnet(1024), network_subnet(0));
+    }
+
+    #[test]
+    fn fingerprint_tracks_config_but_not_name() {
+        let mut env = HashMap::new();
+        env.insert("A".to_string(), "1".to_string());
+        env.insert("B".to_string(), "2".to_string());
+        let volumes = vec!["node_modules".to_string()];
+        let ws = Path::new("/tmp/ws");
+
+        let base = build_create_args("dual-a", ws, &spec("node:20", &env, &volumes, &[]));
+        let fp = args_fingerprint(&base).unwrap().to_string();
+        assert_eq!(fp.len(), 16);
+
+        // Stable across names and env iteration order
+        let renamed = build_create_args("dual-b", ws, &spec("node:20", &env, &volumes, &[]));
+        assert_eq!(args_fingerprint(&renamed), Some(fp.as_str()));
+
+        // The bind-mounted workspace directory is part of the config
+        let moved = build_create_args(
+            "dual-a",
+            Path::new("/tmp/other"),
+            &spec("node:20", &env, &volumes, &[]),
+        );
+        assert_ne!(args_fingerprint(&moved), Some(fp.as_str()));
+
+        let image = build_create_args("dual-a", ws, &spec("node:22", &env, &volumes, &[]));
+        assert_ne!(args_fingerprint(&image), Some(fp.as_str()));
+
+        env.insert("B".to_string(), "3".to_string());
+        let changed_env = build_create_args("dual-a", ws, &spec("node:20", &env, &volumes, &[]));
+        assert_ne!(args_fingerprint(&changed_env), Some(fp.as_str()));
+
+        let no_volumes = build_create_args("dual-a", ws, &spec("node:20", &env, &[], &[]));
+        assert_ne!(
+            args_fingerprint(&no_volumes),
+            args_fingerprint(&changed_env)
+        );
     }
 
     #[test]
@@ -312,7 +1350,11 @@ mod tests {
         let mut env = HashMap::new();
         env.insert("NODE_ENV".to_string(), "development".to_string());
         let volumes = vec!["node_modules".to_string()];
-        let args = build_create_args("dual-test", Path::new("/tmp/ws"), "node:20", &env, &volumes);
+        let args = build_create_args(
+            "dual-test",
+            Path::new("/tmp/ws"),
+            &spec("node:20", &env, &volumes, &[]),
+        );
         assert!(args.contains(&"-e".to_string()));
         assert!(args.contains(&"NODE_ENV=development".to_string()));
     }
@@ -325,20 +1367,153 @@ mod tests {
             ".next".to_string(),
             "target".to_string(),
         ];
-        let args = build_create_args("dual-test", Path::new("/tmp/ws"), "node:20", &env, &volumes);
-        assert!(args.contains(&"/workspace/node_modules".to_string()));
-        assert!(args.contains(&"/workspace/.next".to_string()));
-        assert!(args.contains(&"/workspace/target".to_string()));
+        let args = build_create_args(
+            "dual-test",
+            Path::new("/tmp/ws"),
+            &spec("node:20", &env, &volumes, &[]),
+        );
+        assert!(args.contains(&"dual-test-node_modules:/workspace/node_modules".to_string()));
+        assert!(args.contains(&"dual-test-.next:/workspace/.next".to_string()));
+        assert!(args.contains(&"dual-test-target:/workspace/target".to_string()));
     }
 
     #[test]
     fn create_args_empty_env_no_extra_flags() {
         let env = HashMap::new();
         let volumes = vec!["node_modules".to_string()];
-        let args = build_create_args("dual-test", Path::new("/tmp/ws"), "node:20", &env, &volumes);
+        let args = build_create_args(
+            "dual-test",
+            Path::new("/tmp/ws"),
+            &spec("node:20", &env, &volumes, &[]),
+        );
         assert!(!args.contains(&"-e".to_string()));
     }
 
+    #[test]
+    fn create_args_with_bind_mounts() {
+        let env = HashMap::new();
+        let mounts = vec![
+            BindMount::in_workspace(PathBuf::from("/home/u/.dual/shared/app/.env"), ".env", true),
+            BindMount {
+                source: PathBuf::from("/home/u/.dual/shared/app"),
+                target: "/home/u/.dual/shared/app".to_string(),
+                read_only: false,
+            },
+        ];
+        let args = build_create_args(
+            "dual-test",
+            Path::new("/tmp/ws"),
+            &spec("node:20", &env, &[], &mounts),
+        );
+        assert!(args.contains(&"/home/u/.dual/shared/app/.env:/workspace/.env:ro".to_string()));
+        assert!(args.contains(&"/home/u/.dual/shared/app:/home/u/.dual/shared/app".to_string()));
+        // Mounts come before the image
+        let image_pos = args.iter().position(|a| a == "node:20").unwrap();
+        let mount_pos = args.iter().position(|a| a.ends_with(":ro")).unwrap();
+        assert!(mount_pos < image_pos);
+    }
+
+    #[test]
+    fn create_args_with_resource_limits() {
+        let mut limited = spec("node:20", &HashMap::new(), &[], &[]);
+        limited.resources = Resources {
+            cpus: Some(1.5),
+            memory: Some("4g".to_string()),
+            memory_swap: Some("-1".to_string()),
+            pids_limit: Some(512),
+            shm_size: Some("1g".to_string()),
+        };
+        let args = build_create_args("dual-test", Path::new("/tmp/ws"), &limited);
+        let joined = args.join(" ");
+        assert!(joined.contains("--cpus 1.5"));
+        assert!(joined.contains("--memory 4g"));
+        assert!(joined.contains("--memory-swap -1"));
+        assert!(joined.contains("--pids-limit 512"));
+        assert!(joined.contains("--shm-size 1g"));
+        // Limits come before the image and change the fingerprint
+        let image_pos = args.iter().position(|a| a == "node:20").unwrap();
+        let cpus_pos = args.iter().position(|a| a == "--cpus").unwrap();
+        assert!(cpus_pos < image_pos);
+        let unlimited = build_create_args(
+            "dual-test",
+            Path::new("/tmp/ws"),
+            &spec("node:20", &HashMap::new(), &[], &[]),
+        );
+        assert!(!unlimited.contains(&"--cpus".to_string()));
+        assert_ne!(args_fingerprint(&args), args_fingerprint(&unlimited));
+    }
+
+    #[test]
+    fn workspace_labels_identify_the_workspace() {
+        let mut labelled = spec("node:20", &HashMap::new(), &[], &[]);
+        labelled.labels = workspace_labels("app", "feat/auth");
+        let args = build_create_args("dual-x", Path::new("/tmp/ws"), &labelled);
+
+        assert!(args.contains(&"dev.dual.repo=app".to_string()));
+        assert!(args.contains(&"dev.dual.branch=feat/auth".to_string()));
+        let id = crate::config::workspace_id("app", "feat/auth");
+        assert!(args.contains(&format!("dev.dual.workspace_id={id}")));
+        assert!(args.iter().any(|a| a.starts_with("dev.dual.version=")));
+
+        // Labels don't affect the fingerprint
+        let plain = build_create_args(
+            "dual-x",
+            Path::new("/tmp/ws"),
+            &spec("node:20", &HashMap::new(), &[], &[]),
+        );
+        assert_eq!(args_fingerprint(&args), args_fingerprint(&plain));
+    }
+
+    #[test]
+    fn parses_managed_containers() {
+        let output = "dual-app-main\trunning\tapp-main\t\tapp\tmain\n\
+                      dual-app-feat\texited\tapp-feat-auth--1a2b3c4d\t\tapp\tfeat/auth\n\
+                      dual-app-main-postgres\trunning\tapp-main\tpostgres\tapp\tmain\n\
+                      dual-test-abc\trunning\t\t\t\t\n";
+        let containers = parse_managed(output);
+        assert_eq!(containers.len(), 3);
+        assert_eq!(containers[0].service, None);
+        assert_eq!(containers[2].service.as_deref(), Some("postgres"));
+        assert!(containers[0].running);
+        assert_eq!(containers[0].workspace_id, "app-main");
+        assert!(!containers[1].running);
+        assert_eq!(containers[1].branch, "feat/auth");
+    }
+
+    #[test]
+    fn parses_unlabelled_containers_of_known_workspaces() {
+        let output = "dual-app-main\trunning\tapp-main\t\tapp\tmain\n\
+                      dual-app-old\texited\t\t\t\t\n\
+                      dual-test-abc\trunning\t\t\t\t\n";
+        let known = vec![
+            ("app".to_string(), "main".to_string()),
+            ("app".to_string(), "old".to_string()),
+        ];
+        // Labelled containers are left to `parse_managed`, and unrelated
+        // dual-* containers are not ours
+        let containers = parse_unlabelled(output, &known);
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].name, "dual-app-old");
+        assert_eq!(containers[0].workspace_id, "app-old");
+        assert_eq!(containers[0].branch, "old");
+        assert!(!containers[0].running);
+    }
+
+    #[test]
+    fn parses_docker_stats() {
+        let output = "dual-app-main\t12.50%\t512MiB / 4GiB\t12.50%\t37\n\
+                      dual-app-feat\t150.03%\t1.2GiB / 7.6GiB\t15.79%\t112\n\
+                      garbage line\n";
+        let stats = parse_stats(output);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].name, "dual-app-main");
+        assert_eq!(stats[0].cpu_percent, 12.5);
+        assert_eq!(stats[0].memory, "512MiB / 4GiB");
+        assert_eq!(stats[1].cpu_percent, 150.03);
+        assert_eq!(stats[1].memory_percent, 15.79);
+        assert_eq!(stats[1].pids, 112);
+    }
+
     #[test]
     fn exec_setup_args_correct() {
         let args = build_exec_setup_args("dual-lightfast-main", "pnpm install");
@@ -356,6 +1531,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn exec_detached_args_correct() {
+        let args = build_exec_detached_args("dual-lightfast-main", "pnpm dev");
+        assert_eq!(
+            args,
+            vec![
+                "exec",
+                "-d",
+                "-w",
+                "/workspace",
+                "dual-lightfast-main",
+                "sh",
+                "-c",
+                "pnpm dev",
+            ]
+        );
+    }
+
     #[test]
     fn exec_args_without_tty() {
         let args = build_exec_args("dual-lightfast-main", &["pnpm", "dev"], false);
@@ -395,4 +1588,125 @@ mod tests {
         assert_ne!(ContainerStatus::Running, ContainerStatus::Stopped);
         assert_ne!(ContainerStatus::Stopped, ContainerStatus::Missing);
     }
+
+    #[test]
+    fn reattach_volumes_rewrites_matching_destinations() {
+        let env = HashMap::new();
+        let volumes = vec!["node_modules".to_string(), ".next".to_string()];
+        let args = build_create_args(
+            "dual-new",
+            Path::new("/tmp/ws"),
+            &spec("node:20", &env, &volumes, &[]),
+        );
+        let mounts = vec![("abc123".to_string(), "/workspace/node_modules".to_string())];
+
+        let args = reattach_volumes(args, &mounts);
+        assert!(args.contains(&"abc123:/workspace/node_modules".to_string()));
+        // Volumes without an existing mount get the new container's volume
+        assert!(args.contains(&"dual-new-.next:/workspace/.next".to_string()));
+        // The bind mount is untouched
+        assert!(args.contains(&"/tmp/ws:/workspace".to_string()));
+
+        // Legacy anonymous volume args are matched too
+        let legacy = vec!["-v".to_string(), "/workspace/node_modules".to_string()];
+        let args = reattach_volumes(legacy, &mounts);
+        assert_eq!(args[1], "abc123:/workspace/node_modules");
+    }
+
+    #[test]
+    fn volume_names_are_docker_safe() {
+        assert_eq!(
+            volume_name("dual-app-main", "node_modules"),
+            "dual-app-main-node_modules"
+        );
+        let nested = volume_name("dual-app-main", "apps/web/.next/");
+        assert!(nested.starts_with("dual-app-main-apps_web_.next--"));
+        assert_eq!(nested, volume_name("dual-app-main", "apps/web/.next"));
+        // Sanitized names are tagged, so they can't collide with a literal `_`
+        assert_ne!(
+            volume_name("dual-app-main", "a/b"),
+            volume_name("dual-app-main", "a_b")
+        );
+        assert_eq!(volume_name("dual-app-main", "a_b"), "dual-app-main-a_b");
+    }
+
+    #[test]
+    fn volume_create_args_are_labelled() {
+        let args = build_volume_create_args(
+            &volume_name("dual-app-main", "node_modules"),
+            &[
+                (VOLUME_CONTAINER_LABEL, "dual-app-main"),
+                (VOLUME_DIR_LABEL, "node_modules"),
+            ],
+        );
+        assert_eq!(
+            args,
+            vec![
+                "volume",
+                "create",
+                "--label",
+                "dev.dual.container=dual-app-main",
+                "--label",
+                "dev.dual.dir=node_modules",
+                "dual-app-main-node_modules",
+            ]
+        );
+    }
+
+    #[test]
+    fn owned_volumes_filter_by_container_label() {
+        assert_eq!(
+            build_owned_volumes_args(Some("dual-app-main")),
+            vec![
+                "volume",
+                "ls",
+                "--quiet",
+                "--filter",
+                "label=dev.dual.container=dual-app-main",
+            ]
+        );
+        assert_eq!(
+            build_owned_volumes_args(None).last().unwrap(),
+            "label=dev.dual.container"
+        );
+    }
+
+    #[test]
+    fn seed_volume_args_copy_read_only_source() {
+        let args = build_seed_volume_args(
+            "node:20",
+            "dual-app-main-node_modules",
+            "dual-app-x-node_modules",
+        );
+        assert!(args.contains(&"dual-app-main-node_modules:/from:ro".to_string()));
+        assert!(args.contains(&"dual-app-x-node_modules:/to".to_string()));
+        assert_eq!(args.last().unwrap(), "cp -a /from/. /to/");
+    }
+
+    #[test]
+    fn parses_volume_sizes_from_system_df() {
+        let output = "\
+Images space usage:
+
+REPOSITORY   TAG       IMAGE ID       CREATED       SIZE      SHARED SIZE   UNIQUE SIZE   CONTAINERS
+node         20        1234567890ab   2 weeks ago   1.1GB     0B            1.1GB         2
+
+Containers space usage:
+
+CONTAINER ID   IMAGE     COMMAND            LOCAL VOLUMES   SIZE      CREATED       STATUS    NAMES
+abcdef123456   node:20   \"sleep infinity\"   1               0B        2 hours ago   Up        dual-app-main
+
+Local Volumes space usage:
+
+VOLUME NAME                  LINKS     SIZE
+dual-app-main-node_modules   1         412.3MB
+dual-app-x-.next             0         0B
+
+Build cache usage: 0B
+";
+        let sizes = parse_volume_sizes(output);
+        assert_eq!(sizes.len(), 2);
+        assert_eq!(sizes["dual-app-main-node_modules"], "412.3MB");
+        assert_eq!(sizes["dual-app-x-.next"], "0B");
+    }
 }
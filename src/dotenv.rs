@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+/// Parse dotenv file contents into ordered `(name, value)` pairs.
+///
+/// Supported syntax:
+/// - `KEY=value`, optionally prefixed with `export `; blank lines and `#` comments
+/// - unquoted values are trimmed and end at ` #` (inline comment)
+/// - `'single quoted'` values are literal and may span lines
+/// - `"double quoted"` values may span lines and understand `\n`, `\t`, `\"`, `\\`, `\$`
+/// - `${VAR}`, `${VAR:-default}` and `$VAR` in unquoted and double-quoted values
+///
+/// Interpolation only sees `vars` (variables already defined, e.g. by earlier
+/// files) and earlier lines of the same file — never the host environment,
+/// which the container doesn't share. Unknown variables expand to "".
+pub fn parse(
+    contents: &str,
+    vars: &HashMap<String, String>,
+) -> Result<Vec<(String, String)>, DotenvError> {
+    let mut defined = vars.clone();
+    let mut out = Vec::new();
+    let mut lines = contents.lines().enumerate();
+
+    while let Some((idx, line)) = lines.next() {
+        let line_no = idx + 1;
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let trimmed = trimmed
+            .strip_prefix("export")
+            .filter(|rest| rest.starts_with([' ', '\t']))
+            .map(str::trim_start)
+            .unwrap_or(trimmed);
+
+        let Some((name, rest)) = trimmed.split_once('=') else {
+            return Err(DotenvError::new(line_no, "expected KEY=value"));
+        };
+        let name = name.trim();
+        if !is_valid_name(name) {
+            return Err(DotenvError::new(
+                line_no,
+                &format!("invalid variable name '{name}'"),
+            ));
+        }
+
+        let rest = rest.trim_start();
+        let value = match rest.chars().next() {
+            Some(quote @ ('\'' | '"')) => {
+                // Gather lines until the closing quote
+                let mut raw = rest[1..].to_string();
+                let end = loop {
+                    if let Some(end) = closing_quote(&raw, quote) {
+                        break end;
+                    }
+                    match lines.next() {
+                        Some((_, next)) => {
+                            raw.push('\n');
+                            raw.push_str(next);
+                        }
+                        None => {
+                            return Err(DotenvError::new(line_no, "unterminated quoted value"));
+                        }
+                    }
+                };
+                let body = &raw[..end];
+                if quote == '\'' {
+                    body.to_string()
+                } else {
+                    interpolate(body, &defined, true)
+                }
+            }
+            _ => {
+                let value = match rest.find(" #").or_else(|| rest.find("\t#")) {
+                    Some(pos) => &rest[..pos],
+                    None => rest,
+                };
+                interpolate(value.trim(), &defined, false)
+            }
+        };
+
+        defined.insert(name.to_string(), value.clone());
+        out.push((name.to_string(), value));
+    }
+
+    Ok(out)
+}
+
+/// Whether a name is usable as an environment variable.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Byte offset of the unescaped closing quote in `s`, if any.
+fn closing_quote(s: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '\\' if quote == '"' && !escaped => escaped = true,
+            c if c == quote && !escaped => return Some(i),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// Expand `${VAR}`, `${VAR:-default}` and `$VAR` from `vars`. `\$` is a literal `$`.
+///
+/// Backslash escapes are resolved in the same pass, so an escaped backslash
+/// followed by a variable (`\\$VAR`) gives a backslash and the expanded value.
+/// In double-quoted values (`quoted`) `\n`, `\t`, `\r` and `\<char>` are
+/// resolved too; elsewhere other backslashes are kept.
+fn interpolate(s: &str, vars: &HashMap<String, String>, quoted: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(pos) = rest.find(['$', '\\']) {
+        out.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+
+        if let Some(after) = tail.strip_prefix('\\') {
+            let mut chars = after.chars();
+            match chars.next() {
+                Some('$') => out.push('$'),
+                Some('n') if quoted => out.push('\n'),
+                Some('t') if quoted => out.push('\t'),
+                Some('r') if quoted => out.push('\r'),
+                Some(c) if quoted => out.push(c),
+                _ => {
+                    out.push('\\');
+                    rest = after;
+                    continue;
+                }
+            }
+            rest = chars.as_str();
+        } else if let Some(braced) = tail.strip_prefix("${") {
+            let Some(end) = braced.find('}') else {
+                out.push_str(tail);
+                return out;
+            };
+            let expr = &braced[..end];
+            let (name, default) = match expr.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (expr, None),
+            };
+            match (vars.get(name).filter(|v| !v.is_empty()), default) {
+                (Some(value), _) => out.push_str(value),
+                (None, Some(default)) => out.push_str(default),
+                (None, None) => {}
+            }
+            rest = &braced[end + 1..];
+        } else {
+            let after = &tail[1..];
+            let len = after
+                .char_indices()
+                .find(|(i, c)| {
+                    !(c.is_ascii_alphanumeric() || *c == '_') || (*i == 0 && c.is_ascii_digit())
+                })
+                .map(|(i, _)| i)
+                .unwrap_or(after.len());
+            if len == 0 {
+                out.push('$');
+            } else if let Some(value) = vars.get(&after[..len]) {
+                out.push_str(value);
+            }
+            rest = &after[len..];
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+#[error("line {line}: {message}")]
+pub struct DotenvError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl DotenvError {
+    fn new(line: usize, message: &str) -> Self {
+        Self {
+            line,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_map(contents: &str) -> HashMap<String, String> {
+        parse(contents, &HashMap::new())
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn parses_plain_and_exported_values() {
+        let vars = parse_map(
+            "# comment\n\nA=1\nexport B = two words \nC=value # trailing comment\nexported=x\n",
+        );
+        assert_eq!(vars["A"], "1");
+        assert_eq!(vars["B"], "two words");
+        assert_eq!(vars["C"], "value");
+        assert_eq!(vars["exported"], "x");
+    }
+
+    #[test]
+    fn parses_quoted_values() {
+        let vars = parse_map(
+            "S='literal $A # not a comment'\nD=\"tab\\tquote\\\" dollar \\$A\"\nE=\"\"\n",
+        );
+        assert_eq!(vars["S"], "literal $A # not a comment");
+        assert_eq!(vars["D"], "tab\tquote\" dollar $A");
+        assert_eq!(vars["E"], "");
+    }
+
+    #[test]
+    fn escaped_backslash_before_variable_still_expands() {
+        let vars = parse_map("A=x\nB=\"\\\\$A\"\nC=\"\\\\\\$A\"\nD=a\\b\\$A\n");
+        assert_eq!(vars["B"], "\\x");
+        assert_eq!(vars["C"], "\\$A");
+        assert_eq!(vars["D"], "a\\b$A");
+    }
+
+    #[test]
+    fn parses_multiline_values() {
+        let vars = parse_map("KEY=\"-----BEGIN-----\nabc\n-----END-----\"\nNEXT='a\nb'\nLAST=1\n");
+        assert_eq!(vars["KEY"], "-----BEGIN-----\nabc\n-----END-----");
+        assert_eq!(vars["NEXT"], "a\nb");
+        assert_eq!(vars["LAST"], "1");
+    }
+
+    #[test]
+    fn interpolates_earlier_and_given_variables() {
+        let mut given = HashMap::new();
+        given.insert("HOST".to_string(), "db".to_string());
+        let vars: HashMap<_, _> = parse(
+            "PORT=5432\nURL=postgres://${HOST}:$PORT/app\nQ=\"${MISSING:-fallback}\"\nR=${MISSING}x\n",
+            &given,
+        )
+        .unwrap()
+        .into_iter()
+        .collect();
+        assert_eq!(vars["URL"], "postgres://db:5432/app");
+        assert_eq!(vars["Q"], "fallback");
+        assert_eq!(vars["R"], "x");
+        assert!(!vars.contains_key("HOST"));
+    }
+
+    #[test]
+    fn variable_names() {
+        assert!(is_valid_name("API_TOKEN"));
+        assert!(is_valid_name("_x1"));
+        assert!(!is_valid_name("1X"));
+        assert!(!is_valid_name("A-B"));
+        assert!(!is_valid_name(""));
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let err = parse("A=1\nnot a var\n", &HashMap::new()).unwrap_err();
+        assert_eq!(err.line, 2);
+
+        let err = parse("A=1\nB=\"open\nstill open\n", &HashMap::new()).unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!(err.message.contains("unterminated"));
+
+        let err = parse("1A=x\n", &HashMap::new()).unwrap_err();
+        assert!(err.message.contains("invalid variable name"));
+    }
+}
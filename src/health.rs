@@ -0,0 +1,363 @@
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::config::{HealthCheck, RepoHints};
+use crate::container::{self, ContainerError, ContainerStatus};
+
+/// Image of the throwaway container that runs checks in a workspace's network
+/// namespace. Docker pulls it on first use.
+pub const PROBE_IMAGE: &str = "busybox:stable";
+
+/// Seconds each connection attempt of a probe may take.
+const PROBE_TIMEOUT_SECS: u64 = 2;
+
+/// How often `wait` probes again.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Whether the probe image is already present locally.
+pub fn probe_image_present() -> bool {
+    Command::new("docker")
+        .args(["image", "inspect", "--format", "{{.Id}}", PROBE_IMAGE])
+        .output()
+        .is_ok_and(|out| out.status.success())
+}
+
+/// Readiness of a port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    /// The check passes
+    Healthy,
+    /// The container runs but the check doesn't pass yet
+    Starting,
+    /// The container isn't running
+    Down,
+}
+
+impl Health {
+    /// Symbol shown in `dual urls`.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Health::Healthy => "\u{25cf}",
+            Health::Starting => "\u{25d0}",
+            Health::Down => "\u{25cb}",
+        }
+    }
+}
+
+/// A readiness check for one container port.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    /// The port's name, or its number if it has none
+    pub name: String,
+    pub port: u16,
+    /// Without one, the port only has to accept connections
+    pub http: Option<HealthCheck>,
+}
+
+impl Check {
+    /// Whether a probe result line's value means the check passed.
+    fn passes(&self, result: &str) -> bool {
+        match self.http {
+            None => result == "up",
+            Some(ref http) => match (result.parse::<u16>(), http.status) {
+                (Ok(status), Some(expected)) => status == expected,
+                (Ok(status), None) => (200..400).contains(&status),
+                (Err(_), _) => false,
+            },
+        }
+    }
+}
+
+/// Checks for a workspace's `ports` and `tcp_ports`.
+pub fn checks(hints: &RepoHints) -> Vec<Check> {
+    let ports = hints.ports.iter().map(|entry| Check {
+        name: entry
+            .name()
+            .map(str::to_string)
+            .unwrap_or_else(|| entry.port().to_string()),
+        port: entry.port(),
+        http: entry.health().cloned(),
+    });
+    let tcp_ports = hints.tcp_ports.iter().map(|tcp_port| Check {
+        name: tcp_port.name(),
+        port: tcp_port.port(),
+        http: None,
+    });
+    ports.chain(tcp_ports).collect()
+}
+
+/// Health of each check, in order.
+///
+/// Ports are probed at the container's IP on `network`, where the proxy
+/// connects, so a server listening only on the container's localhost isn't
+/// reported healthy.
+pub fn check(
+    container_name: &str,
+    network: &str,
+    checks: &[Check],
+) -> Result<Vec<Health>, ContainerError> {
+    if container::status(container_name) != ContainerStatus::Running {
+        return Ok(vec![Health::Down; checks.len()]);
+    }
+    let Some(ip) = container::workspace_ip(container_name, network) else {
+        return Ok(vec![Health::Starting; checks.len()]);
+    };
+    let passed = probe(container_name, &ip, checks)?;
+    Ok(passed
+        .into_iter()
+        .map(|ok| {
+            if ok {
+                Health::Healthy
+            } else {
+                Health::Starting
+            }
+        })
+        .collect())
+}
+
+/// Poll until every check passes, calling `on_healthy` as each first does.
+///
+/// Fails at once if the container isn't running (or stops while waiting),
+/// since its ports can't come up on their own.
+pub fn wait(
+    container_name: &str,
+    network: &str,
+    checks: &[Check],
+    timeout: Duration,
+    mut on_healthy: impl FnMut(&Check),
+) -> Result<(), HealthError> {
+    let deadline = Instant::now() + timeout;
+    let mut pending = checks.to_vec();
+    loop {
+        let health = check(container_name, network, &pending)?;
+        if health.contains(&Health::Down) {
+            return Err(HealthError::NotRunning(container_name.to_string()));
+        }
+        let mut still_pending = Vec::new();
+        for (check, health) in pending.into_iter().zip(health) {
+            if health == Health::Healthy {
+                on_healthy(&check);
+            } else {
+                still_pending.push(check);
+            }
+        }
+        pending = still_pending;
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(HealthError::Timeout {
+                secs: timeout.as_secs(),
+                pending: pending.iter().map(|c| c.name.clone()).collect(),
+            });
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Run the checks against `ip` from a probe container sharing
+/// `container_name`'s network, returning whether each passed.
+fn probe(container_name: &str, ip: &str, checks: &[Check]) -> Result<Vec<bool>, ContainerError> {
+    if checks.is_empty() {
+        return Ok(Vec::new());
+    }
+    let output = Command::new("docker")
+        .args(build_probe_args(
+            container_name,
+            &build_probe_script(ip, checks),
+        ))
+        .output()
+        .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
+    if !output.status.success() {
+        return Err(ContainerError::Failed {
+            operation: "health probe".to_string(),
+            name: container_name.to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+    Ok(parse_probe_output(
+        &String::from_utf8_lossy(&output.stdout),
+        checks,
+    ))
+}
+
+/// Build the docker run arguments for a probe (for testing).
+pub fn build_probe_args(container_name: &str, script: &str) -> Vec<String> {
+    vec![
+        "run".to_string(),
+        "--rm".to_string(),
+        "--network".to_string(),
+        format!("container:{container_name}"),
+        PROBE_IMAGE.to_string(),
+        "sh".to_string(),
+        "-c".to_string(),
+        script.to_string(),
+    ]
+}
+
+/// Build the probe's shell script (for testing).
+///
+/// Prints one `{index} {result}` line per check: "up" or "down" for a
+/// connect to `ip`, or the final HTTP status (empty when unreachable).
+pub fn build_probe_script(ip: &str, checks: &[Check]) -> String {
+    let mut script = String::new();
+    for (i, check) in checks.iter().enumerate() {
+        let port = check.port;
+        let line = match check.http {
+            None => format!(
+                "if nc -z -w {PROBE_TIMEOUT_SECS} {ip} {port}; then echo '{i} up'; else echo '{i} down'; fi\n"
+            ),
+            Some(ref http) => {
+                let path = if http.path.starts_with('/') {
+                    http.path.clone()
+                } else {
+                    format!("/{}", http.path)
+                };
+                let url = format!("http://{ip}:{port}{path}").replace('\'', "'\\''");
+                format!(
+                    "echo \"{i} $(wget -S -O /dev/null -T {PROBE_TIMEOUT_SECS} '{url}' 2>&1 | awk '/^ *HTTP\\//{{c=$2}} END{{print c}}')\"\n"
+                )
+            }
+        };
+        script.push_str(&line);
+    }
+    script
+}
+
+/// Whether each check passed, from a probe's output.
+pub fn parse_probe_output(output: &str, checks: &[Check]) -> Vec<bool> {
+    let mut passed = vec![false; checks.len()];
+    for line in output.lines() {
+        let (index, result) = line.split_once(' ').unwrap_or((line, ""));
+        if let Ok(index) = index.parse::<usize>()
+            && let Some(check) = checks.get(index)
+        {
+            passed[index] = check.passes(result.trim());
+        }
+    }
+    passed
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HealthError {
+    #[error(transparent)]
+    Container(#[from] ContainerError),
+
+    #[error("container {0} is not running")]
+    NotRunning(String),
+
+    #[error("timed out after {secs}s waiting for {}", pending.join(", "))]
+    Timeout { secs: u64, pending: Vec<String> },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{PortEntry, TcpPort};
+
+    fn http(path: &str, status: Option<u16>) -> Option<HealthCheck> {
+        Some(HealthCheck {
+            path: path.to_string(),
+            status,
+        })
+    }
+
+    #[test]
+    fn checks_cover_ports_and_tcp_ports() {
+        let hints = RepoHints {
+            ports: vec![
+                3000.into(),
+                PortEntry::Service {
+                    port: 3001,
+                    name: Some("api".to_string()),
+                    path: None,
+                    open: true,
+                    http2: false,
+                    health: http("/healthz", None),
+                },
+            ],
+            tcp_ports: vec![TcpPort::Named {
+                port: 5432,
+                name: "postgres".to_string(),
+            }],
+            ..RepoHints::default()
+        };
+        let checks = checks(&hints);
+        let names: Vec<_> = checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["3000", "api", "postgres"]);
+        assert_eq!(checks[0].http, None);
+        assert_eq!(checks[1].http, http("/healthz", None));
+        assert_eq!(checks[2].port, 5432);
+    }
+
+    #[test]
+    fn probe_runs_in_workspace_network() {
+        let checks = vec![
+            Check {
+                name: "web".to_string(),
+                port: 3000,
+                http: None,
+            },
+            Check {
+                name: "api".to_string(),
+                port: 3001,
+                http: http("healthz", None),
+            },
+        ];
+        // The container's network address, not its localhost
+        let script = build_probe_script("172.18.0.2", &checks);
+        assert!(script.contains("nc -z -w 2 172.18.0.2 3000; then echo '0 up'"));
+        assert!(
+            script
+                .contains("echo \"1 $(wget -S -O /dev/null -T 2 'http://172.18.0.2:3001/healthz'")
+        );
+
+        let args = build_probe_args("dual-app-main", &script);
+        assert_eq!(
+            &args[..5],
+            &[
+                "run",
+                "--rm",
+                "--network",
+                "container:dual-app-main",
+                PROBE_IMAGE
+            ]
+        );
+        assert_eq!(args.last().unwrap(), &script);
+    }
+
+    #[test]
+    fn probe_output_judges_status() {
+        let checks = vec![
+            Check {
+                name: "web".to_string(),
+                port: 3000,
+                http: None,
+            },
+            Check {
+                name: "api".to_string(),
+                port: 3001,
+                http: http("/healthz", None),
+            },
+            Check {
+                name: "admin".to_string(),
+                port: 3002,
+                http: http("/ready", Some(204)),
+            },
+            Check {
+                name: "docs".to_string(),
+                port: 3003,
+                http: http("/", None),
+            },
+        ];
+        assert_eq!(
+            parse_probe_output("0 up\n1 302\n2 200\n3 \n", &checks),
+            vec![true, true, false, false]
+        );
+        assert_eq!(
+            parse_probe_output("0 down\n1 503\n2 204\n", &checks),
+            vec![false, false, true, false]
+        );
+    }
+}
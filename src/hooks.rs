@@ -0,0 +1,410 @@
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+use crate::config::{self, Hook, HookFailure, HookTarget, Hooks};
+use crate::container::WORKSPACE_MOUNT;
+
+/// Seconds a hook may run before it is killed, unless it sets `timeout`.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// A point in a workspace's lifecycle where hooks run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    PostClone,
+    PostCreate,
+    PreLaunch,
+    PostLaunch,
+    PreStop,
+    PreDestroy,
+    PostSync,
+}
+
+impl HookEvent {
+    /// The event's name in `[hooks]` and `DUAL_HOOK`.
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::PostClone => "post_clone",
+            HookEvent::PostCreate => "post_create",
+            HookEvent::PreLaunch => "pre_launch",
+            HookEvent::PostLaunch => "post_launch",
+            HookEvent::PreStop => "pre_stop",
+            HookEvent::PreDestroy => "pre_destroy",
+            HookEvent::PostSync => "post_sync",
+        }
+    }
+
+    /// The hooks configured for this event.
+    pub fn hooks(self, hooks: &Hooks) -> &[Hook] {
+        match self {
+            HookEvent::PostClone => &hooks.post_clone,
+            HookEvent::PostCreate => &hooks.post_create,
+            HookEvent::PreLaunch => &hooks.pre_launch,
+            HookEvent::PostLaunch => &hooks.post_launch,
+            HookEvent::PreStop => &hooks.pre_stop,
+            HookEvent::PreDestroy => &hooks.pre_destroy,
+            HookEvent::PostSync => &hooks.post_sync,
+        }
+    }
+}
+
+impl std::fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The workspace hooks run for.
+pub struct HookContext<'a> {
+    pub repo: &'a str,
+    pub branch: &'a str,
+    pub workspace_dir: &'a Path,
+    /// Whether the user allowed this repo's hooks to run on the host.
+    pub host_trusted: bool,
+    /// Whether the workspace's container is running; container hooks are
+    /// skipped (with a warning) when it isn't.
+    pub container_running: bool,
+    /// Report `abort` failures as warnings instead of stopping (`--force`).
+    pub force: bool,
+}
+
+impl HookContext<'_> {
+    /// Variables describing the workspace, as a hook at `target` sees them.
+    pub fn env(&self, event: HookEvent, target: HookTarget) -> Vec<(String, String)> {
+        let dir = match target {
+            HookTarget::Host => self.workspace_dir.display().to_string(),
+            HookTarget::Container => WORKSPACE_MOUNT.to_string(),
+        };
+        vec![
+            ("DUAL_HOOK".to_string(), event.name().to_string()),
+            (
+                "DUAL_WORKSPACE_ID".to_string(),
+                config::workspace_id(self.repo, self.branch),
+            ),
+            ("DUAL_REPO".to_string(), self.repo.to_string()),
+            ("DUAL_BRANCH".to_string(), self.branch.to_string()),
+            (
+                "DUAL_CONTAINER".to_string(),
+                config::container_name(self.repo, self.branch),
+            ),
+            ("DUAL_WORKSPACE_DIR".to_string(), dir),
+        ]
+    }
+}
+
+/// Run an event's hooks in order, with their output going to the terminal.
+///
+/// Failures of `warn` hooks, and container hooks skipped because the container
+/// isn't running, are returned for the caller to report; the first failing
+/// `abort` hook stops the run and is returned as the error, unless forced.
+pub fn run(
+    event: HookEvent,
+    hooks: &Hooks,
+    ctx: &HookContext,
+) -> Result<Vec<HookError>, HookError> {
+    let mut warnings = Vec::new();
+    for hook in event.hooks(hooks) {
+        if hook.target == HookTarget::Container && !ctx.container_running {
+            warnings.push(HookError {
+                event,
+                command: hook.run.clone(),
+                reason: "skipped, the container is not running".to_string(),
+            });
+            continue;
+        }
+        if let Err(e) = run_hook(event, hook, ctx) {
+            match hook.on_failure {
+                HookFailure::Abort if !ctx.force => return Err(e),
+                HookFailure::Warn | HookFailure::Abort => warnings.push(e),
+            }
+        }
+    }
+    Ok(warnings)
+}
+
+/// Run one hook, killing it once its timeout passes.
+///
+/// For container hooks, the timeout kills the `docker exec` client; a command
+/// that ignores the lost connection keeps running in the container. Host hooks
+/// come from a committed `.dual.toml`, so they fail unless the repo is trusted.
+fn run_hook(event: HookEvent, hook: &Hook, ctx: &HookContext) -> Result<(), HookError> {
+    let failed = |reason: String| HookError {
+        event,
+        command: hook.run.clone(),
+        reason,
+    };
+
+    if hook.target == HookTarget::Host && !ctx.host_trusted {
+        return Err(failed(format!(
+            "host hooks are not allowed for repo '{}' (add it to host_hooks in ~/.dual/workspaces.toml)",
+            ctx.repo
+        )));
+    }
+
+    let env = ctx.env(event, hook.target);
+    let mut cmd = match hook.target {
+        HookTarget::Host => {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", &hook.run]).envs(env);
+            if ctx.workspace_dir.is_dir() {
+                cmd.current_dir(ctx.workspace_dir);
+            }
+            cmd
+        }
+        HookTarget::Container => {
+            let container = config::container_name(ctx.repo, ctx.branch);
+            let mut cmd = Command::new("docker");
+            cmd.args(build_container_args(&container, &hook.run, &env));
+            cmd
+        }
+    };
+
+    let mut child = cmd.spawn().map_err(|e| failed(e.to_string()))?;
+    let timeout = Duration::from_secs(hook.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    match wait_timeout(&mut child, timeout).map_err(|e| failed(e.to_string()))? {
+        Some(status) if status.success() => Ok(()),
+        Some(status) => Err(failed(status.to_string())),
+        None => Err(failed(format!("timed out after {}s", timeout.as_secs()))),
+    }
+}
+
+/// Wait for a child to exit, killing it after `timeout`. `None` means it timed out.
+fn wait_timeout(child: &mut Child, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Build docker exec arguments for a container hook (for testing).
+pub fn build_container_args(container: &str, run: &str, env: &[(String, String)]) -> Vec<String> {
+    let mut args = vec![
+        "exec".to_string(),
+        "-w".to_string(),
+        WORKSPACE_MOUNT.to_string(),
+    ];
+    for (key, value) in env {
+        args.push("-e".to_string());
+        args.push(format!("{key}={value}"));
+    }
+    args.extend([
+        container.to_string(),
+        "sh".to_string(),
+        "-c".to_string(),
+        run.to_string(),
+    ]);
+    args
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{event} hook `{command}` failed: {reason}")]
+pub struct HookError {
+    pub event: HookEvent,
+    pub command: String,
+    pub reason: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_dir(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dual-test-hooks-{test_name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn hook(run: &str, on_failure: HookFailure) -> Hook {
+        Hook {
+            run: run.to_string(),
+            target: HookTarget::Host,
+            timeout: None,
+            on_failure,
+        }
+    }
+
+    #[test]
+    fn host_hooks_run_in_workspace_with_metadata() {
+        let dir = temp_dir("host");
+        let hooks = Hooks {
+            post_clone: vec![hook(
+                "echo \"$DUAL_HOOK $DUAL_WORKSPACE_ID $DUAL_BRANCH\" > hook.txt",
+                HookFailure::Abort,
+            )],
+            ..Hooks::default()
+        };
+        let ctx = HookContext {
+            repo: "app",
+            branch: "main",
+            workspace_dir: &dir,
+            host_trusted: true,
+            container_running: true,
+            force: false,
+        };
+
+        let warnings = run(HookEvent::PostClone, &hooks, &ctx).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(
+            fs::read_to_string(dir.join("hook.txt")).unwrap(),
+            "post_clone app-main main\n"
+        );
+
+        // Other events don't run it
+        fs::remove_file(dir.join("hook.txt")).unwrap();
+        run(HookEvent::PostSync, &hooks, &ctx).unwrap();
+        assert!(!dir.join("hook.txt").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failure_policy() {
+        let dir = temp_dir("policy");
+        let ctx = HookContext {
+            repo: "app",
+            branch: "main",
+            workspace_dir: &dir,
+            host_trusted: true,
+            container_running: true,
+            force: false,
+        };
+
+        let hooks = Hooks {
+            pre_destroy: vec![
+                hook("exit 3", HookFailure::Warn),
+                hook("touch ran", HookFailure::Warn),
+            ],
+            ..Hooks::default()
+        };
+        let warnings = run(HookEvent::PreDestroy, &hooks, &ctx).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].reason.contains('3'));
+        assert!(dir.join("ran").exists());
+
+        let hooks = Hooks {
+            pre_destroy: vec![
+                hook("exit 1", HookFailure::Abort),
+                hook("touch never", HookFailure::Warn),
+            ],
+            ..Hooks::default()
+        };
+        let err = run(HookEvent::PreDestroy, &hooks, &ctx).unwrap_err();
+        assert_eq!(err.event, HookEvent::PreDestroy);
+        assert!(!dir.join("never").exists());
+
+        // Forcing reports the abort and goes on
+        let forced = HookContext { force: true, ..ctx };
+        let warnings = run(HookEvent::PreDestroy, &hooks, &forced).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(dir.join("never").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn host_hooks_need_trust() {
+        let dir = temp_dir("untrusted");
+        let hooks = Hooks {
+            post_clone: vec![hook("touch ran", HookFailure::Abort)],
+            ..Hooks::default()
+        };
+        let ctx = HookContext {
+            repo: "app",
+            branch: "main",
+            workspace_dir: &dir,
+            host_trusted: false,
+            container_running: true,
+            force: false,
+        };
+
+        let err = run(HookEvent::PostClone, &hooks, &ctx).unwrap_err();
+        assert!(err.reason.contains("host_hooks"));
+        assert!(!dir.join("ran").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn container_hooks_skip_stopped_container() {
+        let dir = temp_dir("stopped");
+        let mut in_container = hook("exit 1", HookFailure::Abort);
+        in_container.target = HookTarget::Container;
+        let hooks = Hooks {
+            pre_destroy: vec![in_container, hook("touch ran", HookFailure::Abort)],
+            ..Hooks::default()
+        };
+        let ctx = HookContext {
+            repo: "app",
+            branch: "main",
+            workspace_dir: &dir,
+            host_trusted: true,
+            container_running: false,
+            force: false,
+        };
+
+        // The container hook is skipped, not failed; host hooks still run
+        let warnings = run(HookEvent::PreDestroy, &hooks, &ctx).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].reason.contains("not running"));
+        assert!(dir.join("ran").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn slow_hooks_time_out() {
+        let dir = temp_dir("timeout");
+        let mut slow = hook("sleep 5", HookFailure::Abort);
+        slow.timeout = Some(0);
+        let hooks = Hooks {
+            pre_launch: vec![slow],
+            ..Hooks::default()
+        };
+        let ctx = HookContext {
+            repo: "app",
+            branch: "main",
+            workspace_dir: &dir,
+            host_trusted: true,
+            container_running: true,
+            force: false,
+        };
+
+        let err = run(HookEvent::PreLaunch, &hooks, &ctx).unwrap_err();
+        assert!(err.reason.contains("timed out"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn container_hook_args() {
+        let ctx = HookContext {
+            repo: "app",
+            branch: "main",
+            workspace_dir: Path::new("/home/u/app"),
+            host_trusted: false,
+            container_running: true,
+            force: false,
+        };
+        let env = ctx.env(HookEvent::PostCreate, HookTarget::Container);
+        let args = build_container_args("dual-app-main", "pnpm db:seed", &env);
+
+        assert_eq!(&args[..3], &["exec", "-w", "/workspace"]);
+        assert!(args.contains(&"DUAL_HOOK=post_create".to_string()));
+        assert!(args.contains(&"DUAL_WORKSPACE_DIR=/workspace".to_string()));
+        assert_eq!(
+            &args[args.len() - 4..],
+            &["dual-app-main", "sh", "-c", "pnpm db:seed"]
+        );
+    }
+}
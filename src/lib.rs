@@ -1,11 +1,22 @@
+pub mod access_log;
+pub mod archive;
 pub mod backend;
+pub mod cache;
 pub mod cli;
 pub mod clone;
+pub mod compose;
 pub mod config;
 pub mod container;
+pub mod dotenv;
+pub mod health;
+pub mod hooks;
 pub mod proxy;
+pub mod secrets;
+pub mod services;
+pub mod setup;
 pub mod shared;
 pub mod shell;
 pub mod state;
+pub mod tls;
 pub mod tmux_backend;
 pub mod tui;
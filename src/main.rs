@@ -1,12 +1,23 @@
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use clap::Parser;
+use dual::access_log;
+use dual::archive;
 use dual::backend::MultiplexerBackend;
-use dual::cli::{Cli, Command};
+use dual::cache;
+use dual::cli::{CacheAction, Cli, Command, ProxyAction, SecretsAction, TopSort, VolumesAction};
 use dual::clone;
 use dual::config;
 use dual::container;
+use dual::dotenv;
+use dual::health::{self, Health};
+use dual::hooks::{self, HookEvent};
 use dual::proxy;
+use dual::secrets;
+use dual::services;
+use dual::setup;
 use dual::shared;
 use dual::shell;
 use dual::state;
@@ -40,13 +51,65 @@ fn main() {
         None => cmd_default(&backend),
         Some(Command::Add { name }) => cmd_add(name.as_deref()),
         Some(Command::Create { branch, repo }) => cmd_create(repo.as_deref(), &branch),
-        Some(Command::Launch { workspace }) => cmd_launch(workspace.as_deref(), &backend),
+        Some(Command::Launch { workspace, wait }) => {
+            cmd_launch(workspace.as_deref(), wait, &backend)
+        }
         Some(Command::List) => cmd_list(&backend),
-        Some(Command::Destroy { workspace }) => cmd_destroy(workspace.as_deref(), &backend),
-        Some(Command::Open { workspace }) => cmd_open(workspace),
+        Some(Command::Destroy {
+            workspace,
+            force,
+            archive,
+        }) => cmd_destroy(workspace.as_deref(), force, archive, &backend),
+        Some(Command::Rename {
+            workspace,
+            new_branch,
+        }) => cmd_rename(&workspace, &new_branch, &backend),
+        Some(Command::Migrate) => cmd_migrate(&backend),
+        Some(Command::Restore { archive }) => cmd_restore(archive.as_deref()),
+        Some(Command::Open {
+            workspace,
+            service,
+            no_wait,
+        }) => cmd_open(workspace, service, no_wait),
+        Some(Command::Wait {
+            workspace,
+            service,
+            timeout,
+        }) => cmd_wait(workspace, service, timeout),
         Some(Command::Urls { workspace }) => cmd_urls(workspace),
-        Some(Command::Sync { workspace }) => cmd_sync(workspace),
-        Some(Command::Proxy) => cmd_proxy(),
+        Some(Command::Sync {
+            workspace,
+            status,
+            push,
+            diff,
+            dry_run,
+            force,
+        }) => {
+            if status {
+                cmd_sync_status(workspace)
+            } else if dry_run {
+                cmd_sync_dry_run(workspace)
+            } else if diff {
+                cmd_sync_diff(workspace)
+            } else {
+                cmd_sync(workspace, shared::SyncOptions { push, force })
+            }
+        }
+        Some(Command::Secrets { action }) => cmd_secrets(action),
+        Some(Command::Env { workspace, reveal }) => cmd_env(workspace, reveal),
+        Some(Command::Recreate { workspace, force }) => cmd_recreate(workspace, force),
+        Some(Command::Setup { workspace, rerun }) => cmd_setup(workspace, rerun),
+        Some(Command::Volumes { action }) => cmd_volumes(action),
+        Some(Command::Cache { action }) => cmd_cache(action),
+        Some(Command::Top {
+            sort,
+            interval,
+            once,
+        }) => cmd_top(sort, interval, once),
+        Some(Command::Proxy { action: None }) => cmd_proxy(),
+        Some(Command::Proxy {
+            action: Some(ProxyAction::Log { workspace, follow }),
+        }) => cmd_proxy_log(workspace, follow),
         Some(Command::ShellRc { container }) => cmd_shell_rc(&container),
     };
 
@@ -84,7 +147,7 @@ fn cmd_default(backend: &dyn MultiplexerBackend) -> i32 {
         match tui::run(&st, backend) {
             Ok(Some(workspace_id)) => {
                 // TUI already called ratatui::restore() — terminal is in normal mode
-                let exit_code = cmd_launch(Some(&workspace_id), backend);
+                let exit_code = cmd_launch(Some(&workspace_id), false, backend);
 
                 if inside_tmux {
                     // switch-client is instant — don't loop back to TUI
@@ -156,9 +219,16 @@ fn cmd_add(name: Option<&str>) -> i32 {
     if let Some(ref shared_config) = hints.shared
         && !shared_config.files.is_empty()
     {
-        match shared::ensure_shared_dir(&repo_name) {
-            Ok(shared_dir) => {
-                match shared::init_from_main(&repo_root, &shared_dir, &shared_config.files) {
+        match shared::ensure_shared_dir(&repo_name)
+            .map_err(|e| e.to_string())
+            .and_then(|dir| {
+                store_key(shared_config)
+                    .map(|key| (dir, key))
+                    .map_err(|e| e.to_string())
+            }) {
+            Ok((shared_dir, key)) => {
+                let store = shared::Store::new(&shared_dir, key.as_ref());
+                match shared::init_from_main(&repo_root, store, &shared_config.files) {
                     Ok(moved) => {
                         for f in &moved {
                             info!("  shared: {f} → ~/.dual/shared/{repo_name}/");
@@ -264,7 +334,7 @@ fn cmd_create(repo_arg: Option<&str>, branch: &str) -> i32 {
 }
 
 /// Launch a specific workspace: clone → container → shell RC → tmux → attach.
-fn cmd_launch(workspace_arg: Option<&str>, backend: &dyn MultiplexerBackend) -> i32 {
+fn cmd_launch(workspace_arg: Option<&str>, wait: bool, backend: &dyn MultiplexerBackend) -> i32 {
     let st = match state::load() {
         Ok(s) => s,
         Err(e) => {
@@ -302,6 +372,12 @@ fn cmd_launch(workspace_arg: Option<&str>, backend: &dyn MultiplexerBackend) ->
         }
     };
 
+    if has_legacy_artifacts(&st, entry) {
+        error!("workspace uses the old naming scheme");
+        info!("Run `dual migrate` to rename its clone, container and session.");
+        return 1;
+    }
+
     let workspace_root = st.workspace_root();
     let container_name = config::container_name(&entry.repo, &entry.branch);
     let session_name = config::session_name(&entry.repo, &entry.branch);
@@ -312,6 +388,8 @@ fn cmd_launch(workspace_arg: Option<&str>, backend: &dyn MultiplexerBackend) ->
     );
 
     // Step 1: Resolve workspace directory
+    let cloned = entry.path.is_none()
+        && !clone::workspace_exists(&workspace_root, &entry.repo, &entry.branch);
     let workspace_dir = if let Some(ref path) = entry.path {
         let dir = PathBuf::from(path);
         if !dir.join(".git").exists() {
@@ -366,44 +444,55 @@ fn cmd_launch(workspace_arg: Option<&str>, backend: &dyn MultiplexerBackend) ->
         && !shared_config.files.is_empty()
         && let Ok(shared_dir) = shared::ensure_shared_dir(&entry.repo)
     {
-        if entry.path.is_some() {
-            // Main workspace: ensure shared files are initialized
-            match shared::init_from_main(&workspace_dir, &shared_dir, &shared_config.files) {
-                Ok(moved) => {
-                    for f in &moved {
-                        info!("  shared: {f} → ~/.dual/shared/{}/", entry.repo);
+        match store_key(shared_config) {
+            Ok(key) => {
+                let store = shared::Store::new(&shared_dir, key.as_ref());
+                if entry.path.is_some() {
+                    // Main workspace: ensure shared files are initialized
+                    match shared::init_from_main(&workspace_dir, store, &shared_config.files) {
+                        Ok(moved) => {
+                            for f in &moved {
+                                info!("  shared: {f} → ~/.dual/shared/{}/", entry.repo);
+                            }
+                        }
+                        Err(e) => warn!("shared init failed: {e}"),
                     }
                 }
-                Err(e) => warn!("shared init failed: {e}"),
-            }
-        } else {
-            // Branch workspace: copy shared files
-            match shared::copy_to_branch(&workspace_dir, &shared_dir, &shared_config.files) {
-                Ok(copied) => {
-                    for f in &copied {
-                        info!("  shared: copied {f}");
+                if entry.path.is_none() || store.is_encrypted() {
+                    // Branch (or encrypted) workspace: pull shared files, keeping local edits
+                    let ws_id = config::workspace_id(&entry.repo, &entry.branch);
+                    match shared::sync_workspace(
+                        &workspace_dir,
+                        store,
+                        &ws_id,
+                        shared_config,
+                        shared::SyncOptions::default(),
+                    ) {
+                        Ok(results) => report_sync_results(&results),
+                        Err(e) => warn!("shared copy failed: {e}"),
                     }
                 }
-                Err(e) => warn!("shared copy failed: {e}"),
             }
+            Err(e) => warn!("could not unlock encrypted shared store: {e}"),
         }
     }
+    if cloned && !run_hooks(&st, HookEvent::PostClone, &hints, entry, &workspace_dir) {
+        return 1;
+    }
+    if !run_hooks(&st, HookEvent::PreLaunch, &hints, entry, &workspace_dir) {
+        return 1;
+    }
 
     // Step 3: Ensure container exists and is running
-    let is_new_container = matches!(
-        container::status(&container_name),
-        container::ContainerStatus::Missing
-    );
-    match container::status(&container_name) {
+    let ws_id = config::workspace_id(&entry.repo, &entry.branch);
+    let spec = container_spec(&st, entry, &hints, &workspace_dir);
+    let status = container::status(&container_name);
+    let was_running = status == container::ContainerStatus::Running;
+    match status {
         container::ContainerStatus::Missing => {
             info!("Creating container {container_name}...");
-            if let Err(e) = container::create(
-                &container_name,
-                &workspace_dir,
-                &hints.image,
-                &hints.env,
-                &hints.anonymous_volumes,
-            ) {
+            create_cache_volumes(entry, &hints);
+            if let Err(e) = container::create(&container_name, &workspace_dir, &spec) {
                 error!("container create failed: {e}");
                 return 1;
             }
@@ -412,25 +501,50 @@ fn cmd_launch(workspace_arg: Option<&str>, backend: &dyn MultiplexerBackend) ->
                 return 1;
             }
         }
-        container::ContainerStatus::Stopped => {
-            info!("Starting container {container_name}...");
-            if let Err(e) = container::start(&container_name) {
-                error!("container start failed: {e}");
-                return 1;
+        status => {
+            if config_drifted(&container_name, &workspace_dir, &spec) {
+                warn!("{container_name} was created with different settings than .dual.toml");
+                info!("Run `dual recreate {ws_id}` to apply them (volumes are kept).");
+            }
+            if status == container::ContainerStatus::Stopped {
+                info!("Starting container {container_name}...");
+                if let Err(e) = container::start(&container_name) {
+                    error!("container start failed: {e}");
+                    return 1;
+                }
             }
         }
-        container::ContainerStatus::Running => {}
     }
 
-    // Step 3.5: Run setup command on new containers
-    if is_new_container && let Some(ref setup) = hints.setup {
-        info!("Running setup: {setup}");
-        if let Err(e) = container::exec_setup(&container_name, setup) {
-            error!("setup failed: {e}");
-            return 1;
+    // Sidecar services, restarted if the container wasn't running (they share its network)
+    services::start_all(entry, &hints, &workspace_dir, !was_running);
+
+    // Host ports for `tcp_ports`, forwarded by `dual proxy`
+    if !hints.tcp_ports.is_empty() {
+        let mut latest = match state::load() {
+            Ok(s) => s,
+            Err(e) => {
+                error!("{e}");
+                return 1;
+            }
+        };
+        if allocate_tcp_ports(&mut latest, &ws_id, &hints)
+            && let Err(e) = state::save(&latest)
+        {
+            warn!("failed to save state: {e}");
         }
     }
 
+    // Step 3.5: Run setup steps that haven't succeeded in this container yet
+    if let Err(e) = run_setup(&container_name, &ws_id, &workspace_dir, &hints, false) {
+        error!("{e}");
+        info!("Fix it and run `dual setup {ws_id}`, or launch again to retry.");
+        return 1;
+    }
+    if !run_post_create(&st, &hints, entry, &workspace_dir) {
+        return 1;
+    }
+
     // Step 4: Write shell RC file
     let rc_path = match shell::write_rc_file(&container_name, &hints.extra_commands) {
         Ok(p) => p,
@@ -448,6 +562,21 @@ fn cmd_launch(workspace_arg: Option<&str>, backend: &dyn MultiplexerBackend) ->
             return 1;
         }
     }
+    if let Err(e) = backend.tag_session(&session_name, &ws_id) {
+        warn!("could not tag session {session_name}: {e}");
+    }
+    if !run_hooks(&st, HookEvent::PostLaunch, &hints, entry, &workspace_dir) {
+        return 1;
+    }
+
+    // Step 5.5: With --wait, hold off until the ports answer (e.g. a dev server
+    // started by a hook), attaching anyway if they don't
+    if wait
+        && let Err(e) =
+            wait_for_health(&st, &ws_id, |_| true, Duration::from_secs(LAUNCH_WAIT_SECS))
+    {
+        warn!("{ws_id}: {e}; attaching anyway");
+    }
 
     // Step 6: Attach
     info!("Attaching to {session_name}...");
@@ -459,29 +588,189 @@ fn cmd_launch(workspace_arg: Option<&str>, backend: &dyn MultiplexerBackend) ->
     0
 }
 
-/// List all configured workspaces with their live status.
-fn cmd_list(backend: &dyn MultiplexerBackend) -> i32 {
-    let st = match state::load() {
-        Ok(s) => s,
+/// Allocate host ports for a workspace's `tcp_ports` that don't have one yet.
+/// Returns true if any were allocated.
+fn allocate_tcp_ports(
+    st: &mut state::WorkspaceState,
+    workspace_id: &str,
+    hints: &config::RepoHints,
+) -> bool {
+    let mut allocated = false;
+    for tcp_port in &hints.tcp_ports {
+        if st.tcp_port(workspace_id, tcp_port.port()).is_some() {
+            continue;
+        }
+        let is_free = |port| std::net::TcpListener::bind(("127.0.0.1", port)).is_ok();
+        match st.allocate_tcp_port(workspace_id, tcp_port.port(), is_free) {
+            Some(host_port) => {
+                info!("{workspace_id} {} → 127.0.0.1:{host_port}", tcp_port.name());
+                allocated = true;
+            }
+            None => warn!("no free host port for {workspace_id} {}", tcp_port.name()),
+        }
+    }
+    allocated
+}
+
+/// Stop and remove a workspace's sidecars (keeping their volumes), returning
+/// each one's service and volume mounts.
+fn remove_services(ws_id: &str) -> Vec<(String, Vec<(String, String)>)> {
+    services::sidecars(ws_id)
+        .into_iter()
+        .filter_map(|sidecar| {
+            let service = sidecar.service?;
+            let mounts = container::volume_mounts(&sidecar.name);
+            if let Err(e) = services::remove(&sidecar.name) {
+                warn!("service {service} remove failed: {e}");
+            }
+            Some((service, mounts))
+        })
+        .collect()
+}
+
+/// Recreate sidecars taken down by `remove_services` for `container_name`,
+/// reusing their volumes.
+fn recreate_services(
+    container_name: &str,
+    services: &BTreeMap<String, config::ServiceConfig>,
+    labels: &[(String, String)],
+    moved: &[(String, Vec<(String, String)>)],
+) {
+    for (service, mounts) in moved {
+        if let Some(svc) = services.get(service)
+            && let Err(e) = services::create(container_name, service, svc, labels, mounts)
+        {
+            warn!("service {service} could not be recreated: {e}");
+        }
+    }
+}
+
+/// Run a workspace's hooks for `event`, warning about those that failed with
+/// `on_failure = "warn"`. Returns false if one failed with `on_failure = "abort"`.
+fn run_hooks(
+    st: &state::WorkspaceState,
+    event: HookEvent,
+    hints: &config::RepoHints,
+    entry: &state::WorkspaceEntry,
+    workspace_dir: &Path,
+) -> bool {
+    run_hooks_forced(st, event, hints, entry, workspace_dir, false)
+}
+
+/// `run_hooks`, where `force` turns `abort` failures into warnings.
+fn run_hooks_forced(
+    st: &state::WorkspaceState,
+    event: HookEvent,
+    hints: &config::RepoHints,
+    entry: &state::WorkspaceEntry,
+    workspace_dir: &Path,
+    force: bool,
+) -> bool {
+    if event.hooks(&hints.hooks).is_empty() {
+        return true;
+    }
+    info!("Running {event} hooks...");
+    let ctx = hook_context(st, entry, workspace_dir, force);
+    match hooks::run(event, &hints.hooks, &ctx) {
+        Ok(warnings) => {
+            for w in &warnings {
+                warn!("{w}");
+            }
+            true
+        }
         Err(e) => {
             error!("{e}");
-            return 1;
+            false
         }
+    }
+}
+
+/// Context for running a workspace's hooks.
+fn hook_context<'a>(
+    st: &state::WorkspaceState,
+    entry: &'a state::WorkspaceEntry,
+    workspace_dir: &'a Path,
+    force: bool,
+) -> hooks::HookContext<'a> {
+    let container_name = config::container_name(&entry.repo, &entry.branch);
+    hooks::HookContext {
+        repo: &entry.repo,
+        branch: &entry.branch,
+        workspace_dir,
+        host_trusted: st.host_hooks.contains(&entry.repo),
+        container_running: container::status(&container_name)
+            == container::ContainerStatus::Running,
+        force,
+    }
+}
+
+/// Run the `post_create` hooks unless they already succeeded in the workspace's
+/// current container, recording success in its setup status. A launch that
+/// fails before or during them runs them again next time.
+fn run_post_create(
+    st: &state::WorkspaceState,
+    hints: &config::RepoHints,
+    entry: &state::WorkspaceEntry,
+    workspace_dir: &Path,
+) -> bool {
+    if hints.hooks.post_create.is_empty() {
+        return true;
+    }
+    let ws_id = config::workspace_id(&entry.repo, &entry.branch);
+    let container_name = config::container_name(&entry.repo, &entry.branch);
+    let Some(status_path) = setup::log_dir(&ws_id).map(|dir| setup::status_path(&dir)) else {
+        return run_hooks(st, HookEvent::PostCreate, hints, entry, workspace_dir);
     };
+    let container_id = container::id(&container_name).unwrap_or_default();
+    let mut status = setup::SetupStatus::load(&status_path)
+        .unwrap_or_else(|e| {
+            warn!("{e}");
+            setup::SetupStatus::default()
+        })
+        .for_container(&container_id);
+    if status.post_create {
+        return true;
+    }
 
-    let workspaces = st.all_workspaces();
-    if workspaces.is_empty() {
-        info!("No workspaces configured.");
-        return 0;
+    info!("Running {} hooks...", HookEvent::PostCreate);
+    let ctx = hook_context(st, entry, workspace_dir, false);
+    match hooks::run(HookEvent::PostCreate, &hints.hooks, &ctx) {
+        Ok(warnings) if !warnings.is_empty() => {
+            // A failed `warn` hook doesn't stop the launch, but still counts
+            // as not succeeded, so the next launch runs them again
+            for w in &warnings {
+                warn!("{w}");
+            }
+            return true;
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!("{e}");
+            return false;
+        }
     }
+    status.post_create = true;
+    if let Err(e) = status.save(&status_path) {
+        warn!("could not record post_create status: {e}");
+    }
+    true
+}
 
-    print_workspace_status(&st, backend);
-    0
+/// Whether an existing container's fingerprint differs from the current config.
+///
+/// Containers created before fingerprints were recorded count as drifted.
+fn config_drifted(
+    container_name: &str,
+    workspace_dir: &Path,
+    spec: &container::ContainerSpec,
+) -> bool {
+    let expected = container::build_create_args(container_name, workspace_dir, spec);
+    container::fingerprint(container_name).as_deref() != container::args_fingerprint(&expected)
 }
 
-/// Destroy a workspace: tmux → container → clone.
-fn cmd_destroy(workspace_arg: Option<&str>, backend: &dyn MultiplexerBackend) -> i32 {
-    let mut st = match state::load() {
+/// Recreate a workspace's container from the current config, keeping its volumes.
+fn cmd_recreate(workspace_arg: Option<String>, force: bool) -> i32 {
+    let st = match state::load() {
         Ok(s) => s,
         Err(e) => {
             error!("{e}");
@@ -489,11 +778,8 @@ fn cmd_destroy(workspace_arg: Option<&str>, backend: &dyn MultiplexerBackend) ->
         }
     };
 
-    // Resolve workspace: use arg if provided, otherwise auto-detect from cwd
-    let workspace;
     let entry = if let Some(ws) = workspace_arg {
-        workspace = ws.to_string();
-        match st.resolve_workspace(ws) {
+        match st.resolve_workspace(&ws) {
             Some(e) => e.clone(),
             None => {
                 error!("unknown workspace '{ws}'");
@@ -502,125 +788,109 @@ fn cmd_destroy(workspace_arg: Option<&str>, backend: &dyn MultiplexerBackend) ->
         }
     } else {
         match detect_workspace(&st) {
-            Some(e) => {
-                workspace = config::workspace_id(&e.repo, &e.branch);
-                info!("Auto-detected workspace: {workspace}");
-                e
-            }
+            Some(e) => e,
             None => {
-                error!("could not detect workspace from current directory");
-                info!("Usage: dual destroy [workspace]");
+                error!("not inside a dual workspace");
+                info!("Usage: dual recreate [workspace]");
                 return 1;
             }
         }
     };
 
-    let workspace_root = st.workspace_root();
     let container_name = config::container_name(&entry.repo, &entry.branch);
-    let session_name = config::session_name(&entry.repo, &entry.branch);
-
-    // Destroy tmux session
-    if backend.is_alive(&session_name) {
-        info!("Destroying session {session_name}...");
-        if let Err(e) = backend.destroy(&session_name) {
-            warn!("session destroy failed: {e}");
-        }
+    if container::status(&container_name) == container::ContainerStatus::Missing {
+        info!("{container_name} does not exist; `dual launch` will create it.");
+        return 0;
     }
 
-    // Stop and remove container
-    match container::status(&container_name) {
-        container::ContainerStatus::Running => {
-            info!("Stopping container {container_name}...");
-            if let Err(e) = container::stop(&container_name) {
-                warn!("container stop failed: {e}");
-            }
-            info!("Removing container {container_name}...");
-            if let Err(e) = container::destroy(&container_name) {
-                warn!("container remove failed: {e}");
-            }
-        }
-        container::ContainerStatus::Stopped => {
-            info!("Removing container {container_name}...");
-            if let Err(e) = container::destroy(&container_name) {
-                warn!("container remove failed: {e}");
-            }
-        }
-        container::ContainerStatus::Missing => {}
+    let workspace_dir = st.workspace_dir(&entry);
+    let hints = config::load_hints(&workspace_dir).unwrap_or_default();
+    let spec = container_spec(&st, &entry, &hints, &workspace_dir);
+
+    if !force && !config_drifted(&container_name, &workspace_dir, &spec) {
+        info!("{container_name} is up to date with .dual.toml (use --force to recreate anyway)");
+        return 0;
     }
 
-    // Remove clone (only for non-explicit-path workspaces)
-    if entry.path.is_none() && clone::workspace_exists(&workspace_root, &entry.repo, &entry.branch)
+    if container::status(&container_name) == container::ContainerStatus::Running
+        && !run_hooks(&st, HookEvent::PreStop, &hints, &entry, &workspace_dir)
     {
-        info!("Removing clone...");
-        if let Err(e) = clone::remove_workspace(&workspace_root, &entry.repo, &entry.branch) {
-            error!("failed to remove clone: {e}");
-            return 1;
-        }
+        return 1;
     }
 
-    // Remove from state
-    st.remove_workspace(&entry.repo, &entry.branch);
-    if let Err(e) = state::save(&st) {
-        warn!("failed to save state: {e}");
+    info!("Recreating container {container_name}...");
+    remove_services(&config::workspace_id(&entry.repo, &entry.branch));
+    create_cache_volumes(&entry, &hints);
+    if let Err(e) = container::recreate(&container_name, &workspace_dir, &spec) {
+        error!("recreate failed: {e}");
+        return 1;
+    }
+    if container::status(&container_name) == container::ContainerStatus::Running {
+        services::start_all(&entry, &hints, &workspace_dir, false);
     }
 
-    info!("Workspace '{workspace}' destroyed.");
+    info!("Recreated {container_name}");
     0
 }
 
-/// Open workspace services in the default browser.
-fn cmd_open(workspace: Option<String>) -> i32 {
-    let st = match state::load() {
-        Ok(s) => s,
-        Err(e) => {
-            error!("{e}");
-            return 1;
-        }
-    };
-
-    let url_groups = proxy::workspace_urls(&st);
-    if url_groups.is_empty() {
-        info!("No URLs configured. Add 'ports' to .dual.toml in your repo.");
-        return 0;
+/// Run a workspace's setup steps in its container: the pending ones (never run,
+/// failed, or changed), or all of them with `rerun_all`. Output is streamed and
+/// logged to ~/.dual/logs/{workspace}/. Stops at the first failing step.
+///
+/// Returns how many steps ran.
+fn run_setup(
+    container_name: &str,
+    ws_id: &str,
+    workspace_dir: &Path,
+    hints: &config::RepoHints,
+    rerun_all: bool,
+) -> Result<usize, String> {
+    let steps = hints.setup_plan();
+    if steps.is_empty() {
+        return Ok(0);
     }
-
-    // Filter by workspace if specified
-    let filtered: Vec<_> = match &workspace {
-        Some(ws) => url_groups.into_iter().filter(|(id, _)| id == ws).collect(),
-        None => url_groups,
+    let log_dir = setup::log_dir(ws_id).ok_or("could not determine home directory")?;
+    let status_path = setup::status_path(&log_dir);
+    let container_id = container::id(container_name).unwrap_or_default();
+    let mut status = setup::SetupStatus::load(&status_path)
+        .unwrap_or_else(|e| {
+            warn!("{e}");
+            setup::SetupStatus::default()
+        })
+        .for_container(&container_id);
+
+    let pending: Vec<&config::SetupStep> = if rerun_all {
+        steps.iter().collect()
+    } else {
+        status.pending(&steps, workspace_dir)
     };
 
-    if filtered.is_empty() {
-        if let Some(ws) = &workspace {
-            error!("no URLs for workspace '{ws}'");
+    for step in &pending {
+        // Names are unique (see `RepoHints::validate`), so this finds the step
+        let index = steps.iter().position(|s| s.name == step.name).unwrap_or(0);
+        let log_path = setup::step_log_path(&log_dir, index, &step.name);
+        info!("==> {}: {}", step.name, step.run);
+        let result = setup::run_step(container_name, step, workspace_dir, &log_path)
+            .map_err(|e| format!("setup step '{}' failed: {e}", step.name))?;
+        let failed = result.state == setup::StepState::Failed;
+        status.steps.insert(step.name.clone(), result);
+        if let Err(e) = status.save(&status_path) {
+            warn!("could not record setup status: {e}");
         }
-        return 1;
-    }
-
-    for (_, urls) in &filtered {
-        for url_line in urls {
-            // Extract the URL part (after the status icon)
-            let url = url_line
-                .trim()
-                .trim_start_matches('\u{25cf}')
-                .trim_start_matches('\u{25cb}')
-                .trim();
-            let http_url = format!("http://{url}");
-            #[cfg(target_os = "macos")]
-            let _ = std::process::Command::new("open").arg(&http_url).spawn();
-            #[cfg(target_os = "linux")]
-            let _ = std::process::Command::new("xdg-open")
-                .arg(&http_url)
-                .spawn();
-            info!("Opening {http_url}");
+        if failed {
+            return Err(format!(
+                "setup step '{}' failed (log: {})",
+                step.name,
+                log_path.display()
+            ));
         }
     }
 
-    0
+    Ok(pending.len())
 }
 
-/// Show workspace URLs.
-fn cmd_urls(workspace: Option<String>) -> i32 {
+/// Run a workspace's setup steps on demand.
+fn cmd_setup(workspace_arg: Option<String>, rerun: bool) -> i32 {
     let st = match state::load() {
         Ok(s) => s,
         Err(e) => {
@@ -629,31 +899,58 @@ fn cmd_urls(workspace: Option<String>) -> i32 {
         }
     };
 
-    let url_groups = proxy::workspace_urls(&st);
-    if url_groups.is_empty() {
-        info!("No URLs configured. Add 'ports' to .dual.toml in your repo.");
-        return 0;
+    let entry = if let Some(ws) = workspace_arg {
+        match st.resolve_workspace(&ws) {
+            Some(e) => e.clone(),
+            None => {
+                error!("unknown workspace '{ws}'");
+                return 1;
+            }
+        }
+    } else {
+        match detect_workspace(&st) {
+            Some(e) => e,
+            None => {
+                error!("not inside a dual workspace");
+                info!("Usage: dual setup [workspace] [--rerun]");
+                return 1;
+            }
+        }
+    };
+
+    let ws_id = config::workspace_id(&entry.repo, &entry.branch);
+    let container_name = config::container_name(&entry.repo, &entry.branch);
+    if container::status(&container_name) != container::ContainerStatus::Running {
+        error!("{container_name} is not running");
+        info!("Start it with `dual launch {ws_id}`.");
+        return 1;
     }
 
-    // Filter by workspace if specified
-    let filtered: Vec<_> = match &workspace {
-        Some(ws) => url_groups.into_iter().filter(|(id, _)| id == ws).collect(),
-        None => url_groups,
-    };
+    let workspace_dir = st.workspace_dir(&entry);
+    let hints = config::load_hints(&workspace_dir).unwrap_or_default();
+    if hints.setup_plan().is_empty() {
+        info!("No setup steps in .dual.toml");
+        return 0;
+    }
 
-    for (workspace_id, urls) in &filtered {
-        info!("{workspace_id}");
-        for url in urls {
-            info!("{url}");
+    match run_setup(&container_name, &ws_id, &workspace_dir, &hints, rerun) {
+        Ok(0) => {
+            info!("Setup is up to date (use --rerun to run every step)");
+            0
+        }
+        Ok(n) => {
+            info!("Setup complete ({n} step(s) run)");
+            0
+        }
+        Err(e) => {
+            error!("{e}");
+            1
         }
-        info!("");
     }
-
-    0
 }
 
-/// Start the reverse proxy.
-fn cmd_proxy() -> i32 {
+/// Show live resource usage of running workspace containers.
+fn cmd_top(sort: TopSort, interval: u64, once: bool) -> i32 {
     let st = match state::load() {
         Ok(s) => s,
         Err(e) => {
@@ -662,24 +959,73 @@ fn cmd_proxy() -> i32 {
         }
     };
 
-    let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
-    match rt.block_on(proxy::start(&st)) {
-        Ok(()) => 0,
-        Err(e) => {
-            error!("proxy failed: {e}");
-            1
+    let entries = st.all_workspaces();
+    let known: HashSet<String> = entries
+        .iter()
+        .map(|e| config::workspace_id(&e.repo, &e.branch))
+        .collect();
+    let pairs: Vec<(String, String)> = entries
+        .iter()
+        .map(|e| (e.repo.clone(), e.branch.clone()))
+        .collect();
+
+    loop {
+        // Container name → workspace id, from the containers' labels, or
+        // their names for containers created before dual labelled them
+        let workspaces: HashMap<String, String> = container::list_all()
+            .into_iter()
+            .chain(container::list_unlabelled(&pairs))
+            .filter(|c| c.running && known.contains(&c.workspace_id))
+            .map(|c| {
+                let label = match c.service {
+                    Some(service) => format!("{}/{service}", c.workspace_id),
+                    None => c.workspace_id,
+                };
+                (c.name, label)
+            })
+            .collect();
+        let running: Vec<String> = workspaces.keys().cloned().collect();
+        let mut stats = container::stats(&running);
+        sort_stats(&mut stats, sort);
+
+        if !once {
+            // Clear the screen and move the cursor home
+            print!("\x1b[2J\x1b[H");
+        }
+        println!(
+            "{:<40} {:>8} {:>24} {:>7} {:>6}",
+            "WORKSPACE", "CPU %", "MEM USAGE / LIMIT", "MEM %", "PIDS"
+        );
+        for s in &stats {
+            let id = workspaces.get(&s.name).unwrap_or(&s.name);
+            println!(
+                "{id:<40} {:>7.1}% {:>24} {:>6.1}% {:>6}",
+                s.cpu_percent, s.memory, s.memory_percent, s.pids
+            );
+        }
+        if stats.is_empty() {
+            println!("(no running workspace containers)");
+        }
+
+        if once {
+            return 0;
         }
+        std::thread::sleep(std::time::Duration::from_secs(interval.max(1)));
     }
 }
 
-/// Output shell RC for a container (used by `eval "$(dual shell-rc <name>)"`).
-fn cmd_shell_rc(container_name: &str) -> i32 {
-    print!("{}", shell::generate_rc(container_name, &[]));
-    0
+/// Sort `dual top` rows: numeric columns highest first, names alphabetically.
+fn sort_stats(stats: &mut [container::ContainerStats], sort: TopSort) {
+    match sort {
+        TopSort::Cpu => stats.sort_by(|a, b| b.cpu_percent.total_cmp(&a.cpu_percent)),
+        TopSort::Mem => stats.sort_by(|a, b| b.memory_percent.total_cmp(&a.memory_percent)),
+        TopSort::Pids => stats.sort_by_key(|s| std::cmp::Reverse(s.pids)),
+        TopSort::Name => stats.sort_by(|a, b| a.name.cmp(&b.name)),
+    }
 }
 
-/// Sync shared config files for a workspace.
-fn cmd_sync(workspace_arg: Option<String>) -> i32 {
+/// List dual-managed volumes, or seed a workspace's volumes from another's.
+fn cmd_volumes(action: Option<VolumesAction>) -> i32 {
     let st = match state::load() {
         Ok(s) => s,
         Err(e) => {
@@ -688,26 +1034,1515 @@ fn cmd_sync(workspace_arg: Option<String>) -> i32 {
         }
     };
 
-    // Resolve which workspace we're syncing
-    let entry = if let Some(ws) = workspace_arg {
-        match st.resolve_workspace(&ws) {
-            Some(e) => e.clone(),
+    match action {
+        None => list_volumes(&st, None),
+        Some(VolumesAction::Ls { workspace }) => list_volumes(&st, workspace.as_deref()),
+        Some(VolumesAction::Seed { workspace, from }) => {
+            seed_volumes(&st, &workspace, from.as_deref())
+        }
+    }
+}
+
+/// Print volumes grouped by the workspace they belong to.
+fn list_volumes(st: &state::WorkspaceState, workspace: Option<&str>) -> i32 {
+    let filter = match workspace {
+        Some(ws) => match st.resolve_workspace(ws) {
+            Some(e) => Some(config::container_name(&e.repo, &e.branch)),
             None => {
                 error!("unknown workspace '{ws}'");
                 return 1;
             }
-        }
+        },
+        None => None,
+    };
+
+    // Attribute volumes to the container that mounts them, falling back to the label
+    let mut owners: HashMap<String, String> = HashMap::new();
+    for entry in st.all_workspaces() {
+        let name = config::container_name(&entry.repo, &entry.branch);
+        for (volume, _) in container::volume_mounts(&name) {
+            owners.insert(volume, name.clone());
+        }
+    }
+
+    let mut groups: Vec<(String, Vec<container::VolumeInfo>)> = Vec::new();
+    for volume in container::list_volumes() {
+        let owner = owners
+            .get(&volume.name)
+            .cloned()
+            .unwrap_or_else(|| volume.container.clone());
+        if filter.as_ref().is_some_and(|f| *f != owner) {
+            continue;
+        }
+        match groups.iter_mut().find(|(o, _)| *o == owner) {
+            Some((_, vols)) => vols.push(volume),
+            None => groups.push((owner, vec![volume])),
+        }
+    }
+
+    if groups.is_empty() {
+        info!("No volumes.");
+        return 0;
+    }
+
+    groups.sort_by(|a, b| a.0.cmp(&b.0));
+    for (owner, volumes) in &groups {
+        let orphaned = container::status(owner) == container::ContainerStatus::Missing;
+        if orphaned {
+            info!("{owner} (no container)");
+        } else {
+            info!("{owner}");
+        }
+        for volume in volumes {
+            let size = volume.size.as_deref().unwrap_or("?");
+            info!("  {:<24} {size:>10}  {}", volume.dir, volume.name);
+        }
+        info!("");
+    }
+
+    0
+}
+
+/// Copy the isolated directories of `from` (default: the repo's main workspace)
+/// into `workspace`'s volumes, so a new branch starts with e.g. `node_modules`.
+fn seed_volumes(st: &state::WorkspaceState, workspace: &str, from: Option<&str>) -> i32 {
+    let Some(target) = st.resolve_workspace(workspace) else {
+        error!("unknown workspace '{workspace}'");
+        return 1;
+    };
+
+    let source = match from {
+        Some(ws) => match st.resolve_workspace(ws) {
+            Some(e) => e,
+            None => {
+                error!("unknown workspace '{ws}'");
+                return 1;
+            }
+        },
+        None => match st
+            .workspaces_for_repo(&target.repo)
+            .into_iter()
+            .find(|ws| ws.path.is_some())
+        {
+            Some(e) => e,
+            None => {
+                error!("repo '{}' has no main workspace to seed from", target.repo);
+                info!("Use --from <workspace> to pick one.");
+                return 1;
+            }
+        },
+    };
+
+    let source_container = config::container_name(&source.repo, &source.branch);
+    let target_container = config::container_name(&target.repo, &target.branch);
+    if source_container == target_container {
+        error!("cannot seed a workspace from itself");
+        return 1;
+    }
+
+    // The target may not be cloned yet; fall back to the source's hints
+    let target_dir = st.workspace_dir(target);
+    let hints = if target_dir.join(".git").exists() {
+        config::load_hints(&target_dir).unwrap_or_default()
+    } else {
+        config::load_hints(&st.workspace_dir(source)).unwrap_or_default()
+    };
+
+    let source_mounts = container::volume_mounts(&source_container);
+    let mut seeded = 0;
+    for dir in &hints.anonymous_volumes {
+        let dest = format!("/workspace/{dir}");
+        let Some((from_volume, _)) = source_mounts.iter().find(|(_, d)| *d == dest) else {
+            warn!("{source_container} has no volume for {dir}, skipping");
+            continue;
+        };
+
+        let to_volume = match container::workspace_volume(&target_container, dir) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("{e}");
+                return 1;
+            }
+        };
+
+        info!("Seeding {dir} from {source_container}...");
+        if let Err(e) = container::seed_volume(&hints.image, from_volume, &to_volume) {
+            error!("seed failed: {e}");
+            return 1;
+        }
+        seeded += 1;
+    }
+
+    info!("Seeded {seeded} volume(s) for {target_container}");
+    0
+}
+
+/// List all configured workspaces with their live status.
+fn cmd_list(backend: &dyn MultiplexerBackend) -> i32 {
+    let st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    let workspaces = st.all_workspaces();
+    if workspaces.is_empty() {
+        info!("No workspaces configured.");
+        return 0;
+    }
+
+    print_workspace_status(&st, backend);
+    0
+}
+
+/// Destroy a workspace: safety check → tmux → container → archive → clone.
+fn cmd_destroy(
+    workspace_arg: Option<&str>,
+    force: bool,
+    archive: bool,
+    backend: &dyn MultiplexerBackend,
+) -> i32 {
+    let mut st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    // Resolve workspace: use arg if provided, otherwise auto-detect from cwd
+    let workspace;
+    let entry = if let Some(ws) = workspace_arg {
+        workspace = ws.to_string();
+        match st.resolve_workspace(ws) {
+            Some(e) => e.clone(),
+            None => {
+                error!("unknown workspace '{ws}'");
+                return 1;
+            }
+        }
+    } else {
+        match detect_workspace(&st) {
+            Some(e) => {
+                workspace = config::workspace_id(&e.repo, &e.branch);
+                info!("Auto-detected workspace: {workspace}");
+                e
+            }
+            None => {
+                error!("could not detect workspace from current directory");
+                info!("Usage: dual destroy [workspace]");
+                return 1;
+            }
+        }
+    };
+
+    let workspace_root = st.workspace_root();
+    let container_name = config::container_name(&entry.repo, &entry.branch);
+    let session_name = config::session_name(&entry.repo, &entry.branch);
+    let removes_clone = entry.path.is_none()
+        && clone::workspace_exists(&workspace_root, &entry.repo, &entry.branch);
+    let clone_dir = config::workspace_dir(&workspace_root, &entry.repo, &entry.branch);
+
+    // Refuse to delete a clone holding work that exists nowhere else
+    if removes_clone && !force && !archive {
+        match clone::check_unsaved_work(&clone_dir) {
+            Ok(work) if work.is_clean() => {}
+            Ok(work) => {
+                error!("workspace '{workspace}' has unsaved work:");
+                for line in work.summary() {
+                    error!("  {line}");
+                }
+                info!("Use `dual destroy {workspace} --archive` to keep a restorable copy,");
+                info!("or `dual destroy {workspace} --force` to discard it.");
+                return 1;
+            }
+            Err(e) => {
+                error!("could not inspect clone: {e}");
+                info!("Use --force to destroy anyway.");
+                return 1;
+            }
+        }
+    }
+
+    // Hooks run first, so a dump they write ends up in the archive
+    let workspace_dir = st.workspace_dir(&entry);
+    let hints = config::load_hints(&workspace_dir).unwrap_or_default();
+    if !run_hooks_forced(
+        &st,
+        HookEvent::PreDestroy,
+        &hints,
+        &entry,
+        &workspace_dir,
+        force,
+    ) {
+        info!("Destroy cancelled. Use --force to destroy anyway.");
+        return 1;
+    }
+    if container::status(&container_name) == container::ContainerStatus::Running
+        && !run_hooks_forced(
+            &st,
+            HookEvent::PreStop,
+            &hints,
+            &entry,
+            &workspace_dir,
+            force,
+        )
+    {
+        info!("Destroy cancelled.");
+        return 1;
+    }
+
+    // Archive before anything is torn down, so a failure leaves the workspace intact
+    if removes_clone && archive {
+        let Some(archive_root) = config::archive_dir() else {
+            error!("could not determine home directory");
+            return 1;
+        };
+        match archive::archive_workspace(&clone_dir, &archive_root, &entry) {
+            Ok(dir) => info!("Archived clone to {}", dir.display()),
+            Err(e) => {
+                error!("archive failed: {e}");
+                return 1;
+            }
+        }
+    }
+
+    // Destroy tmux session
+    if backend.is_alive(&session_name) {
+        info!("Destroying session {session_name}...");
+        if let Err(e) = backend.destroy(&session_name) {
+            warn!("session destroy failed: {e}");
+        }
+    }
+
+    // Remove sidecars first (they share the container's network), then the
+    // container, then the volumes they all used
+    let mut mounted: Vec<String> =
+        remove_services(&config::workspace_id(&entry.repo, &entry.branch))
+            .into_iter()
+            .flat_map(|(_, mounts)| mounts)
+            .map(|(volume, _)| volume)
+            .collect();
+    mounted.extend(
+        container::volume_mounts(&container_name)
+            .into_iter()
+            .map(|(volume, _)| volume),
+    );
+    match container::status(&container_name) {
+        container::ContainerStatus::Running => {
+            info!("Stopping container {container_name}...");
+            if let Err(e) = container::stop(&container_name) {
+                warn!("container stop failed: {e}");
+            }
+            info!("Removing container {container_name}...");
+            if let Err(e) = container::destroy(&container_name) {
+                warn!("container remove failed: {e}");
+            }
+        }
+        container::ContainerStatus::Stopped => {
+            info!("Removing container {container_name}...");
+            if let Err(e) = container::destroy(&container_name) {
+                warn!("container remove failed: {e}");
+            }
+        }
+        container::ContainerStatus::Missing => {}
+    }
+    // Only volumes created for a workspace container: those labelled with this
+    // one (found even if the container is already gone), plus mounted ones
+    // carried over from a rename. Shared caches are never removed.
+    let managed = container::owned_volumes(None);
+    let mut volumes = container::owned_volumes(Some(&container_name));
+    for volume in mounted {
+        if managed.contains(&volume) && !volumes.contains(&volume) {
+            volumes.push(volume);
+        }
+    }
+    for volume in &volumes {
+        if let Err(e) = container::remove_volume(volume) {
+            warn!("volume remove failed: {e}");
+        }
+    }
+    // The workspace network, once nothing is attached to it
+    let network = config::network_name(&entry.repo, &entry.branch);
+    if let Err(e) = container::remove_network(&network) {
+        debug!("network remove failed: {e}");
+    }
+
+    // Remove setup logs and status
+    if let Some(log_dir) = setup::log_dir(&config::workspace_id(&entry.repo, &entry.branch))
+        && log_dir.exists()
+        && let Err(e) = std::fs::remove_dir_all(&log_dir)
+    {
+        warn!("failed to remove logs: {e}");
+    }
+
+    // Remove clone (only for non-explicit-path workspaces)
+    if removes_clone {
+        info!("Removing clone...");
+        if let Err(e) = clone::remove_workspace(&workspace_root, &entry.repo, &entry.branch) {
+            error!("failed to remove clone: {e}");
+            return 1;
+        }
+    }
+
+    // Remove from state
+    st.remove_workspace(&entry.repo, &entry.branch);
+    if let Err(e) = state::save(&st) {
+        warn!("failed to save state: {e}");
+    }
+
+    info!("Workspace '{workspace}' destroyed.");
+    0
+}
+
+/// An undo step recorded by `cmd_rename`, run in reverse order on failure.
+type UndoStep<'a> = (&'static str, Box<dyn FnOnce() -> Result<(), String> + 'a>);
+
+/// Rename a workspace: git branch → clone dir → container → RC file → tmux → state.
+///
+/// Each completed step registers an undo action; if a later step fails, the
+/// completed steps are rolled back so the workspace stays usable under its old name.
+/// Main workspaces are refused, as their branch lives in the user's own checkout.
+fn cmd_rename(workspace: &str, new_branch: &str, backend: &dyn MultiplexerBackend) -> i32 {
+    let mut st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    let entry = match st.resolve_workspace(workspace) {
+        Some(e) => e.clone(),
+        None => {
+            error!("unknown workspace '{workspace}'");
+            return 1;
+        }
+    };
+
+    if st.has_workspace(&entry.repo, new_branch) {
+        error!("workspace {}/{new_branch} already exists", entry.repo);
+        return 1;
+    }
+
+    // A main workspace is the user's own checkout: renaming its branch is up to them
+    if let Some(ref path) = entry.path {
+        error!(
+            "{workspace} is the main workspace at {path}; rename its branch there with git instead"
+        );
+        return 1;
+    }
+
+    let mut renamed = entry.clone();
+    renamed.branch = new_branch.to_string();
+
+    let old_dir = st.workspace_dir(&entry);
+    let new_dir = st.workspace_dir(&renamed);
+    let old_container = config::container_name(&entry.repo, &entry.branch);
+    let new_container = config::container_name(&entry.repo, new_branch);
+    let old_session = config::session_name(&entry.repo, &entry.branch);
+    let new_session = config::session_name(&entry.repo, new_branch);
+    let cloned = old_dir.join(".git").exists();
+
+    if old_dir != new_dir && new_dir.exists() {
+        error!("{} already exists", new_dir.display());
+        return 1;
+    }
+
+    let mut undo: Vec<UndoStep> = Vec::new();
+    let mut moved_services = Vec::new();
+    let mut rc_path = None;
+    let mut session_renamed = false;
+    let result = (|| -> Result<(), String> {
+        // Step 1: rename the git branch
+        if cloned {
+            clone::rename_branch(&old_dir, &entry.branch, new_branch)
+                .map_err(|e| format!("branch rename failed: {e}"))?;
+            let (dir, old, new) = (
+                old_dir.clone(),
+                entry.branch.clone(),
+                new_branch.to_string(),
+            );
+            undo.push((
+                "git branch",
+                Box::new(move || clone::rename_branch(&dir, &new, &old).map_err(|e| e.to_string())),
+            ));
+        }
+
+        // Step 2: move the clone
+        if cloned && old_dir != new_dir {
+            if let Some(parent) = new_dir.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("could not create {}: {e}", parent.display()))?;
+            }
+            std::fs::rename(&old_dir, &new_dir).map_err(|e| format!("move failed: {e}"))?;
+            let (from, to) = (new_dir.clone(), old_dir.clone());
+            undo.push((
+                "clone directory",
+                Box::new(move || std::fs::rename(&from, &to).map_err(|e| e.to_string())),
+            ));
+        }
+
+        // Step 3: recreate the container under the new name, keeping its volumes
+        if container::status(&old_container) != container::ContainerStatus::Missing {
+            let hints = config::load_hints(&new_dir).unwrap_or_default();
+            let spec = container_spec(&st, &renamed, &hints, &new_dir);
+            let old_spec = container_spec(&st, &entry, &hints, &new_dir);
+            // Sidecars are recreated for the new container once the rename is
+            // done, or for the old one again on rollback
+            moved_services = remove_services(&config::workspace_id(&entry.repo, &entry.branch));
+            if !moved_services.is_empty() {
+                let (services, _) = services::resolve(&hints, &new_dir);
+                let (container_name, labels, moved) = (
+                    old_container.clone(),
+                    container::workspace_labels(&entry.repo, &entry.branch),
+                    moved_services.clone(),
+                );
+                undo.push((
+                    "services",
+                    Box::new(move || {
+                        recreate_services(&container_name, &services, &labels, &moved);
+                        Ok(())
+                    }),
+                ));
+            }
+            create_cache_volumes(&renamed, &hints);
+            container::rename(&old_container, &new_container, &new_dir, &spec)
+                .map_err(|e| format!("container rename failed: {e}"))?;
+            let (old, new, dir) = (
+                old_container.clone(),
+                new_container.clone(),
+                old_dir.clone(),
+            );
+            undo.push((
+                "container",
+                Box::new(move || {
+                    container::rename(&new, &old, &dir, &old_spec).map_err(|e| e.to_string())
+                }),
+            ));
+        }
+
+        // Step 4: write the shell RC for the new container name (the old one
+        // is removed once the rename has succeeded)
+        let hints = config::load_hints(&new_dir).unwrap_or_default();
+        rc_path = Some(
+            shell::write_rc_file(&new_container, &hints.extra_commands)
+                .map_err(|e| format!("failed to write shell RC: {e}"))?,
+        );
+
+        // Step 5: rename the tmux session; its open shells are left alone
+        if backend.is_alive(&old_session) {
+            backend
+                .rename_session(&old_session, &new_session)
+                .map_err(|e| format!("session rename failed: {e}"))?;
+            let (old, new) = (old_session.clone(), new_session.clone());
+            undo.push((
+                "tmux session",
+                Box::new(move || {
+                    backend
+                        .rename_session(&new, &old)
+                        .map_err(|e| e.to_string())
+                }),
+            ));
+            let new_id = config::workspace_id(&entry.repo, new_branch);
+            let _ = backend.tag_session(&new_session, &new_id);
+            session_renamed = true;
+        }
+
+        // Step 6: persist the new identity
+        st.rename_workspace(&entry.repo, &entry.branch, new_branch)
+            .map_err(|e| e.to_string())?;
+        state::save(&st).map_err(|e| format!("failed to save state: {e}"))?;
+
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        error!("{e}");
+        warn!("Rolling back...");
+        while let Some((what, step)) = undo.pop() {
+            if let Err(e) = step() {
+                warn!("could not roll back {what}: {e}");
+            }
+        }
+        // The original container's RC was kept; drop the new one
+        if rc_path.is_some() {
+            let _ = shell::remove_rc_file(&new_container);
+        }
+        return 1;
+    }
+
+    let _ = shell::remove_rc_file(&old_container);
+    if session_renamed && let Some(ref rc_path) = rc_path {
+        print_shell_update(
+            &new_session,
+            (old_dir != new_dir).then_some(new_dir.as_path()),
+            rc_path,
+        );
+    }
+
+    let ws_id = config::workspace_id(&entry.repo, new_branch);
+    // The container now runs on the new name's network
+    if let Err(e) = container::remove_network(&config::network_name(&entry.repo, &entry.branch)) {
+        debug!("old network not removed: {e}");
+    }
+    if !moved_services.is_empty() {
+        let hints = config::load_hints(&new_dir).unwrap_or_default();
+        let (services, _) = services::resolve(&hints, &new_dir);
+        let labels = container::workspace_labels(&entry.repo, new_branch);
+        recreate_services(&new_container, &services, &labels, &moved_services);
+    }
+    // Carry setup logs and status over, so finished steps don't run again
+    if let (Some(old_logs), Some(new_logs)) = (
+        setup::log_dir(&config::workspace_id(&entry.repo, &entry.branch)),
+        setup::log_dir(&ws_id),
+    ) && old_logs.exists()
+        && let Err(e) = std::fs::rename(&old_logs, &new_logs)
+    {
+        warn!("could not move setup logs: {e}");
+    }
+    info!("Renamed {workspace} → {ws_id}");
+    0
+}
+
+/// Tell the user how to point a renamed session's open shells at the new RC
+/// (and clone directory, if it moved). Typing the commands into the session
+/// ourselves could land in whatever program is running there.
+fn print_shell_update(session: &str, new_dir: Option<&Path>, rc_path: &Path) {
+    let source = shell::source_file_command(rc_path);
+    let command = match new_dir {
+        Some(dir) => format!("cd {} && {source}", dir.display()),
+        None => source,
+    };
+    info!("Shells already open in session {session} still use the old name. In each, run:");
+    info!("  {command}");
+}
+
+/// Check whether a workspace still has a clone, container or session under the legacy names.
+fn has_legacy_artifacts(st: &state::WorkspaceState, entry: &state::WorkspaceEntry) -> bool {
+    let legacy_container = config::legacy_container_name(&entry.repo, &entry.branch);
+    if legacy_container == config::container_name(&entry.repo, &entry.branch) {
+        return false;
+    }
+    let legacy_dir = config::legacy_workspace_dir(&st.workspace_root(), &entry.repo, &entry.branch);
+    (entry.path.is_none() && legacy_dir.join(".git").exists())
+        || container::status(&legacy_container) != container::ContainerStatus::Missing
+}
+
+/// Rename clones, containers, sessions and RC files from the legacy `/` → `__`
+/// encoding to the current one (see `config::encode_branch`).
+fn cmd_migrate(backend: &dyn MultiplexerBackend) -> i32 {
+    let st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    let workspace_root = st.workspace_root();
+    let mut migrated = 0;
+    let mut failed = 0;
+
+    for entry in st.all_workspaces() {
+        let ws_id = config::workspace_id(&entry.repo, &entry.branch);
+        let Some((old_name, old_dir)) =
+            config::legacy_workspace(&workspace_root, &entry.repo, &entry.branch)
+        else {
+            continue;
+        };
+        let new_name = config::container_name(&entry.repo, &entry.branch);
+        let new_dir = st.workspace_dir(entry);
+        let mut changed = false;
+
+        // Clone directory
+        if entry.path.is_none() && old_dir.exists() && old_dir != new_dir {
+            if new_dir.exists() {
+                error!(
+                    "{ws_id}: both {} and {} exist",
+                    old_dir.display(),
+                    new_dir.display()
+                );
+                failed += 1;
+                continue;
+            }
+            if let Err(e) = std::fs::rename(&old_dir, &new_dir) {
+                error!("{ws_id}: could not move clone: {e}");
+                failed += 1;
+                continue;
+            }
+            info!("{ws_id}: moved clone → {}", new_dir.display());
+            changed = true;
+        }
+
+        // Container (recreated so the bind mount follows the moved clone)
+        if container::status(&old_name) != container::ContainerStatus::Missing {
+            let hints = config::load_hints(&new_dir).unwrap_or_default();
+            create_cache_volumes(entry, &hints);
+            match container::rename(
+                &old_name,
+                &new_name,
+                &new_dir,
+                &container_spec(&st, entry, &hints, &new_dir),
+            ) {
+                Ok(()) => info!("{ws_id}: container {old_name} → {new_name}"),
+                Err(e) => {
+                    error!("{ws_id}: container rename failed: {e}");
+                    failed += 1;
+                    continue;
+                }
+            }
+            changed = true;
+        }
+
+        // Shell RC
+        let hints = config::load_hints(&new_dir).unwrap_or_default();
+        if changed || backend.is_alive(&old_name) {
+            let _ = shell::remove_rc_file(&old_name);
+            if let Err(e) = shell::write_rc_file(&new_name, &hints.extra_commands) {
+                warn!("{ws_id}: failed to write shell RC: {e}");
+            }
+        }
+
+        // Tmux session
+        if backend.is_alive(&old_name) {
+            match backend.rename_session(&old_name, &new_name) {
+                Ok(()) => {
+                    let _ = backend.tag_session(&new_name, &ws_id);
+                    info!("{ws_id}: session {old_name} → {new_name}");
+                    if let Ok(rc_path) = shell::write_rc_file(&new_name, &hints.extra_commands) {
+                        print_shell_update(&new_name, Some(&new_dir), &rc_path);
+                    }
+                }
+                Err(e) => warn!("{ws_id}: session rename failed: {e}"),
+            }
+            changed = true;
+        }
+
+        if changed {
+            migrated += 1;
+        }
+    }
+
+    if migrated == 0 && failed == 0 {
+        info!("Nothing to migrate.");
+    } else {
+        info!("Migrated {migrated} workspace(s).");
+    }
+
+    if failed > 0 { 1 } else { 0 }
+}
+
+/// Recreate a destroyed workspace from an archive and re-register it.
+fn cmd_restore(archive_arg: Option<&str>) -> i32 {
+    let mut st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    let Some(archive_root) = config::archive_dir() else {
+        error!("could not determine home directory");
+        return 1;
+    };
+
+    let Some(name) = archive_arg else {
+        let archives = archive::list_archives(&archive_root);
+        if archives.is_empty() {
+            info!("No archived workspaces.");
+        }
+        for (dir, _) in &archives {
+            if let Some(name) = dir.file_name() {
+                info!("  {}", name.to_string_lossy());
+            }
+        }
+        return 0;
+    };
+
+    let (archive_dir, manifest) = match archive::find_archive(&archive_root, name) {
+        Some(found) => found,
+        None => {
+            error!("no archive named '{name}'");
+            info!("Run `dual restore` to list archives.");
+            return 1;
+        }
+    };
+
+    let entry = manifest.workspace;
+    if st.has_workspace(&entry.repo, &entry.branch) {
+        error!(
+            "workspace {}/{} already exists. Destroy it first.",
+            entry.repo, entry.branch
+        );
+        return 1;
+    }
+
+    let target_dir = st.workspace_dir(&entry);
+    info!("Restoring {} → {}", name, target_dir.display());
+    if let Err(e) = archive::restore_archive(&archive_dir, &target_dir) {
+        error!("restore failed: {e}");
+        return 1;
+    }
+
+    let ws_id = config::workspace_id(&entry.repo, &entry.branch);
+    if let Err(e) = st.add_workspace(entry) {
+        error!("{e}");
+        return 1;
+    }
+    if let Err(e) = state::save(&st) {
+        error!("failed to save state: {e}");
+        return 1;
+    }
+
+    info!("Restored workspace: {ws_id}");
+    info!("Use `dual launch {ws_id}` to start.");
+    0
+}
+
+/// Seconds `dual launch --wait` waits for the workspace's ports to be ready.
+const LAUNCH_WAIT_SECS: u64 = 120;
+
+/// Seconds `dual open` waits for the ports it opens to be ready.
+const OPEN_WAIT_SECS: u64 = 60;
+
+/// Open workspace services in the default browser, once they're ready.
+fn cmd_open(workspace: Option<String>, service: Option<String>, no_wait: bool) -> i32 {
+    let st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    let url_groups = proxy::workspace_urls(&st);
+    if url_groups.is_empty() {
+        info!("No URLs configured. Add 'ports' to .dual.toml in your repo.");
+        return 0;
+    }
+
+    // Filter by workspace if specified
+    let filtered: Vec<_> = match &workspace {
+        Some(ws) => url_groups
+            .into_iter()
+            .filter(|g| &g.workspace_id == ws)
+            .collect(),
+        None => url_groups,
+    };
+
+    if filtered.is_empty() {
+        if let Some(ws) = &workspace {
+            error!("no URLs for workspace '{ws}'");
+        }
+        return 1;
+    }
+
+    // A named service is opened on request; otherwise every port not marked `open = false`
+    let selected: Vec<(&proxy::WorkspaceUrls, Vec<&proxy::ServiceUrl>)> = filtered
+        .iter()
+        .map(|g| {
+            let services = g
+                .services
+                .iter()
+                .filter(|s| match &service {
+                    Some(name) => s.name.as_ref() == Some(name),
+                    None => s.open,
+                })
+                .collect();
+            (g, services)
+        })
+        .collect();
+    if let Some(name) = &service
+        && selected.iter().all(|(_, services)| services.is_empty())
+    {
+        error!("no port named '{name}'");
+        return 1;
+    }
+
+    // Give running workspaces a chance to finish starting before the browser hits them
+    if !no_wait {
+        for (group, services) in &selected {
+            if !group.running || services.is_empty() {
+                continue;
+            }
+            let ports: Vec<u16> = services.iter().map(|s| s.port).collect();
+            let result = wait_for_health(
+                &st,
+                &group.workspace_id,
+                |check| ports.contains(&check.port),
+                Duration::from_secs(OPEN_WAIT_SECS),
+            );
+            if let Err(e) = result {
+                warn!("{}: {e}; opening anyway", group.workspace_id);
+            }
+        }
+    }
+
+    let urls = selected
+        .iter()
+        .flat_map(|(_, services)| services)
+        .map(|s| s.url.as_str());
+    for url in urls {
+        #[cfg(target_os = "macos")]
+        let _ = std::process::Command::new("open").arg(url).spawn();
+        #[cfg(target_os = "linux")]
+        let _ = std::process::Command::new("xdg-open").arg(url).spawn();
+        info!("Opening {url}");
+    }
+
+    0
+}
+
+/// Show workspace URLs.
+fn cmd_urls(workspace: Option<String>) -> i32 {
+    let st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    let url_groups = proxy::workspace_urls(&st);
+    if url_groups.is_empty() {
+        info!("No URLs configured. Add 'ports' to .dual.toml in your repo.");
+        return 0;
+    }
+
+    // Filter by workspace if specified
+    let mut filtered: Vec<_> = match &workspace {
+        Some(ws) => url_groups
+            .into_iter()
+            .filter(|g| &g.workspace_id == ws)
+            .collect(),
+        None => url_groups,
+    };
+
+    // Probes run in parallel, and only with the probe image already present:
+    // `dual urls` never pulls it, so it stays quick and works offline
+    let running = filtered.iter().filter(|g| g.running).count();
+    if running > 0 && health::probe_image_present() {
+        let st = &st;
+        std::thread::scope(|scope| {
+            for group in filtered.iter_mut().filter(|g| g.running) {
+                scope.spawn(move || group.health = workspace_health(st, &group.workspace_id));
+            }
+        });
+    } else if running > 0 {
+        debug!(
+            "{} not present; skipping health checks",
+            health::PROBE_IMAGE
+        );
+    }
+
+    for group in &filtered {
+        info!("{}", group.workspace_id);
+        for line in group.lines() {
+            info!("{line}");
+        }
+        info!("");
+    }
+
+    0
+}
+
+/// Health of a running workspace's ports by container port; empty if it can't be checked.
+fn workspace_health(st: &state::WorkspaceState, workspace_id: &str) -> HashMap<u16, Health> {
+    let Some(entry) = st.resolve_workspace(workspace_id) else {
+        return HashMap::new();
+    };
+    let hints = config::load_hints(&st.workspace_dir(entry)).unwrap_or_default();
+    let checks = health::checks(&hints);
+    let container_name = config::container_name(&entry.repo, &entry.branch);
+    let network = config::network_name(&entry.repo, &entry.branch);
+    match health::check(&container_name, &network, &checks) {
+        Ok(health) => checks.iter().map(|c| c.port).zip(health).collect(),
+        Err(e) => {
+            warn!("{workspace_id}: health check failed: {e}");
+            HashMap::new()
+        }
+    }
+}
+
+/// Wait for the checks of a workspace's ports that `keep` selects, logging
+/// each as it becomes ready.
+fn wait_for_health(
+    st: &state::WorkspaceState,
+    workspace_id: &str,
+    keep: impl Fn(&health::Check) -> bool,
+    timeout: Duration,
+) -> Result<(), health::HealthError> {
+    let Some(entry) = st.resolve_workspace(workspace_id) else {
+        return Ok(());
+    };
+    let hints = config::load_hints(&st.workspace_dir(entry)).unwrap_or_default();
+    let checks: Vec<_> = health::checks(&hints).into_iter().filter(keep).collect();
+    if checks.is_empty() {
+        return Ok(());
+    }
+
+    let names: Vec<&str> = checks.iter().map(|c| c.name.as_str()).collect();
+    info!(
+        "Waiting for {workspace_id} {} (up to {}s)...",
+        names.join(", "),
+        timeout.as_secs()
+    );
+    let container_name = config::container_name(&entry.repo, &entry.branch);
+    let network = config::network_name(&entry.repo, &entry.branch);
+    health::wait(&container_name, &network, &checks, timeout, |check| {
+        info!("  {} ready", check.name)
+    })
+}
+
+/// Block until a workspace's ports (or one named port) are ready.
+fn cmd_wait(workspace_arg: Option<String>, service: Option<String>, timeout: u64) -> i32 {
+    let st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    let entry = if let Some(ws) = workspace_arg {
+        match st.resolve_workspace(&ws) {
+            Some(e) => e.clone(),
+            None => {
+                error!("unknown workspace '{ws}'");
+                return 1;
+            }
+        }
+    } else {
+        match detect_workspace(&st) {
+            Some(e) => e,
+            None => {
+                error!("not inside a dual workspace");
+                info!("Usage: dual wait [workspace] [--service NAME] [--timeout SECS]");
+                return 1;
+            }
+        }
+    };
+
+    let ws_id = config::workspace_id(&entry.repo, &entry.branch);
+    let container_name = config::container_name(&entry.repo, &entry.branch);
+    match container::status(&container_name) {
+        container::ContainerStatus::Running => {}
+        container::ContainerStatus::Stopped => {
+            error!("{ws_id} is not running");
+            info!("Run `dual launch {ws_id}` to start it.");
+            return 1;
+        }
+        container::ContainerStatus::Missing => {
+            error!("{ws_id} has no container yet");
+            info!("Run `dual launch {ws_id}` first.");
+            return 1;
+        }
+    }
+
+    let hints = config::load_hints(&st.workspace_dir(&entry)).unwrap_or_default();
+    let checks = health::checks(&hints);
+    if let Some(name) = &service
+        && !checks.iter().any(|c| &c.name == name)
+    {
+        error!("no port named '{name}'");
+        return 1;
+    }
+    if checks.is_empty() {
+        info!("No ports configured. Add 'ports' to .dual.toml in your repo.");
+        return 0;
+    }
+
+    let keep = |check: &health::Check| service.as_ref().is_none_or(|name| &check.name == name);
+    match wait_for_health(&st, &ws_id, keep, Duration::from_secs(timeout)) {
+        Ok(()) => {
+            info!("{ws_id} is ready");
+            0
+        }
+        Err(e) => {
+            error!("{ws_id}: {e}");
+            1
+        }
+    }
+}
+
+/// Start the reverse proxy.
+fn cmd_proxy() -> i32 {
+    let mut st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    // Workspaces launched before they had `tcp_ports` get their host ports now
+    let workspaces: Vec<_> = st
+        .all_workspaces()
+        .iter()
+        .map(|entry| {
+            let hints = config::load_hints(&st.workspace_dir(entry)).unwrap_or_default();
+            (config::workspace_id(&entry.repo, &entry.branch), hints)
+        })
+        .collect();
+    let mut allocated = false;
+    for (ws_id, hints) in &workspaces {
+        allocated |= allocate_tcp_ports(&mut st, ws_id, hints);
+    }
+    if allocated && let Err(e) = state::save(&st) {
+        warn!("failed to save state: {e}");
+    }
+
+    let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
+    match rt.block_on(proxy::start(&st)) {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("proxy failed: {e}");
+            1
+        }
+    }
+}
+
+/// Print the proxy's access log, optionally following it.
+fn cmd_proxy_log(workspace: Option<String>, follow: bool) -> i32 {
+    let Some(path) = access_log::access_log_path() else {
+        error!("could not determine home directory");
+        return 1;
+    };
+
+    match access_log::read(&path, workspace.as_deref()) {
+        Ok(entries) => {
+            if entries.is_empty() && !follow {
+                info!("No requests logged yet. Start the proxy with `dual proxy`.");
+            }
+            for entry in &entries {
+                println!("{}", entry.line());
+            }
+        }
+        Err(e) => {
+            error!("failed to read {}: {e}", path.display());
+            return 1;
+        }
+    }
+
+    if follow
+        && let Err(e) = access_log::follow(&path, workspace.as_deref(), |entry| {
+            println!("{}", entry.line())
+        })
+    {
+        error!("failed to follow {}: {e}", path.display());
+        return 1;
+    }
+    0
+}
+
+/// Output shell RC for a container (used by `eval "$(dual shell-rc <name>)"`).
+fn cmd_shell_rc(container_name: &str) -> i32 {
+    print!("{}", shell::generate_rc(container_name, &[]));
+    0
+}
+
+/// Manage a repo's encrypted secret variables.
+fn cmd_secrets(action: SecretsAction) -> i32 {
+    let repo_arg = match &action {
+        SecretsAction::Set { repo, .. }
+        | SecretsAction::Get { repo, .. }
+        | SecretsAction::List { repo }
+        | SecretsAction::Rm { repo, .. } => repo.clone(),
+    };
+
+    let repo = match repo_arg {
+        Some(r) => r,
+        None => {
+            let detected = state::load().ok().and_then(|st| detect_repo_from_cwd(&st));
+            match detected {
+                Some(r) => r,
+                None => {
+                    error!("could not detect repo from current directory");
+                    info!("Usage: dual secrets <command> --repo <name>");
+                    return 1;
+                }
+            }
+        }
+    };
+
+    let Some(path) = secrets::vars_path(&repo) else {
+        error!("could not determine home directory");
+        return 1;
+    };
+    let key = match secrets::KeyProvider::from_env().and_then(|p| p.load()) {
+        Ok(k) => k,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+    let mut vars = match secrets::load_vars(&path, &key) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    match action {
+        SecretsAction::Set { name, value, .. } => {
+            if !dotenv::is_valid_name(&name) {
+                error!("'{name}' is not a valid environment variable name");
+                return 1;
+            }
+            let value = match value {
+                Some(v) => v,
+                None => {
+                    let mut input = String::new();
+                    if let Err(e) = std::io::stdin().read_line(&mut input) {
+                        error!("could not read value: {e}");
+                        return 1;
+                    }
+                    input.trim_end_matches(['\r', '\n']).to_string()
+                }
+            };
+            vars.insert(name.clone(), value);
+            if let Err(e) = secrets::save_vars(&path, &key, &vars) {
+                error!("{e}");
+                return 1;
+            }
+            info!("Set {name} for {repo}. Recreate containers to apply.");
+        }
+        SecretsAction::Get { name, .. } => match vars.get(&name) {
+            Some(value) => println!("{value}"),
+            None => {
+                error!("no secret '{name}' for {repo}");
+                return 1;
+            }
+        },
+        SecretsAction::List { .. } => {
+            for name in vars.keys() {
+                println!("{name}");
+            }
+        }
+        SecretsAction::Rm { name, .. } => {
+            if vars.remove(&name).is_none() {
+                error!("no secret '{name}' for {repo}");
+                return 1;
+            }
+            if let Err(e) = secrets::save_vars(&path, &key, &vars) {
+                error!("{e}");
+                return 1;
+            }
+            info!("Removed {name} from {repo}.");
+        }
+    }
+
+    0
+}
+
+/// Load the secrets key if the repo's shared store is encrypted.
+fn store_key(
+    shared_config: &config::SharedConfig,
+) -> Result<Option<secrets::SecretKey>, secrets::SecretsError> {
+    if !shared_config.encrypted {
+        return Ok(None);
+    }
+    secrets::KeyProvider::from_env()?.load().map(Some)
+}
+
+/// Effective container environment, lowest to highest precedence:
+/// cache env vars, `.dual.toml` [env], `env_files` in order, `dual secrets` variables, then the
+/// DUAL_WORKSPACE_ID / DUAL_BRANCH / DUAL_REPO variables. Also returns the
+/// names of variables that came from `env_files` or `dual secrets`, whose
+/// values `dual env` masks.
+fn workspace_env(
+    entry: &state::WorkspaceEntry,
+    hints: &config::RepoHints,
+    workspace_dir: &Path,
+) -> (HashMap<String, String>, HashSet<String>) {
+    let mut env = cache::env(&workspace_caches(hints));
+    env.extend(hints.env.clone());
+
+    let mut masked = HashSet::new();
+    for file in &hints.env_files {
+        let contents = match read_env_file(entry, hints, workspace_dir, file) {
+            Ok(Some(c)) => c,
+            Ok(None) => continue, // Optional: missing files are skipped
+            Err(e) => {
+                warn!("{file} not loaded: {e}");
+                continue;
+            }
+        };
+        match dotenv::parse(&contents, &env) {
+            Ok(vars) => {
+                masked.extend(vars.iter().map(|(name, _)| name.clone()));
+                env.extend(vars);
+            }
+            Err(e) => warn!("{file} not loaded: {e}"),
+        }
+    }
+
+    if let Some(path) = secrets::vars_path(&entry.repo).filter(|p| p.exists()) {
+        match secrets::KeyProvider::from_env()
+            .and_then(|provider| provider.load())
+            .and_then(|key| secrets::load_vars(&path, &key))
+        {
+            Ok(vars) => {
+                masked.extend(vars.keys().cloned());
+                env.extend(vars);
+            }
+            Err(e) => warn!("secrets for {} not loaded: {e}", entry.repo),
+        }
+    }
+
+    env.insert(
+        "DUAL_WORKSPACE_ID".to_string(),
+        config::workspace_id(&entry.repo, &entry.branch),
+    );
+    env.insert("DUAL_BRANCH".to_string(), entry.branch.clone());
+    env.insert("DUAL_REPO".to_string(), entry.repo.clone());
+
+    (env, masked)
+}
+
+/// Everything a workspace's container is created with: image, environment
+/// (see `workspace_env`), isolated volumes, mounts and resource limits.
+fn container_spec(
+    st: &state::WorkspaceState,
+    entry: &state::WorkspaceEntry,
+    hints: &config::RepoHints,
+    workspace_dir: &Path,
+) -> container::ContainerSpec {
+    container::ContainerSpec {
+        image: hints.image.clone(),
+        env: workspace_env(entry, hints, workspace_dir).0,
+        volumes: hints.anonymous_volumes.clone(),
+        mounts: workspace_mounts(entry, hints),
+        resources: hints.resources.or(&st.resources),
+        network: Some(config::network_name(&entry.repo, &entry.branch)),
+        labels: container::workspace_labels(&entry.repo, &entry.branch),
+    }
+}
+
+/// Read an `env_files` entry: the workspace copy, or the shared store's if the
+/// workspace has none. Files shared in `mount` mode always come from the store,
+/// since the workspace only holds an empty mount point.
+fn read_env_file(
+    entry: &state::WorkspaceEntry,
+    hints: &config::RepoHints,
+    workspace_dir: &Path,
+    file: &str,
+) -> Result<Option<String>, String> {
+    let shared_config = hints.shared.as_ref();
+    let mounted = shared_config
+        .is_some_and(|s| !s.encrypted && s.mode_for(file) == config::SharedMode::Mount);
+
+    let local = workspace_dir.join(file);
+    if !mounted && local.is_file() {
+        return std::fs::read_to_string(&local)
+            .map(Some)
+            .map_err(|e| e.to_string());
+    }
+
+    let (Some(shared_config), Some(shared_dir)) = (shared_config, config::shared_dir(&entry.repo))
+    else {
+        return Ok(None);
+    };
+    let key = store_key(shared_config).map_err(|e| e.to_string())?;
+    let store = shared::Store::new(&shared_dir, key.as_ref());
+    store
+        .read(file)
+        .map(|data| data.map(|d| String::from_utf8_lossy(&d).to_string()))
+        .map_err(|e| e.to_string())
+}
+
+/// Print a workspace's effective container environment, secrets masked.
+fn cmd_env(workspace_arg: Option<String>, reveal: bool) -> i32 {
+    let st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    let entry = if let Some(ws) = workspace_arg {
+        match st.resolve_workspace(&ws) {
+            Some(e) => e.clone(),
+            None => {
+                error!("unknown workspace '{ws}'");
+                return 1;
+            }
+        }
+    } else {
+        match detect_workspace(&st) {
+            Some(e) => e,
+            None => {
+                error!("not inside a dual workspace");
+                info!("Usage: dual env [workspace]");
+                return 1;
+            }
+        }
+    };
+
+    let workspace_dir = st.workspace_dir(&entry);
+    let hints = config::load_hints(&workspace_dir).unwrap_or_default();
+    let (env, masked) = workspace_env(&entry, &hints, &workspace_dir);
+
+    let mut names: Vec<_> = env.keys().collect();
+    names.sort();
+    for name in names {
+        if !reveal && (masked.contains(name) || secrets::looks_secret(name)) {
+            println!("{name}=********");
+        } else {
+            println!("{name}={}", env[name]);
+        }
+    }
+
+    0
+}
+
+/// Extra mounts for a workspace: shared files (see `shared::container_mounts`)
+/// and package-manager caches (see `create_cache_volumes`).
+fn workspace_mounts(
+    entry: &state::WorkspaceEntry,
+    hints: &config::RepoHints,
+) -> Vec<container::BindMount> {
+    let mut mounts = match config::shared_dir(&entry.repo) {
+        Some(dir) => shared::container_mounts(hints.shared.as_ref(), &dir, entry.path.is_some()),
+        None => Vec::new(),
+    };
+    mounts.extend(
+        workspace_caches(hints)
+            .iter()
+            .map(|cache| cache.mount(hints.cache_scope, &entry.repo)),
+    );
+    mounts
+}
+
+/// Create a workspace's labelled cache volumes if missing, right before its
+/// container is created. Computing a spec (drift checks, `dual env`) doesn't.
+fn create_cache_volumes(entry: &state::WorkspaceEntry, hints: &config::RepoHints) {
+    for cache in workspace_caches(hints) {
+        if let Err(e) = cache.ensure_volume(hints.cache_scope, &entry.repo) {
+            // Docker still creates the volume on mount, just without labels
+            warn!("cache {} volume not created: {e}", cache.name);
+        }
+    }
+}
+
+/// The caches configured in `.dual.toml`, skipping (and warning about) unknown presets.
+fn workspace_caches(hints: &config::RepoHints) -> Vec<cache::Cache> {
+    hints
+        .caches
+        .iter()
+        .filter_map(|entry| match cache::Cache::from_entry(entry) {
+            Ok(c) => Some(c),
+            Err(e) => {
+                warn!("{e}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// List cache volumes or empty them.
+fn cmd_cache(action: CacheAction) -> i32 {
+    let volumes = cache::list();
+
+    match action {
+        CacheAction::Ls => {
+            if volumes.is_empty() {
+                info!("No caches. Add `caches = [\"pnpm\"]` to .dual.toml to share one.");
+                return 0;
+            }
+            for volume in &volumes {
+                let scope = volume.repo.as_deref().unwrap_or("global");
+                let size = volume.size.as_deref().unwrap_or("?");
+                info!(
+                    "{:<10} {:<16} {size:>10}  {}",
+                    volume.cache, scope, volume.name
+                );
+            }
+            0
+        }
+        CacheAction::Clear { name, repo } => {
+            let selected: Vec<_> = volumes
+                .iter()
+                .filter(|v| name.as_ref().is_none_or(|n| *n == v.cache))
+                .filter(|v| repo.is_none() || v.repo == repo)
+                .collect();
+            if selected.is_empty() {
+                info!("No matching caches.");
+                return 0;
+            }
+
+            let mut failed = false;
+            for volume in selected {
+                info!("Clearing {}...", volume.name);
+                if let Err(e) = cache::clear(&volume.name) {
+                    error!("{e}");
+                    failed = true;
+                }
+            }
+            if failed { 1 } else { 0 }
+        }
+    }
+}
+
+/// Resolve the workspace for `dual sync`: explicit argument or current directory.
+fn resolve_sync_workspace(
+    st: &state::WorkspaceState,
+    workspace_arg: Option<String>,
+) -> Option<state::WorkspaceEntry> {
+    if let Some(ws) = workspace_arg {
+        let entry = st.resolve_workspace(&ws).cloned();
+        if entry.is_none() {
+            error!("unknown workspace '{ws}'");
+        }
+        entry
     } else {
-        match detect_workspace(&st) {
-            Some(e) => e,
-            None => {
-                error!("not inside a dual workspace");
-                info!("Usage: dual sync [workspace]");
-                return 1;
+        let entry = detect_workspace(st);
+        if entry.is_none() {
+            error!("not inside a dual workspace");
+            info!("Usage: dual sync [workspace]");
+        }
+        entry
+    }
+}
+
+/// Log the outcome of a branch sync.
+fn report_sync_results(results: &[(String, shared::SyncAction)]) {
+    for (file, action) in results {
+        match action {
+            shared::SyncAction::Unchanged => {}
+            shared::SyncAction::Pulled => info!("  synced {file}"),
+            shared::SyncAction::Pushed => info!("  pushed {file} → shared/"),
+            shared::SyncAction::SkippedLocal => {
+                warn!("  {file} changed locally (run `dual sync --push` to share it)")
             }
+            shared::SyncAction::Conflict => warn!(
+                "  {file} changed both locally and in shared/ (see `dual sync --diff`, resolve with --force)"
+            ),
+        }
+    }
+}
+
+/// Sync shared config files for a workspace.
+fn cmd_sync(workspace_arg: Option<String>, options: shared::SyncOptions) -> i32 {
+    let st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
         }
     };
 
+    // Resolve which workspace we're syncing
+    let Some(entry) = resolve_sync_workspace(&st, workspace_arg) else {
+        return 1;
+    };
+
     // Load hints
     let workspace_dir = st.workspace_dir(&entry);
     let hints = config::load_hints(&workspace_dir).unwrap_or_default();
@@ -726,12 +2561,20 @@ fn cmd_sync(workspace_arg: Option<String>) -> i32 {
             return 1;
         }
     };
+    let key = match store_key(shared_config) {
+        Ok(k) => k,
+        Err(e) => {
+            error!("could not unlock encrypted shared store: {e}");
+            return 1;
+        }
+    };
+    let store = shared::Store::new(&shared_dir, key.as_ref());
 
     let is_main = entry.path.is_some();
 
     if is_main {
         // Main workspace: init shared dir, then prompt to sync all branches
-        match shared::init_from_main(&workspace_dir, &shared_dir, &shared_config.files) {
+        match shared::init_from_main(&workspace_dir, store, &shared_config.files) {
             Ok(moved) => {
                 for f in &moved {
                     info!("  moved {f} → shared/");
@@ -743,6 +2586,18 @@ fn cmd_sync(workspace_arg: Option<String>) -> i32 {
             }
         }
 
+        // An encrypted store isn't linked, so main syncs its own copies first
+        if store.is_encrypted() {
+            let ws_id = config::workspace_id(&entry.repo, &entry.branch);
+            match shared::sync_workspace(&workspace_dir, store, &ws_id, shared_config, options) {
+                Ok(results) => report_sync_results(&results),
+                Err(e) => {
+                    error!("{e}");
+                    return 1;
+                }
+            }
+        }
+
         // Prompt to sync all branches
         let branches: Vec<_> = st
             .workspaces_for_repo(&entry.repo)
@@ -767,33 +2622,194 @@ fn cmd_sync(workspace_arg: Option<String>) -> i32 {
             return 0;
         }
 
+        // Pushing only makes sense from a branch; from main, only pull into branches
+        let pull_only = shared::SyncOptions {
+            push: false,
+            force: options.force,
+        };
         for branch_entry in &branches {
             let branch_dir = st.workspace_dir(branch_entry);
             if !branch_dir.exists() {
                 continue; // Not yet cloned
             }
             let ws_id = config::workspace_id(&branch_entry.repo, &branch_entry.branch);
-            match shared::copy_to_branch(&branch_dir, &shared_dir, &shared_config.files) {
-                Ok(copied) => {
-                    info!("{ws_id}: synced {} file(s)", copied.len());
+            match shared::sync_workspace(&branch_dir, store, &ws_id, shared_config, pull_only) {
+                Ok(results) => {
+                    let pulled = results
+                        .iter()
+                        .filter(|(_, a)| *a == shared::SyncAction::Pulled)
+                        .count();
+                    info!("{ws_id}: synced {pulled} file(s)");
+                    report_sync_results(&results);
+                    if pulled > 0 {
+                        let hints = config::load_hints(&branch_dir).unwrap_or_default();
+                        run_hooks(&st, HookEvent::PostSync, &hints, branch_entry, &branch_dir);
+                    }
                 }
                 Err(e) => error!("{ws_id}: {e}"),
             }
         }
     } else {
-        // Branch workspace: copy from shared dir
-        match shared::copy_to_branch(&workspace_dir, &shared_dir, &shared_config.files) {
-            Ok(copied) => {
-                if copied.is_empty() {
+        // Branch workspace: two-way sync against the shared dir
+        let ws_id = config::workspace_id(&entry.repo, &entry.branch);
+        match shared::sync_workspace(&workspace_dir, store, &ws_id, shared_config, options) {
+            Ok(results) => {
+                if results.is_empty() {
                     info!(
                         "No shared files available yet. Run `dual sync` in the main workspace first."
                     );
+                } else if results
+                    .iter()
+                    .all(|(_, a)| *a == shared::SyncAction::Unchanged)
+                {
+                    info!("Shared files are up to date.");
                 } else {
-                    for f in &copied {
-                        info!("  synced {f}");
-                    }
+                    report_sync_results(&results);
                 }
+                if results
+                    .iter()
+                    .any(|(_, a)| *a == shared::SyncAction::Pulled)
+                    && !run_hooks(&st, HookEvent::PostSync, &hints, &entry, &workspace_dir)
+                {
+                    return 1;
+                }
+                if results
+                    .iter()
+                    .any(|(_, a)| *a == shared::SyncAction::Conflict)
+                {
+                    return 1;
+                }
+            }
+            Err(e) => {
+                error!("{e}");
+                return 1;
+            }
+        }
+    }
+
+    0
+}
+
+/// Show which workspaces have shared files that differ from the store.
+fn cmd_sync_status(workspace_arg: Option<String>) -> i32 {
+    let st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    let entries: Vec<state::WorkspaceEntry> = match workspace_arg {
+        Some(ws) => match st.resolve_workspace(&ws) {
+            Some(e) => vec![e.clone()],
+            None => {
+                error!("unknown workspace '{ws}'");
+                return 1;
+            }
+        },
+        None => st.all_workspaces().to_vec(),
+    };
+
+    for entry in &entries {
+        let ws_id = config::workspace_id(&entry.repo, &entry.branch);
+        let workspace_dir = st.workspace_dir(entry);
+        if !workspace_dir.exists() {
+            continue;
+        }
+        let hints = config::load_hints(&workspace_dir).unwrap_or_default();
+        let Some(shared_config) = hints.shared.filter(|s| !s.files.is_empty()) else {
+            continue;
+        };
+
+        if entry.path.is_some() && !shared_config.encrypted {
+            println!("  {ws_id:<32} linked (main workspace)");
+            continue;
+        }
+
+        let Some(shared_dir) = config::shared_dir(&entry.repo) else {
+            continue;
+        };
+        let key = match store_key(&shared_config) {
+            Ok(k) => k,
+            Err(e) => {
+                println!("  {ws_id:<32} {e}");
+                continue;
+            }
+        };
+        let store = shared::Store::new(&shared_dir, key.as_ref());
+        let reports = match shared::report(&workspace_dir, store, &ws_id, &shared_config) {
+            Ok(r) => r,
+            Err(e) => {
+                println!("  {ws_id:<32} {e}");
+                continue;
             }
+        };
+        let dirty: Vec<String> = reports
+            .iter()
+            .filter(|r| !r.clean)
+            .map(|r| format!("{} ({})", r.file, r.state))
+            .collect();
+
+        if dirty.is_empty() {
+            println!("  {ws_id:<32} up to date");
+        } else {
+            println!("  {ws_id:<32} {}", dirty.join(", "));
+        }
+    }
+
+    0
+}
+
+/// Print diffs between a workspace's shared files and the shared store.
+fn cmd_sync_diff(workspace_arg: Option<String>) -> i32 {
+    let st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    let Some(entry) = resolve_sync_workspace(&st, workspace_arg) else {
+        return 1;
+    };
+
+    let workspace_dir = st.workspace_dir(&entry);
+    let hints = config::load_hints(&workspace_dir).unwrap_or_default();
+    let Some(shared_config) = hints.shared.filter(|s| !s.files.is_empty()) else {
+        error!("no [shared] section in .dual.toml (or files list is empty)");
+        return 1;
+    };
+
+    let Some(shared_dir) = config::shared_dir(&entry.repo) else {
+        error!("could not determine home directory");
+        return 1;
+    };
+    let key = match store_key(&shared_config) {
+        Ok(k) => k,
+        Err(e) => {
+            error!("could not unlock encrypted shared store: {e}");
+            return 1;
+        }
+    };
+    let store = shared::Store::new(&shared_dir, key.as_ref());
+    let ws_id = config::workspace_id(&entry.repo, &entry.branch);
+    let reports = match shared::report(&workspace_dir, store, &ws_id, &shared_config) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    // Mounted files have no workspace copy to compare
+    for r in reports
+        .iter()
+        .filter(|r| !r.clean && r.mode != config::SharedMode::Mount)
+    {
+        match shared::diff(&workspace_dir, store, &r.file) {
+            Ok(patch) => print!("{patch}"),
             Err(e) => {
                 error!("{e}");
                 return 1;
@@ -804,6 +2820,82 @@ fn cmd_sync(workspace_arg: Option<String>) -> i32 {
     0
 }
 
+/// Show the resolved shared file set for a workspace and what `dual sync` would do.
+fn cmd_sync_dry_run(workspace_arg: Option<String>) -> i32 {
+    let st = match state::load() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            return 1;
+        }
+    };
+
+    let Some(entry) = resolve_sync_workspace(&st, workspace_arg) else {
+        return 1;
+    };
+
+    let workspace_dir = st.workspace_dir(&entry);
+    let hints = config::load_hints(&workspace_dir).unwrap_or_default();
+    let Some(shared_config) = hints.shared.filter(|s| !s.files.is_empty()) else {
+        error!("no [shared] section in .dual.toml (or files list is empty)");
+        return 1;
+    };
+
+    let Some(shared_dir) = config::shared_dir(&entry.repo) else {
+        error!("could not determine home directory");
+        return 1;
+    };
+
+    if entry.path.is_some() && !shared_config.encrypted {
+        // Main workspace: files are moved into the shared dir and linked back
+        let files = match shared::resolve_files(&[&workspace_dir], &shared_config.files) {
+            Ok(f) => f,
+            Err(e) => {
+                error!("{e}");
+                return 1;
+            }
+        };
+        for file in &files {
+            let state = if shared::is_linked(&workspace_dir, &shared_dir, file) {
+                "linked"
+            } else if workspace_dir.join(file).exists() {
+                "would move to shared/"
+            } else {
+                "missing"
+            };
+            println!("  {file:<40} {state}");
+        }
+        return 0;
+    }
+
+    let key = match store_key(&shared_config) {
+        Ok(k) => k,
+        Err(e) => {
+            error!("could not unlock encrypted shared store: {e}");
+            return 1;
+        }
+    };
+    let store = shared::Store::new(&shared_dir, key.as_ref());
+    let ws_id = config::workspace_id(&entry.repo, &entry.branch);
+    match shared::report(&workspace_dir, store, &ws_id, &shared_config) {
+        Ok(reports) => {
+            for r in &reports {
+                let mode = match r.mode {
+                    config::SharedMode::Copy => "copy",
+                    config::SharedMode::Symlink => "symlink",
+                    config::SharedMode::Mount => "mount",
+                };
+                println!("  {:<40} {mode:<8} {}", r.file, r.state);
+            }
+            0
+        }
+        Err(e) => {
+            error!("{e}");
+            1
+        }
+    }
+}
+
 /// Detect the repo name from the current working directory.
 ///
 /// Matches the git remote URL of the cwd against known workspace URLs in state.
@@ -861,6 +2953,12 @@ fn detect_workspace(st: &state::WorkspaceState) -> Option<state::WorkspaceEntry>
 /// Print workspace status grouped by repo.
 fn print_workspace_status(st: &state::WorkspaceState, backend: &dyn MultiplexerBackend) {
     let workspace_root = st.workspace_root();
+    // Sessions tagged with their workspace are found even if renamed
+    let tagged: HashSet<String> = backend
+        .list_sessions()
+        .into_iter()
+        .map(|(_, workspace_id)| workspace_id)
+        .collect();
 
     // Collect unique repo names in order of first appearance
     let mut repos: Vec<String> = Vec::new();
@@ -885,7 +2983,8 @@ fn print_workspace_status(st: &state::WorkspaceState, backend: &dyn MultiplexerB
                 clone::workspace_exists(&workspace_root, &ws.repo, &ws.branch)
             };
             let container_st = container::status(&container_name);
-            let tmux_alive = backend.is_alive(&session_name);
+            let tmux_alive = tagged.contains(&config::workspace_id(&ws.repo, &ws.branch))
+                || backend.is_alive(&session_name);
 
             let (icon, status_text) = match (&container_st, tmux_alive) {
                 (container::ContainerStatus::Running, true) => {
@@ -909,8 +3008,7 @@ fn print_workspace_status(st: &state::WorkspaceState, backend: &dyn MultiplexerB
                 }
             };
 
-            let branch_display = config::decode_branch(&config::encode_branch(&ws.branch));
-            println!("  {branch_display:<24} {icon} {status_text}");
+            println!("  {:<24} {icon} {status_text}", ws.branch);
         }
         println!();
     }
@@ -965,7 +3063,9 @@ fn derive_repo_name(path: &std::path::Path) -> String {
 #[cfg(test)]
 mod tests {
     use clap::Parser;
-    use dual::cli::{Cli, Command};
+    use dual::cli::{
+        CacheAction, Cli, Command, ProxyAction, SecretsAction, TopSort, VolumesAction,
+    };
 
     #[test]
     fn no_args_is_default() {
@@ -982,8 +3082,9 @@ mod tests {
     #[test]
     fn launch_subcommand() {
         let cli = Cli::parse_from(["dual", "launch", "lightfast-main"]);
-        if let Some(Command::Launch { workspace }) = cli.command {
+        if let Some(Command::Launch { workspace, wait }) = cli.command {
             assert_eq!(workspace.as_deref(), Some("lightfast-main"));
+            assert!(!wait);
         } else {
             panic!("expected Launch command");
         }
@@ -992,18 +3093,34 @@ mod tests {
     #[test]
     fn launch_no_workspace() {
         let cli = Cli::parse_from(["dual", "launch"]);
-        if let Some(Command::Launch { workspace }) = cli.command {
+        if let Some(Command::Launch { workspace, .. }) = cli.command {
             assert!(workspace.is_none());
         } else {
             panic!("expected Launch command");
         }
     }
 
+    #[test]
+    fn launch_wait_flag() {
+        let cli = Cli::parse_from(["dual", "launch", "lightfast-main", "--wait"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Launch { wait: true, .. })
+        ));
+    }
+
     #[test]
     fn destroy_subcommand() {
         let cli = Cli::parse_from(["dual", "destroy", "lightfast-main"]);
-        if let Some(Command::Destroy { workspace }) = cli.command {
+        if let Some(Command::Destroy {
+            workspace,
+            force,
+            archive,
+        }) = cli.command
+        {
             assert_eq!(workspace.as_deref(), Some("lightfast-main"));
+            assert!(!force);
+            assert!(!archive);
         } else {
             panic!("expected Destroy command");
         }
@@ -1012,13 +3129,55 @@ mod tests {
     #[test]
     fn destroy_no_workspace() {
         let cli = Cli::parse_from(["dual", "destroy"]);
-        if let Some(Command::Destroy { workspace }) = cli.command {
+        if let Some(Command::Destroy { workspace, .. }) = cli.command {
             assert!(workspace.is_none());
         } else {
             panic!("expected Destroy command");
         }
     }
 
+    #[test]
+    fn destroy_with_force_and_archive() {
+        let cli = Cli::parse_from(["dual", "destroy", "lightfast-main", "--force", "--archive"]);
+        if let Some(Command::Destroy { force, archive, .. }) = cli.command {
+            assert!(force);
+            assert!(archive);
+        } else {
+            panic!("expected Destroy command");
+        }
+    }
+
+    #[test]
+    fn rename_subcommand() {
+        let cli = Cli::parse_from(["dual", "rename", "lightfast-feat__auth", "feat/login"]);
+        if let Some(Command::Rename {
+            workspace,
+            new_branch,
+        }) = cli.command
+        {
+            assert_eq!(workspace, "lightfast-feat__auth");
+            assert_eq!(new_branch, "feat/login");
+        } else {
+            panic!("expected Rename command");
+        }
+    }
+
+    #[test]
+    fn migrate_subcommand() {
+        let cli = Cli::parse_from(["dual", "migrate"]);
+        assert!(matches!(cli.command, Some(Command::Migrate)));
+    }
+
+    #[test]
+    fn restore_subcommand() {
+        let cli = Cli::parse_from(["dual", "restore", "lightfast-feat__auth"]);
+        if let Some(Command::Restore { archive }) = cli.command {
+            assert_eq!(archive.as_deref(), Some("lightfast-feat__auth"));
+        } else {
+            panic!("expected Restore command");
+        }
+    }
+
     #[test]
     fn add_subcommand() {
         let cli = Cli::parse_from(["dual", "add"]);
@@ -1064,8 +3223,15 @@ mod tests {
     #[test]
     fn open_without_workspace() {
         let cli = Cli::parse_from(["dual", "open"]);
-        if let Some(Command::Open { workspace }) = cli.command {
+        if let Some(Command::Open {
+            workspace,
+            service,
+            no_wait,
+        }) = cli.command
+        {
             assert!(workspace.is_none());
+            assert!(service.is_none());
+            assert!(!no_wait);
         } else {
             panic!("expected Open command");
         }
@@ -1074,13 +3240,54 @@ mod tests {
     #[test]
     fn open_with_workspace() {
         let cli = Cli::parse_from(["dual", "open", "lightfast-feat__auth"]);
-        if let Some(Command::Open { workspace }) = cli.command {
+        if let Some(Command::Open { workspace, .. }) = cli.command {
             assert_eq!(workspace.as_deref(), Some("lightfast-feat__auth"));
         } else {
             panic!("expected Open command");
         }
     }
 
+    #[test]
+    fn open_single_service() {
+        let cli = Cli::parse_from(["dual", "open", "lightfast-main", "--service", "app"]);
+        if let Some(Command::Open {
+            workspace, service, ..
+        }) = cli.command
+        {
+            assert_eq!(workspace.as_deref(), Some("lightfast-main"));
+            assert_eq!(service.as_deref(), Some("app"));
+        } else {
+            panic!("expected Open command");
+        }
+    }
+
+    #[test]
+    fn wait_subcommand() {
+        let cli = Cli::parse_from(["dual", "wait", "lightfast-main", "--service", "api"]);
+        if let Some(Command::Wait {
+            workspace,
+            service,
+            timeout,
+        }) = cli.command
+        {
+            assert_eq!(workspace.as_deref(), Some("lightfast-main"));
+            assert_eq!(service.as_deref(), Some("api"));
+            assert_eq!(timeout, 120);
+        } else {
+            panic!("expected Wait command");
+        }
+
+        let cli = Cli::parse_from(["dual", "wait", "--timeout", "5"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Wait {
+                workspace: None,
+                timeout: 5,
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn urls_without_workspace() {
         let cli = Cli::parse_from(["dual", "urls"]);
@@ -1104,7 +3311,21 @@ mod tests {
     #[test]
     fn proxy_subcommand() {
         let cli = Cli::parse_from(["dual", "proxy"]);
-        assert!(matches!(cli.command, Some(Command::Proxy)));
+        assert!(matches!(cli.command, Some(Command::Proxy { action: None })));
+    }
+
+    #[test]
+    fn proxy_log_subcommand() {
+        let cli = Cli::parse_from(["dual", "proxy", "log", "-f", "app-main"]);
+        if let Some(Command::Proxy {
+            action: Some(ProxyAction::Log { workspace, follow }),
+        }) = cli.command
+        {
+            assert_eq!(workspace.as_deref(), Some("app-main"));
+            assert!(follow);
+        } else {
+            panic!("expected Proxy log command");
+        }
     }
 
     #[test]
@@ -1120,8 +3341,16 @@ mod tests {
     #[test]
     fn sync_subcommand_no_args() {
         let cli = Cli::parse_from(["dual", "sync"]);
-        if let Some(Command::Sync { workspace }) = cli.command {
+        if let Some(Command::Sync {
+            workspace,
+            status,
+            push,
+            ..
+        }) = cli.command
+        {
             assert!(workspace.is_none());
+            assert!(!status);
+            assert!(!push);
         } else {
             panic!("expected Sync command");
         }
@@ -1130,13 +3359,251 @@ mod tests {
     #[test]
     fn sync_subcommand_with_workspace() {
         let cli = Cli::parse_from(["dual", "sync", "lightfast-feat__auth"]);
-        if let Some(Command::Sync { workspace }) = cli.command {
+        if let Some(Command::Sync { workspace, .. }) = cli.command {
             assert_eq!(workspace.as_deref(), Some("lightfast-feat__auth"));
         } else {
             panic!("expected Sync command");
         }
     }
 
+    #[test]
+    fn secrets_subcommands() {
+        let cli = Cli::parse_from([
+            "dual",
+            "secrets",
+            "set",
+            "API_TOKEN",
+            "abc",
+            "--repo",
+            "app",
+        ]);
+        if let Some(Command::Secrets {
+            action: SecretsAction::Set { name, value, repo },
+        }) = cli.command
+        {
+            assert_eq!(name, "API_TOKEN");
+            assert_eq!(value.as_deref(), Some("abc"));
+            assert_eq!(repo.as_deref(), Some("app"));
+        } else {
+            panic!("expected Secrets Set command");
+        }
+
+        let cli = Cli::parse_from(["dual", "secrets", "set", "API_TOKEN"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Secrets {
+                action: SecretsAction::Set { value: None, .. }
+            })
+        ));
+
+        let cli = Cli::parse_from(["dual", "secrets", "list"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Secrets {
+                action: SecretsAction::List { repo: None }
+            })
+        ));
+
+        let cli = Cli::parse_from(["dual", "secrets", "rm", "API_TOKEN"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Secrets {
+                action: SecretsAction::Rm { .. }
+            })
+        ));
+    }
+
+    #[test]
+    fn top_subcommand() {
+        let cli = Cli::parse_from(["dual", "top"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Top {
+                sort: TopSort::Cpu,
+                interval: 2,
+                once: false
+            })
+        ));
+
+        let cli = Cli::parse_from(["dual", "top", "--sort", "mem", "--once"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Top {
+                sort: TopSort::Mem,
+                once: true,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn top_sorts_highest_first() {
+        let sample = |name: &str, cpu: f64, pids: u64| dual::container::ContainerStats {
+            name: name.to_string(),
+            cpu_percent: cpu,
+            memory: String::new(),
+            memory_percent: 0.0,
+            pids,
+        };
+        let mut stats = vec![
+            sample("b", 5.0, 10),
+            sample("a", 50.0, 3),
+            sample("c", 0.5, 99),
+        ];
+
+        super::sort_stats(&mut stats, TopSort::Cpu);
+        assert_eq!(stats[0].name, "a");
+        super::sort_stats(&mut stats, TopSort::Pids);
+        assert_eq!(stats[0].name, "c");
+        super::sort_stats(&mut stats, TopSort::Name);
+        assert_eq!(stats[0].name, "a");
+    }
+
+    #[test]
+    fn cache_subcommands() {
+        let cli = Cli::parse_from(["dual", "cache", "ls"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Cache {
+                action: CacheAction::Ls
+            })
+        ));
+
+        let cli = Cli::parse_from(["dual", "cache", "clear", "pnpm", "--repo", "app"]);
+        if let Some(Command::Cache {
+            action: CacheAction::Clear { name, repo },
+        }) = cli.command
+        {
+            assert_eq!(name.as_deref(), Some("pnpm"));
+            assert_eq!(repo.as_deref(), Some("app"));
+        } else {
+            panic!("expected Cache Clear command");
+        }
+    }
+
+    #[test]
+    fn volumes_subcommands() {
+        let cli = Cli::parse_from(["dual", "volumes"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Volumes { action: None })
+        ));
+
+        let cli = Cli::parse_from(["dual", "volumes", "ls", "app-main"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Volumes {
+                action: Some(VolumesAction::Ls { workspace: Some(_) })
+            })
+        ));
+
+        let cli = Cli::parse_from(["dual", "volumes", "seed", "app-feat", "--from", "app-main"]);
+        if let Some(Command::Volumes {
+            action: Some(VolumesAction::Seed { workspace, from }),
+        }) = cli.command
+        {
+            assert_eq!(workspace, "app-feat");
+            assert_eq!(from.as_deref(), Some("app-main"));
+        } else {
+            panic!("expected Volumes Seed command");
+        }
+    }
+
+    #[test]
+    fn setup_subcommand() {
+        let cli = Cli::parse_from(["dual", "setup", "app-main", "--rerun"]);
+        if let Some(Command::Setup { workspace, rerun }) = cli.command {
+            assert_eq!(workspace.as_deref(), Some("app-main"));
+            assert!(rerun);
+        } else {
+            panic!("expected Setup command");
+        }
+
+        let cli = Cli::parse_from(["dual", "setup"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Setup {
+                workspace: None,
+                rerun: false
+            })
+        ));
+    }
+
+    #[test]
+    fn recreate_subcommand() {
+        let cli = Cli::parse_from(["dual", "recreate", "app-main", "--force"]);
+        if let Some(Command::Recreate { workspace, force }) = cli.command {
+            assert_eq!(workspace.as_deref(), Some("app-main"));
+            assert!(force);
+        } else {
+            panic!("expected Recreate command");
+        }
+
+        let cli = Cli::parse_from(["dual", "recreate"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Recreate {
+                workspace: None,
+                force: false
+            })
+        ));
+    }
+
+    #[test]
+    fn env_subcommand() {
+        let cli = Cli::parse_from(["dual", "env", "app-feat__auth"]);
+        if let Some(Command::Env { workspace, reveal }) = cli.command {
+            assert_eq!(workspace.as_deref(), Some("app-feat__auth"));
+            assert!(!reveal);
+        } else {
+            panic!("expected Env command");
+        }
+
+        let cli = Cli::parse_from(["dual", "env"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Env {
+                workspace: None,
+                reveal: false
+            })
+        ));
+
+        let cli = Cli::parse_from(["dual", "env", "--reveal"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Env {
+                workspace: None,
+                reveal: true
+            })
+        ));
+    }
+
+    #[test]
+    fn sync_subcommand_flags() {
+        let cli = Cli::parse_from(["dual", "sync", "--status"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Sync { status: true, .. })
+        ));
+
+        let cli = Cli::parse_from(["dual", "sync", "--dry-run", "app-main"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Sync { dry_run: true, .. })
+        ));
+
+        let cli = Cli::parse_from(["dual", "sync", "--push", "--force"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Sync {
+                push: true,
+                force: true,
+                diff: false,
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn derive_repo_name_from_path() {
         use std::path::Path;
@@ -1,70 +1,364 @@
-use std::collections::HashMap;
-use std::net::SocketAddr;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
+use http_body_util::combinators::BoxBody;
 use http_body_util::{BodyExt, Full};
-use hyper::body::{Bytes, Incoming};
-use hyper::server::conn::http1;
+use hyper::body::{Body, Bytes, Frame, Incoming, SizeHint};
+use hyper::header::{self, HeaderMap, HeaderValue};
 use hyper::service::service_fn;
-use hyper::{Request, Response, StatusCode};
-use hyper_util::rt::TokioIo;
+use hyper::{Request, Response, StatusCode, Version};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpListener;
+use tokio::sync::OnceCell;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::{self, ServerConfig};
 
 use tracing::{debug, info, warn};
 
-use crate::config;
-use crate::container;
-use crate::state::WorkspaceState;
+use crate::access_log::{self, AccessEntry, AccessLog, Recent};
+use crate::config::{self, PortEntry, RepoHints};
+use crate::container::{self, ContainerError};
+use crate::health::Health;
+use crate::services;
+use crate::state::{ProxyConfig, WorkspaceEntry, WorkspaceState};
+use crate::tls;
+
+/// First byte of a TLS handshake record; plain HTTP starts with a method name.
+const TLS_HANDSHAKE: u8 = 0x16;
+
+/// How long an idle upstream connection stays in the pool.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Path on bare `localhost` serving recent requests as JSON (`[proxy] inspect`).
+const INSPECT_PATH: &str = "/_dual/requests";
+
+/// How often a running proxy checks whether the local CA is due for renewal.
+const CA_RENEWAL_CHECK: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long a request for a woken workspace waits for its port to accept connections.
+const WAKE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Response body: streamed from the container, or generated by the proxy.
+type ProxyBody = BoxBody<Bytes, hyper::Error>;
 
 /// Build a 502 Bad Gateway response with a text body.
-fn bad_gateway(body: impl Into<String>) -> Response<Full<Bytes>> {
+fn bad_gateway(body: impl Into<String>) -> Response<ProxyBody> {
     Response::builder()
         .status(StatusCode::BAD_GATEWAY)
-        .body(Full::new(Bytes::from(body.into())))
+        .body(
+            Full::new(Bytes::from(body.into()))
+                .map_err(|never| match never {})
+                .boxed(),
+        )
+        .expect("valid status code always produces valid response")
+}
+
+/// Pooled clients for container ports, keeping connections alive per (ip, port).
+pub struct Upstreams {
+    http1: Client<HttpConnector, Incoming>,
+    /// For ports marked `http2`, speaking HTTP/2 without TLS (h2c)
+    h2c: Client<HttpConnector, Incoming>,
+}
+
+impl Default for Upstreams {
+    fn default() -> Self {
+        let mut http1 = Client::builder(TokioExecutor::new());
+        http1.pool_idle_timeout(POOL_IDLE_TIMEOUT);
+        let mut h2c = Client::builder(TokioExecutor::new());
+        h2c.pool_idle_timeout(POOL_IDLE_TIMEOUT).http2_only(true);
+        Upstreams {
+            http1: http1.build_http(),
+            h2c: h2c.build_http(),
+        }
+    }
+}
+
+/// Build an HTML page response.
+fn html_page(status: StatusCode, html: String) -> Response<ProxyBody> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(
+            Full::new(Bytes::from(html))
+                .map_err(|never| match never {})
+                .boxed(),
+        )
         .expect("valid status code always produces valid response")
 }
 
+/// Build a JSON response.
+fn json_response(json: String) -> Response<ProxyBody> {
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(
+            Full::new(Bytes::from(json))
+                .map_err(|never| match never {})
+                .boxed(),
+        )
+        .expect("valid header always produces valid response")
+}
+
+/// Escape text for HTML element content and attribute values.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A response body that reports how many bytes it carried once it ends or is dropped.
+struct Counted {
+    inner: ProxyBody,
+    bytes: u64,
+    on_done: Option<Box<dyn FnOnce(u64) + Send + Sync>>,
+}
+
+impl Counted {
+    fn finish(&mut self) {
+        if let Some(on_done) = self.on_done.take() {
+            on_done(self.bytes);
+        }
+    }
+}
+
+impl Body for Counted {
+    type Data = Bytes;
+    type Error = hyper::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, hyper::Error>>> {
+        let poll = Pin::new(&mut self.inner).poll_frame(cx);
+        match poll {
+            Poll::Ready(Some(Ok(ref frame))) => {
+                if let Some(data) = frame.data_ref() {
+                    self.bytes += data.len() as u64;
+                }
+            }
+            Poll::Ready(None) => self.finish(),
+            _ => {}
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// A client connection to the proxy.
+#[derive(Debug, Clone, Copy)]
+struct Connection {
+    listen_port: u16,
+    peer: SocketAddr,
+    tls: bool,
+}
+
+/// The container the proxy itself runs in, if any (its hostname is the container ID).
+fn own_container() -> Option<String> {
+    if !std::path::Path::new("/.dockerenv").exists() {
+        return None;
+    }
+    std::fs::read_to_string("/etc/hostname")
+        .ok()
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+}
+
 /// Routing entry: subdomain → container IP.
 type RouteMap = HashMap<String, String>;
 
+/// A `tcp_ports` entry forwarded from a host port to a container.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TcpForward {
+    pub workspace_id: String,
+    pub name: String,
+    /// Port on 127.0.0.1, allocated in state
+    pub host_port: u16,
+    /// Container address (ip:port)
+    pub target: String,
+}
+
+/// A stopped workspace the proxy starts when a request arrives for it.
+#[derive(Debug, Clone)]
+pub struct Sleeping {
+    pub entry: WorkspaceEntry,
+    pub workspace_dir: PathBuf,
+    pub hints: RepoHints,
+}
+
+impl Sleeping {
+    /// Whether the workspace serves `port` through the proxy.
+    fn serves(&self, port: u16) -> bool {
+        self.hints.ports.iter().any(|p| p.port() == port)
+    }
+}
+
 /// Full routing state: port → (subdomain → container_ip).
+#[derive(Default)]
 pub struct ProxyState {
     /// Map of port → RouteMap (subdomain → container_ip).
     pub routes: HashMap<u16, RouteMap>,
+
+    /// Raw TCP forwards for `tcp_ports`.
+    pub tcp: Vec<TcpForward>,
+
+    /// Named ports: workspace id → (service name → port).
+    pub services: HashMap<String, HashMap<String, u16>>,
+
+    /// Ports proxied over HTTP/2: (workspace id, port).
+    pub h2c: HashSet<(String, u16)>,
+
+    /// Upstream connection pools shared by all listeners.
+    pub upstreams: Upstreams,
+
+    /// Stopped workspaces started on request (`[proxy] wake`), by workspace id.
+    pub sleeping: HashMap<String, Sleeping>,
+
+    /// Container IPs of sleeping workspaces woken since the proxy started,
+    /// each set once by the first request to wake it.
+    woken: Mutex<HashMap<String, Arc<OnceCell<String>>>>,
+
+    /// Every workspace's URLs, for the status page.
+    pub workspaces: Vec<WorkspaceUrls>,
+
+    /// Where each request is logged, if the log could be opened.
+    pub access_log: Option<AccessLog>,
+
+    /// Recent requests for `/_dual/requests` (`[proxy] inspect`).
+    pub recent: Option<Recent>,
+
+    /// In single-port mode, the one port every workspace port is served on
+    /// as `{port}.{workspace}.localhost`.
+    pub single_port: Option<u16>,
 }
 
 impl ProxyState {
     /// Build routing table from workspace state and running containers.
+    ///
+    /// Each container is reached on its workspace network. A proxy running in
+    /// a container itself joins those networks first; containers created
+    /// before workspaces had their own network are reached on the default bridge.
     pub fn from_state(state: &WorkspaceState) -> Self {
         let mut routes: HashMap<u16, RouteMap> = HashMap::new();
+        let mut tcp = Vec::new();
+        let mut services: HashMap<String, HashMap<String, u16>> = HashMap::new();
+        let mut h2c = HashSet::new();
+        let mut sleeping = HashMap::new();
+        let own_container = own_container();
 
         for entry in state.all_workspaces() {
             let container_name = config::container_name(&entry.repo, &entry.branch);
 
-            // Only route to running containers
-            if container::status(&container_name) != container::ContainerStatus::Running {
-                continue;
-            }
-
-            let ip = match container::get_ip(&container_name) {
-                Some(ip) => ip,
-                None => continue,
+            // Only route to running containers, and stopped ones when they wake on request
+            let running = match container::status(&container_name) {
+                container::ContainerStatus::Running => true,
+                container::ContainerStatus::Stopped if state.proxy.wake => false,
+                _ => continue,
             };
 
+            let network = config::network_name(&entry.repo, &entry.branch);
+            if let Some(ref proxy) = own_container
+                && let Err(e) = container::connect_network(&network, proxy)
+            {
+                debug!("could not join {network}: {e}");
+            }
+
             // Load hints to get ports
             let ws_dir = state.workspace_dir(entry);
             let hints = config::load_hints(&ws_dir).unwrap_or_default();
 
             let workspace_id = config::workspace_id(&entry.repo, &entry.branch);
-            for &port in &hints.ports {
+            for port in &hints.ports {
+                if let Some(name) = port.name() {
+                    services
+                        .entry(workspace_id.clone())
+                        .or_default()
+                        .insert(name.to_string(), port.port());
+                }
+                if port.http2() {
+                    h2c.insert((workspace_id.clone(), port.port()));
+                }
+            }
+
+            if !running {
+                sleeping.insert(
+                    workspace_id,
+                    Sleeping {
+                        entry: entry.clone(),
+                        workspace_dir: ws_dir,
+                        hints,
+                    },
+                );
+                continue;
+            }
+
+            let Some(ip) = container::workspace_ip(&container_name, &network) else {
+                continue;
+            };
+
+            for port in &hints.ports {
                 routes
-                    .entry(port)
+                    .entry(port.port())
                     .or_default()
                     .insert(workspace_id.clone(), ip.clone());
             }
+            for tcp_port in &hints.tcp_ports {
+                if let Some(host_port) = state.tcp_port(&workspace_id, tcp_port.port()) {
+                    tcp.push(TcpForward {
+                        workspace_id: workspace_id.clone(),
+                        name: tcp_port.name(),
+                        host_port,
+                        target: format!("{ip}:{}", tcp_port.port()),
+                    });
+                }
+            }
         }
 
-        ProxyState { routes }
+        ProxyState {
+            routes,
+            tcp,
+            services,
+            h2c,
+            upstreams: Upstreams::default(),
+            sleeping,
+            woken: Mutex::default(),
+            workspaces: workspace_urls(state),
+            access_log: None,
+            recent: state
+                .proxy
+                .inspect
+                .then(|| Recent::new(access_log::RECENT_CAPACITY)),
+            single_port: state.proxy.port,
+        }
     }
 
     /// Get the container IP for a given port and subdomain.
@@ -75,18 +369,255 @@ impl ProxyState {
             .map(|s| s.as_str())
     }
 
-    /// Get all unique ports that need listeners.
+    /// Ids of every workspace the proxy routes to, running or sleeping.
+    pub fn workspace_ids(&self) -> HashSet<String> {
+        self.routes
+            .values()
+            .flat_map(|routes| routes.keys().cloned())
+            .chain(self.sleeping.keys().cloned())
+            .collect()
+    }
+
+    /// Get all unique ports that need listeners, including those of sleeping workspaces.
     pub fn ports(&self) -> Vec<u16> {
-        self.routes.keys().copied().collect()
+        let ports: HashSet<u16> = self
+            .routes
+            .keys()
+            .copied()
+            .chain(
+                self.sleeping
+                    .values()
+                    .flat_map(|s| s.hints.ports.iter().map(PortEntry::port)),
+            )
+            .collect();
+        ports.into_iter().collect()
     }
+
+    /// Ports the proxy listens on: the single port, or every routed port.
+    pub fn listen_ports(&self) -> Vec<u16> {
+        match self.single_port {
+            Some(port) => vec![port],
+            None => self.ports(),
+        }
+    }
+
+    /// Container IP and port for a request's subdomain on a listening port.
+    pub fn route(&self, listen_port: u16, subdomain: &str) -> Option<(&str, u16)> {
+        let (workspace_id, port) = self.target(listen_port, subdomain)?;
+        self.resolve(port, workspace_id).map(|ip| (ip, port))
+    }
+
+    /// Workspace id and container port a request's subdomain asks for.
+    ///
+    /// `{name}.{workspace}` goes to a named port on any listener. Otherwise, in
+    /// single-port mode the subdomain is `{port}.{workspace}` and a bare
+    /// `{workspace}` goes to the workspace's lowest port.
+    fn target<'s>(&self, listen_port: u16, subdomain: &'s str) -> Option<(&'s str, u16)> {
+        Some(match subdomain.split_once('.') {
+            Some((label, workspace_id)) => {
+                let port = match label.parse() {
+                    Ok(port) if self.single_port.is_some() => port,
+                    _ => *self.services.get(workspace_id)?.get(label)?,
+                };
+                (workspace_id, port)
+            }
+            None if self.single_port.is_some() => (subdomain, self.default_port(subdomain)?),
+            None => (subdomain, listen_port),
+        })
+    }
+
+    /// A workspace's lowest routed port.
+    fn default_port(&self, workspace_id: &str) -> Option<u16> {
+        self.routes
+            .iter()
+            .filter(|(_, m)| m.contains_key(workspace_id))
+            .map(|(&port, _)| port)
+            .chain(
+                self.sleeping
+                    .get(workspace_id)
+                    .into_iter()
+                    .flat_map(|s| s.hints.ports.iter().map(PortEntry::port)),
+            )
+            .min()
+    }
+
+    /// Whether a workspace's container is up: routed, or woken by a request.
+    fn is_running(&self, workspace_id: &str) -> bool {
+        self.routes.values().any(|m| m.contains_key(workspace_id))
+            || self
+                .woken
+                .lock()
+                .expect("woken lock poisoned")
+                .get(workspace_id)
+                .is_some_and(|ip| ip.initialized())
+    }
+
+    /// Start a sleeping workspace and wait until `port` accepts connections,
+    /// returning its container IP. Concurrent requests for the same workspace
+    /// share one start; other workspaces wake independently.
+    async fn wake(&self, workspace_id: &str, port: u16) -> Result<String, WakeError> {
+        let cell = self
+            .woken
+            .lock()
+            .expect("woken lock poisoned")
+            .entry(workspace_id.to_string())
+            .or_default()
+            .clone();
+        let ip = cell
+            .get_or_try_init(|| async {
+                let sleeping = self.sleeping[workspace_id].clone();
+                tokio::task::spawn_blocking(move || start_workspace(&sleeping))
+                    .await
+                    .map_err(|_| WakeError::Interrupted(workspace_id.to_string()))?
+            })
+            .await?
+            .clone();
+
+        if wait_for_port(&format!("{ip}:{port}"), WAKE_TIMEOUT).await {
+            Ok(ip)
+        } else {
+            self.forget(workspace_id);
+            Err(WakeError::Timeout {
+                port,
+                secs: WAKE_TIMEOUT.as_secs(),
+            })
+        }
+    }
+
+    /// Drop a woken workspace's cached IP, e.g. once it stops answering, so
+    /// the next request wakes it again.
+    fn forget(&self, workspace_id: &str) {
+        self.woken
+            .lock()
+            .expect("woken lock poisoned")
+            .remove(workspace_id);
+    }
+
+    /// Record a finished request in the access log and recent requests.
+    fn record(&self, entry: AccessEntry) {
+        if let Some(ref log) = self.access_log
+            && let Err(e) = log.write(&entry)
+        {
+            debug!("access log write failed: {e}");
+        }
+        if let Some(ref recent) = self.recent {
+            recent.push(entry);
+        }
+    }
+
+    /// HTML page listing every workspace with its URLs and whether it's running.
+    fn status_page(&self, heading: &str) -> String {
+        let mut html = format!(
+            "<!doctype html>\n<html><head><meta charset=\"utf-8\"><title>dual</title>\
+             <style>body{{font-family:system-ui,sans-serif;margin:2rem}}\
+             .stopped{{color:#888}}</style></head>\n<body>\n<h1>{}</h1>\n",
+            escape_html(heading)
+        );
+        if self.workspaces.is_empty() {
+            html.push_str("<p>No workspaces have ports configured.</p>\n");
+        }
+        for workspace in &self.workspaces {
+            let id = &workspace.workspace_id;
+            let running = self.is_running(id);
+            let status = match (running, self.sleeping.contains_key(id)) {
+                (true, _) => "running",
+                (false, true) => "stopped, starts on request",
+                (false, false) => "stopped",
+            };
+            let class = if running { "" } else { " class=\"stopped\"" };
+            html.push_str(&format!(
+                "<h2{class}>{} <small>({status})</small></h2>\n<ul>\n",
+                escape_html(id)
+            ));
+            for service in &workspace.services {
+                let url = escape_html(&service.url);
+                let name = match service.name {
+                    Some(ref name) => format!("{} ", escape_html(name)),
+                    None => String::new(),
+                };
+                html.push_str(&format!("<li>{name}<a href=\"{url}\">{url}</a></li>\n"));
+            }
+            for (name, _, host_port) in &workspace.tcp {
+                html.push_str(&format!(
+                    "<li>{} 127.0.0.1:{host_port}</li>\n",
+                    escape_html(name)
+                ));
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</body></html>\n");
+        html
+    }
+}
+
+/// Start a stopped workspace's container, sidecars and `dev` command,
+/// returning the container's IP.
+///
+/// A container started since the proxy began (e.g. by `dual launch`) is left
+/// alone: its sidecars are only started if missing or stopped, and `dev`,
+/// already running, isn't run again.
+fn start_workspace(sleeping: &Sleeping) -> Result<String, WakeError> {
+    let entry = &sleeping.entry;
+    let container_name = config::container_name(&entry.repo, &entry.branch);
+    let stopped = container::status(&container_name) != container::ContainerStatus::Running;
+    if stopped {
+        info!("Waking {container_name}...");
+        container::start(&container_name)?;
+    }
+    services::start_all(entry, &sleeping.hints, &sleeping.workspace_dir, stopped);
+    if stopped
+        && let Some(ref dev) = sleeping.hints.dev
+        && let Err(e) = container::exec_detached(&container_name, dev)
+    {
+        warn!("dev command failed to start in {container_name}: {e}");
+    }
+
+    let network = config::network_name(&entry.repo, &entry.branch);
+    container::workspace_ip(&container_name, &network).ok_or(WakeError::NoAddress(container_name))
+}
+
+/// Poll until `addr` accepts TCP connections, giving up after `timeout`.
+async fn wait_for_port(addr: &str, timeout: Duration) -> bool {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        if tokio::net::TcpStream::connect(addr).await.is_ok() {
+            return true;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// The proxy URL of a workspace port, in the configured scheme and mode.
+pub fn url(proxy: &ProxyConfig, workspace_id: &str, entry: &PortEntry) -> String {
+    let (scheme, default_port) = if proxy.https {
+        ("https", 443)
+    } else {
+        ("http", 80)
+    };
+    let port = entry.port();
+    let host = match (entry.name(), proxy.port) {
+        (Some(name), _) => format!("{name}.{workspace_id}.localhost"),
+        (None, Some(_)) => format!("{port}.{workspace_id}.localhost"),
+        (None, None) => format!("{workspace_id}.localhost"),
+    };
+    let listen_port = proxy.port.unwrap_or(port);
+    let authority = if listen_port == default_port {
+        host
+    } else {
+        format!("{host}:{listen_port}")
+    };
+    let path = entry.path();
+    let path = if path == "/" { "" } else { path.as_str() };
+    format!("{scheme}://{authority}{path}")
 }
 
 /// Start the reverse proxy, listening on all configured ports.
 pub async fn start(state: &WorkspaceState) -> Result<(), Box<dyn std::error::Error>> {
-    let proxy_state = ProxyState::from_state(state);
-    let ports = proxy_state.ports();
-
-    if ports.is_empty() {
+    let mut proxy_state = ProxyState::from_state(state);
+    if proxy_state.ports().is_empty() && proxy_state.tcp.is_empty() {
         info!("No ports configured for proxy. Add 'ports' to .dual.toml in your repo.");
         info!("Example .dual.toml:");
         info!("  image = \"node:20\"");
@@ -94,28 +625,86 @@ pub async fn start(state: &WorkspaceState) -> Result<(), Box<dyn std::error::Err
         return Ok(());
     }
 
+    // HTTPS is served on the same ports as HTTP, told apart by the first byte
+    let tls = if state.proxy.https {
+        Some(tls_acceptor(proxy_state.workspace_ids())?)
+    } else {
+        None
+    };
+
+    if let Some(path) = access_log::access_log_path() {
+        match AccessLog::open(&path, access_log::MAX_LOG_BYTES) {
+            Ok(log) => proxy_state.access_log = Some(log),
+            Err(e) => warn!("cannot open access log {}: {e}", path.display()),
+        }
+    }
+
     let proxy_state = Arc::new(proxy_state);
 
     info!("Starting reverse proxy...");
     info!("Routes:");
     for (&port, routes) in &proxy_state.routes {
         for (subdomain, ip) in routes {
-            info!("  {subdomain}.localhost:{port} → {ip}:{port}");
+            let host = match proxy_state.single_port {
+                Some(listen) => format!("{port}.{subdomain}.localhost:{listen}"),
+                None => format!("{subdomain}.localhost:{port}"),
+            };
+            info!("  {host} → {ip}:{port}");
         }
     }
+    for workspace_id in proxy_state.sleeping.keys() {
+        info!("  {workspace_id} is stopped and starts on request");
+    }
 
     let mut handles = Vec::new();
 
-    for port in ports {
+    // Raw TCP forwards; a host port taken by something else only skips that forward
+    for forward in &proxy_state.tcp {
+        let addr = SocketAddr::from(([127, 0, 0, 1], forward.host_port));
+        match TcpListener::bind(addr).await {
+            Ok(listener) => {
+                info!(
+                    "  {} {} 127.0.0.1:{} → {}",
+                    forward.workspace_id, forward.name, forward.host_port, forward.target
+                );
+                handles.push(tokio::spawn(forward_tcp(listener, forward.target.clone())));
+            }
+            Err(e) => warn!(
+                "{} {}: cannot listen on {addr}: {e}",
+                forward.workspace_id, forward.name
+            ),
+        }
+    }
+
+    // A port taken by another program only loses that port's routes
+    let mut listening = 0;
+    for port in proxy_state.listen_ports() {
         let state = Arc::clone(&proxy_state);
+        let tls = tls.clone();
         let addr = SocketAddr::from(([127, 0, 0, 1], port));
 
-        let listener = TcpListener::bind(addr).await?;
+        let listener = match TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!("cannot listen on {addr}: {e}");
+                if e.kind() == std::io::ErrorKind::PermissionDenied && port < 1024 {
+                    info!(
+                        "  Ports below 1024 need privileges, e.g. sudo setcap cap_net_bind_service=+ep $(which dual)"
+                    );
+                } else if proxy_state.single_port.is_none() {
+                    info!(
+                        "  Set `port` under [proxy] in ~/.dual/workspaces.toml to serve every workspace on one port"
+                    );
+                }
+                continue;
+            }
+        };
         info!("Listening on {addr}");
+        listening += 1;
 
         handles.push(tokio::spawn(async move {
             loop {
-                let (stream, _) = match listener.accept().await {
+                let (stream, peer) = match listener.accept().await {
                     Ok(conn) => conn,
                     Err(e) => {
                         warn!(port, "accept error: {e}");
@@ -124,29 +713,41 @@ pub async fn start(state: &WorkspaceState) -> Result<(), Box<dyn std::error::Err
                 };
 
                 let state = Arc::clone(&state);
-                let io = TokioIo::new(stream);
+                let tls = tls.clone();
 
                 tokio::spawn(async move {
-                    let service = service_fn(move |req| {
-                        let state = Arc::clone(&state);
-                        handle_request(state, port, req)
-                    });
-
-                    if let Err(e) = http1::Builder::new()
-                        .preserve_header_case(true)
-                        .serve_connection(io, service)
-                        .with_upgrades()
-                        .await
-                        && !e.to_string().contains("connection closed")
-                    {
-                        debug!("connection error: {e}");
+                    let mut first = [0u8; 1];
+                    let is_tls =
+                        matches!(stream.peek(&mut first).await, Ok(1) if first[0] == TLS_HANDSHAKE);
+                    let conn = Connection {
+                        listen_port: port,
+                        peer,
+                        tls: false,
+                    };
+                    match tls {
+                        Some(acceptor) if is_tls => match acceptor.accept(stream).await {
+                            Ok(stream) => {
+                                serve_http(stream, state, Connection { tls: true, ..conn }).await
+                            }
+                            Err(e) => debug!("TLS handshake failed: {e}"),
+                        },
+                        _ => serve_http(stream, state, conn).await,
                     }
                 });
             }
         }));
     }
 
-    info!("Proxy running. Press Ctrl+C to stop.");
+    if listening == 0 && !proxy_state.ports().is_empty() {
+        return Err("no proxy port could be bound".into());
+    }
+
+    info!("Proxy running. Press Ctrl+C to stop. Requests: `dual proxy log -f`");
+    if let Some(port) = proxy_state.listen_ports().into_iter().min()
+        && proxy_state.recent.is_some()
+    {
+        info!("Recent requests: http://localhost:{port}{INSPECT_PATH}");
+    }
 
     // Wait for all listeners (runs forever until Ctrl+C)
     for handle in handles {
@@ -156,101 +757,294 @@ pub async fn start(state: &WorkspaceState) -> Result<(), Box<dyn std::error::Err
     Ok(())
 }
 
-/// Handle a single HTTP request by proxying to the correct container.
-async fn handle_request(
+/// Build the TLS acceptor for `workspace_ids`, creating the local CA on first use.
+///
+/// The CA is only replaced when the proxy starts, so a long-running proxy
+/// checks daily and says when a restart is due.
+fn tls_acceptor(workspace_ids: HashSet<String>) -> Result<TlsAcceptor, Box<dyn std::error::Error>> {
+    let dir = config::certs_dir().ok_or("could not determine home directory")?;
+    let (store, created) = tls::CertStore::open(&dir)?;
+    let store = Arc::new(store.with_workspaces(workspace_ids));
+    if created {
+        warn!("Created a new local CA. Trust it to avoid certificate warnings:");
+    } else {
+        info!("HTTPS enabled. Local CA:");
+    }
+    info!("  {}", store.ca_path().display());
+    if created {
+        info!(
+            "  macOS: sudo security add-trusted-cert -d -r trustRoot -k /Library/Keychains/System.keychain <path>"
+        );
+        info!(
+            "  Linux: copy it to /usr/local/share/ca-certificates/dual.crt and run sudo update-ca-certificates"
+        );
+    }
+
+    let renewal = Arc::clone(&store);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CA_RENEWAL_CHECK);
+        loop {
+            interval.tick().await;
+            if renewal.ca_due_for_renewal() {
+                warn!("The local CA expires soon. Restart the proxy to replace it");
+            }
+        }
+    });
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut tls_config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_cert_resolver(store);
+    tls_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(tls_config)))
+}
+
+/// Serve HTTP/1.1 or HTTP/2 on a client connection, plain or TLS.
+async fn serve_http<S>(stream: S, state: Arc<ProxyState>, conn: Connection)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = service_fn(move |req| {
+        let state = Arc::clone(&state);
+        handle_logged(state, conn, req)
+    });
+
+    let mut builder = auto::Builder::new(TokioExecutor::new());
+    builder.http1().preserve_header_case(true);
+    if let Err(e) = builder
+        .serve_connection_with_upgrades(TokioIo::new(stream), service)
+        .await
+        && !e.to_string().contains("connection closed")
+    {
+        debug!("connection error: {e}");
+    }
+}
+
+/// Accept connections forever, piping each one to `target` unchanged.
+async fn forward_tcp(listener: TcpListener, target: String) {
+    loop {
+        let (mut inbound, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("accept error: {e}");
+                continue;
+            }
+        };
+        let target = target.clone();
+        tokio::spawn(async move {
+            match tokio::net::TcpStream::connect(&target).await {
+                Ok(mut outbound) => {
+                    if let Err(e) = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await
+                    {
+                        debug!("tcp forward to {target} ended: {e}");
+                    }
+                }
+                Err(e) => debug!("cannot connect to {target}: {e}"),
+            }
+        });
+    }
+}
+
+/// The host a request is for, lowercased; HTTP/2 clients send it as the authority.
+fn request_host<B>(req: &Request<B>) -> String {
+    // Hostnames are case-insensitive; workspace ids are always lowercase
+    req.headers()
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .or_else(|| req.uri().authority().map(|a| a.as_str()))
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+/// Handle a request, recording it once its response body has been sent.
+async fn handle_logged(
     state: Arc<ProxyState>,
-    port: u16,
+    conn: Connection,
     req: Request<Incoming>,
-) -> Result<Response<Full<Bytes>>, hyper::Error> {
-    // Extract subdomain from Host header
-    let host = req
-        .headers()
-        .get("host")
-        .and_then(|h| h.to_str().ok())
-        .unwrap_or("");
+) -> Result<Response<ProxyBody>, hyper::Error> {
+    let host = request_host(&req);
+    let subdomain = extract_subdomain(&host);
+    // Reading the inspector shouldn't fill it
+    let inspecting = is_loopback_host(&host) && req.uri().path() == INSPECT_PATH;
+    if inspecting || (state.access_log.is_none() && state.recent.is_none()) {
+        return handle_request(state, conn, req).await;
+    }
 
-    let subdomain = extract_subdomain(host);
-    debug!(host, port, "routing request");
+    let started = Instant::now();
+    let time = AccessEntry::now();
+    let method = req.method().to_string();
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.to_string())
+        .unwrap_or_else(|| "/".to_string());
+    let workspace = subdomain
+        .and_then(|s| state.target(conn.listen_port, s))
+        .map(|(workspace_id, _)| workspace_id.to_string());
 
-    let container_ip = match subdomain.and_then(|s| state.resolve(port, s)) {
-        Some(ip) => ip.to_string(),
-        None => {
-            let body = format!(
-                "No route for host: {host}\n\nAvailable routes on port {port}:\n{}",
-                state
-                    .routes
-                    .get(&port)
-                    .map(|m| m
-                        .keys()
-                        .map(|s| format!("  {s}.localhost:{port}"))
-                        .collect::<Vec<_>>()
-                        .join("\n"))
-                    .unwrap_or_default()
-            );
-            return Ok(bad_gateway(body));
-        }
+    let resp = handle_request(Arc::clone(&state), conn, req).await?;
+    let mut entry = AccessEntry {
+        time,
+        workspace,
+        host,
+        method,
+        path,
+        status: resp.status().as_u16(),
+        latency_ms: started.elapsed().as_millis() as u64,
+        bytes: 0,
     };
-
-    // Forward the request to the container
-    let target_uri = format!(
-        "http://{}:{}{}",
-        container_ip,
-        port,
-        req.uri()
-            .path_and_query()
-            .map(|pq| pq.as_str())
-            .unwrap_or("/")
-    );
-
-    // Use a TCP connection to the container
-    let stream = match tokio::net::TcpStream::connect(format!("{container_ip}:{port}")).await {
-        Ok(s) => s,
-        Err(e) => {
-            let body = format!("Cannot connect to {container_ip}:{port}: {e}");
-            return Ok(bad_gateway(body));
+    Ok(resp.map(|inner| {
+        Counted {
+            inner,
+            bytes: 0,
+            on_done: Some(Box::new(move |bytes| {
+                entry.bytes = bytes;
+                state.record(entry);
+            })),
         }
-    };
+        .boxed()
+    }))
+}
 
-    let io = TokioIo::new(stream);
+/// Handle a single HTTP request by proxying to the correct container.
+async fn handle_request(
+    state: Arc<ProxyState>,
+    conn: Connection,
+    mut req: Request<Incoming>,
+) -> Result<Response<ProxyBody>, hyper::Error> {
+    let host = request_host(&req);
 
-    let (mut sender, conn) = match hyper::client::conn::http1::handshake(io).await {
-        Ok(c) => c,
-        Err(e) => {
-            let body = format!("Handshake failed with {container_ip}:{port}: {e}");
-            return Ok(bad_gateway(body));
-        }
-    };
+    let listen_port = conn.listen_port;
+    debug!(host, port = listen_port, "routing request");
 
-    // Drive the connection in the background
-    tokio::spawn(async move {
-        if let Err(e) = conn.await
-            && !e.to_string().contains("connection closed")
+    // Bare localhost lists the workspaces, or recent requests when inspecting.
+    // Any other host (e.g. a DNS-rebinding page resolving to 127.0.0.1) gets neither.
+    let Some(subdomain) = extract_subdomain(&host) else {
+        if !is_loopback_host(&host) {
+            let body = format!("dual proxy does not serve {host}");
+            return Ok(html_page(
+                StatusCode::MISDIRECTED_REQUEST,
+                escape_html(&body),
+            ));
+        }
+        if req.uri().path() == INSPECT_PATH
+            && let Some(ref recent) = state.recent
         {
-            warn!("backend connection error: {e}");
+            return Ok(json_response(recent.to_json()));
         }
-    });
-
-    // Forward request
-    let _ = target_uri; // URI used for logging context
-    match sender.send_request(req).await {
-        Ok(resp) => {
-            // Collect the response body
-            let (parts, body) = resp.into_parts();
-            match body.collect().await {
-                Ok(collected) => Ok(Response::from_parts(parts, Full::new(collected.to_bytes()))),
+        return Ok(html_page(
+            StatusCode::OK,
+            state.status_page("dual workspaces"),
+        ));
+    };
+    let Some((workspace_id, port)) = state.target(listen_port, subdomain) else {
+        let page = state.status_page(&format!("No workspace at {host}"));
+        return Ok(html_page(StatusCode::NOT_FOUND, page));
+    };
+    let mut woken = false;
+    let container_ip = match state.resolve(port, workspace_id) {
+        Some(ip) => ip.to_string(),
+        None => match state.sleeping.get(workspace_id) {
+            Some(sleeping) if sleeping.serves(port) => match state.wake(workspace_id, port).await {
+                Ok(ip) => {
+                    woken = true;
+                    ip
+                }
                 Err(e) => {
-                    let body = format!("Error reading response: {e}");
-                    Ok(bad_gateway(body))
+                    warn!("could not wake {workspace_id}: {e}");
+                    let page = state.status_page(&format!("Could not start {workspace_id}: {e}"));
+                    return Ok(html_page(StatusCode::SERVICE_UNAVAILABLE, page));
                 }
+            },
+            _ => {
+                let page = state.status_page(&format!("No workspace at {host}"));
+                return Ok(html_page(StatusCode::NOT_FOUND, page));
             }
+        },
+    };
+    let http2 = state.h2c.contains(&(workspace_id.to_string(), port));
+
+    // Point the request at the container, keeping the Host the client used
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+    let target: hyper::Uri = match format!("http://{container_ip}:{port}{path}").parse() {
+        Ok(uri) => uri,
+        Err(e) => return Ok(bad_gateway(format!("Invalid request path {path}: {e}"))),
+    };
+    *req.uri_mut() = target;
+    if let Ok(value) = HeaderValue::from_str(&host) {
+        req.headers_mut().entry(header::HOST).or_insert(value);
+    }
+    let proto = if conn.tls { "https" } else { "http" };
+    set_forwarded_headers(req.headers_mut(), conn.peer.ip(), &host, proto);
+
+    // WebSocket and other upgrades only exist in HTTP/1.1
+    let client_upgrade = (req.version() == Version::HTTP_11
+        && req.headers().contains_key(header::UPGRADE))
+    .then(|| hyper::upgrade::on(&mut req));
+
+    let client = if http2 {
+        *req.version_mut() = Version::HTTP_2;
+        &state.upstreams.h2c
+    } else {
+        *req.version_mut() = Version::HTTP_11;
+        &state.upstreams.http1
+    };
+
+    match client.request(req).await {
+        Ok(mut resp) => {
+            if resp.status() == StatusCode::SWITCHING_PROTOCOLS
+                && let Some(client_upgrade) = client_upgrade
+            {
+                let upstream_upgrade = hyper::upgrade::on(&mut resp);
+                tokio::spawn(async move {
+                    match tokio::try_join!(client_upgrade, upstream_upgrade) {
+                        Ok((client, upstream)) => {
+                            let _ = tokio::io::copy_bidirectional(
+                                &mut TokioIo::new(client),
+                                &mut TokioIo::new(upstream),
+                            )
+                            .await;
+                        }
+                        Err(e) => debug!("upgrade failed: {e}"),
+                    }
+                });
+            }
+            Ok(resp.map(BodyExt::boxed))
         }
         Err(e) => {
-            let body = format!("Proxy error: {e}");
+            if woken && e.is_connect() {
+                state.forget(workspace_id);
+            }
+            let body = format!("Cannot reach {container_ip}:{port}: {e}");
             Ok(bad_gateway(body))
         }
     }
 }
 
+/// Add `X-Forwarded-For`, `-Host` and `-Proto`, so apps see the client's origin.
+///
+/// The client's address is appended to any `X-Forwarded-For` it sent.
+fn set_forwarded_headers(headers: &mut HeaderMap, client: IpAddr, host: &str, proto: &str) {
+    let forwarded_for = match headers.get("x-forwarded-for").and_then(|v| v.to_str().ok()) {
+        Some(prior) => format!("{prior}, {client}"),
+        None => client.to_string(),
+    };
+    for (name, value) in [
+        ("x-forwarded-for", forwarded_for.as_str()),
+        ("x-forwarded-host", host),
+        ("x-forwarded-proto", proto),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            headers.insert(name, value);
+        }
+    }
+}
+
 /// Extract subdomain from a Host header value.
 /// "lightfast-main.localhost:3000" → Some("lightfast-main")
 /// "localhost:3000" → None
@@ -269,33 +1063,139 @@ fn extract_subdomain(host: &str) -> Option<&str> {
     None
 }
 
+/// Whether a Host header value is exactly `localhost`, `127.0.0.1` or `[::1]`,
+/// with or without a port.
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.find(']') {
+        Some(end) if host.starts_with('[') => &host[..=end],
+        _ => host.split(':').next().unwrap_or(host),
+    };
+    matches!(name, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+/// A workspace's proxied and forwarded ports, for `dual urls` and `dual open`.
+pub struct WorkspaceUrls {
+    pub workspace_id: String,
+    pub running: bool,
+    pub services: Vec<ServiceUrl>,
+    /// (name, container port, host port) of each allocated `tcp_ports` entry
+    pub tcp: Vec<(String, u16, u16)>,
+    /// Health by container port, once checked; lines otherwise show whether
+    /// the container runs
+    pub health: HashMap<u16, Health>,
+}
+
+/// The URL of one `ports` entry.
+pub struct ServiceUrl {
+    pub name: Option<String>,
+    /// Port inside the container
+    pub port: u16,
+    pub url: String,
+    /// Whether `dual open` opens it without `--service`
+    pub open: bool,
+}
+
+impl WorkspaceUrls {
+    /// Display lines, e.g. "  ● app  http://app.lightfast-main.localhost:3001".
+    pub fn lines(&self) -> Vec<String> {
+        let status = |port: u16| match self.health.get(&port) {
+            Some(health) => health.symbol(),
+            None if self.running => Health::Healthy.symbol(),
+            None => Health::Down.symbol(),
+        };
+        let width = self
+            .services
+            .iter()
+            .filter_map(|s| s.name.as_ref().map(|n| n.len()))
+            .max();
+
+        let mut lines = Vec::new();
+        for service in &self.services {
+            match width {
+                Some(width) => {
+                    let name = service.name.as_deref().unwrap_or("");
+                    lines.push(format!(
+                        "  {} {name:<width$}  {}",
+                        status(service.port),
+                        service.url
+                    ));
+                }
+                None => lines.push(format!("  {} {}", status(service.port), service.url)),
+            }
+        }
+        for (name, port, host_port) in &self.tcp {
+            lines.push(format!(
+                "  {} {} {name} 127.0.0.1:{host_port}",
+                status(*port),
+                self.workspace_id
+            ));
+        }
+        lines
+    }
+}
+
 /// Get all configured URLs for workspaces.
-pub fn workspace_urls(state: &WorkspaceState) -> Vec<(String, Vec<String>)> {
+pub fn workspace_urls(state: &WorkspaceState) -> Vec<WorkspaceUrls> {
     let mut result = Vec::new();
 
     for entry in state.all_workspaces() {
         let container_name = config::container_name(&entry.repo, &entry.branch);
         let workspace_id = config::workspace_id(&entry.repo, &entry.branch);
-        let is_running = container::status(&container_name) == container::ContainerStatus::Running;
+        let running = container::status(&container_name) == container::ContainerStatus::Running;
 
         // Load hints to get ports
         let ws_dir = state.workspace_dir(entry);
         let hints = config::load_hints(&ws_dir).unwrap_or_default();
 
-        let mut urls = Vec::new();
-        for &port in &hints.ports {
-            let status = if is_running { "\u{25cf}" } else { "\u{25cb}" };
-            urls.push(format!("  {status} {workspace_id}.localhost:{port}"));
-        }
+        let services: Vec<ServiceUrl> = hints
+            .ports
+            .iter()
+            .map(|port| ServiceUrl {
+                name: port.name().map(str::to_string),
+                port: port.port(),
+                url: url(&state.proxy, &workspace_id, port),
+                open: port.open(),
+            })
+            .collect();
+        let tcp: Vec<(String, u16, u16)> = hints
+            .tcp_ports
+            .iter()
+            .filter_map(|tcp_port| {
+                state
+                    .tcp_port(&workspace_id, tcp_port.port())
+                    .map(|host_port| (tcp_port.name(), tcp_port.port(), host_port))
+            })
+            .collect();
 
-        if !urls.is_empty() {
-            result.push((workspace_id, urls));
+        if !services.is_empty() || !tcp.is_empty() {
+            result.push(WorkspaceUrls {
+                workspace_id,
+                running,
+                services,
+                tcp,
+                health: HashMap::new(),
+            });
         }
     }
 
     result
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum WakeError {
+    #[error(transparent)]
+    Container(#[from] ContainerError),
+
+    #[error("{0} has no IP address")]
+    NoAddress(String),
+
+    #[error("port {port} did not accept connections within {secs}s")]
+    Timeout { port: u16, secs: u64 },
+
+    #[error("starting {0} was interrupted")]
+    Interrupted(String),
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -322,6 +1222,28 @@ mod tests {
         assert_eq!(extract_subdomain("localhost"), None);
     }
 
+    #[test]
+    fn loopback_hosts() {
+        for host in [
+            "localhost",
+            "localhost:3000",
+            "127.0.0.1:3000",
+            "[::1]",
+            "[::1]:3000",
+        ] {
+            assert!(is_loopback_host(host), "{host}");
+        }
+        for host in [
+            "evil.example:3000",
+            "localhost.evil.example",
+            "app-main.localhost:3000",
+            "127.0.0.2",
+            "",
+        ] {
+            assert!(!is_loopback_host(host), "{host}");
+        }
+    }
+
     #[test]
     fn extract_subdomain_nested() {
         assert_eq!(
@@ -337,7 +1259,10 @@ mod tests {
         route_map.insert("lightfast-main".to_string(), "172.17.0.2".to_string());
         routes.insert(3000, route_map);
 
-        let state = ProxyState { routes };
+        let state = ProxyState {
+            routes,
+            ..ProxyState::default()
+        };
         assert_eq!(state.resolve(3000, "lightfast-main"), Some("172.17.0.2"));
         assert_eq!(state.resolve(3000, "unknown"), None);
         assert_eq!(state.resolve(4000, "lightfast-main"), None);
@@ -349,9 +1274,458 @@ mod tests {
         routes.insert(3000, RouteMap::new());
         routes.insert(3001, RouteMap::new());
 
-        let state = ProxyState { routes };
+        let state = ProxyState {
+            routes,
+            ..ProxyState::default()
+        };
         let mut ports = state.ports();
         ports.sort();
         assert_eq!(ports, vec![3000, 3001]);
     }
+
+    #[test]
+    fn single_port_routes_by_port_subdomain() {
+        let mut routes = HashMap::new();
+        for port in [3000, 3001] {
+            let mut route_map = RouteMap::new();
+            route_map.insert("lightfast-main".to_string(), "172.18.0.2".to_string());
+            routes.insert(port, route_map);
+        }
+        let mut state = ProxyState {
+            routes,
+            single_port: Some(8000),
+            ..ProxyState::default()
+        };
+        assert_eq!(state.listen_ports(), vec![8000]);
+        assert_eq!(
+            state.route(8000, "3001.lightfast-main"),
+            Some(("172.18.0.2", 3001))
+        );
+        // A bare workspace goes to its lowest port
+        assert_eq!(
+            state.route(8000, "lightfast-main"),
+            Some(("172.18.0.2", 3000))
+        );
+        assert_eq!(state.route(8000, "4000.lightfast-main"), None);
+        assert_eq!(state.route(8000, "web.lightfast-main"), None);
+
+        // Multi-port mode routes by the listening port
+        state.single_port = None;
+        assert_eq!(
+            state.route(3001, "lightfast-main"),
+            Some(("172.18.0.2", 3001))
+        );
+        assert_eq!(state.route(3001, "3001.lightfast-main"), None);
+    }
+
+    #[test]
+    fn urls_follow_proxy_mode() {
+        let port = PortEntry::Port(3000);
+        let mut proxy = ProxyConfig::default();
+        assert_eq!(
+            url(&proxy, "app-main", &port),
+            "http://app-main.localhost:3000"
+        );
+
+        proxy.port = Some(8000);
+        assert_eq!(
+            url(&proxy, "app-main", &port),
+            "http://3000.app-main.localhost:8000"
+        );
+        proxy.port = Some(80);
+        assert_eq!(
+            url(&proxy, "app-main", &port),
+            "http://3000.app-main.localhost"
+        );
+
+        proxy.https = true;
+        assert_eq!(
+            url(&proxy, "app-main", &port),
+            "https://3000.app-main.localhost:80"
+        );
+        proxy.port = Some(443);
+        assert_eq!(
+            url(&proxy, "app-main", &port),
+            "https://3000.app-main.localhost"
+        );
+    }
+
+    #[test]
+    fn named_ports_route_and_show_by_name() {
+        let app = PortEntry::Service {
+            port: 3001,
+            name: Some("app".to_string()),
+            path: Some("/dashboard".to_string()),
+            open: true,
+            http2: false,
+            health: None,
+        };
+        let mut proxy = ProxyConfig::default();
+        assert_eq!(
+            url(&proxy, "lightfast-main", &app),
+            "http://app.lightfast-main.localhost:3001/dashboard"
+        );
+        proxy.port = Some(8000);
+        assert_eq!(
+            url(&proxy, "lightfast-main", &app),
+            "http://app.lightfast-main.localhost:8000/dashboard"
+        );
+
+        let mut routes = HashMap::new();
+        let mut route_map = RouteMap::new();
+        route_map.insert("lightfast-main".to_string(), "172.18.0.2".to_string());
+        routes.insert(3001, route_map);
+        let mut services = HashMap::new();
+        services.insert(
+            "lightfast-main".to_string(),
+            HashMap::from([("app".to_string(), 3001)]),
+        );
+        let mut state = ProxyState {
+            routes,
+            services,
+            ..ProxyState::default()
+        };
+        assert_eq!(
+            state.route(3001, "app.lightfast-main"),
+            Some(("172.18.0.2", 3001))
+        );
+        assert_eq!(state.route(3001, "api.lightfast-main"), None);
+        state.single_port = Some(8000);
+        assert_eq!(
+            state.route(8000, "app.lightfast-main"),
+            Some(("172.18.0.2", 3001))
+        );
+    }
+
+    #[test]
+    fn url_lines_align_names() {
+        let urls = WorkspaceUrls {
+            workspace_id: "lightfast-main".to_string(),
+            running: true,
+            services: vec![
+                ServiceUrl {
+                    name: Some("www".to_string()),
+                    port: 3000,
+                    url: "http://www.lightfast-main.localhost:3000".to_string(),
+                    open: true,
+                },
+                ServiceUrl {
+                    name: Some("docs".to_string()),
+                    port: 3002,
+                    url: "http://docs.lightfast-main.localhost:3002".to_string(),
+                    open: false,
+                },
+            ],
+            tcp: vec![("postgres".to_string(), 5432, 45432)],
+            health: HashMap::new(),
+        };
+        assert_eq!(
+            urls.lines(),
+            vec![
+                "  \u{25cf} www   http://www.lightfast-main.localhost:3000",
+                "  \u{25cf} docs  http://docs.lightfast-main.localhost:3002",
+                "  \u{25cf} lightfast-main postgres 127.0.0.1:45432",
+            ]
+        );
+
+        // Checked health replaces the container's running state
+        let mut urls = urls;
+        urls.health = HashMap::from([
+            (3000, Health::Healthy),
+            (3002, Health::Starting),
+            (5432, Health::Down),
+        ]);
+        assert_eq!(
+            urls.lines(),
+            vec![
+                "  \u{25cf} www   http://www.lightfast-main.localhost:3000",
+                "  \u{25d0} docs  http://docs.lightfast-main.localhost:3002",
+                "  \u{25cb} lightfast-main postgres 127.0.0.1:45432",
+            ]
+        );
+    }
+
+    #[test]
+    fn forwarded_headers_append_client() {
+        let mut headers = HeaderMap::new();
+        let client: IpAddr = "127.0.0.1".parse().unwrap();
+        set_forwarded_headers(&mut headers, client, "app-main.localhost:3000", "https");
+        assert_eq!(headers["x-forwarded-for"], "127.0.0.1");
+        assert_eq!(headers["x-forwarded-host"], "app-main.localhost:3000");
+        assert_eq!(headers["x-forwarded-proto"], "https");
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static("10.0.0.1"));
+        set_forwarded_headers(&mut headers, client, "app-main.localhost:3000", "http");
+        assert_eq!(headers["x-forwarded-for"], "10.0.0.1, 127.0.0.1");
+        assert_eq!(headers["x-forwarded-proto"], "http");
+    }
+
+    /// A container stand-in that echoes the headers apps rely on, counting connections.
+    async fn echo_upstream() -> (u16, Arc<std::sync::atomic::AtomicUsize>) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_port = upstream.local_addr().unwrap().port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = Arc::clone(&connections);
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = upstream.accept().await.unwrap();
+                accepted.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let service = service_fn(|req: Request<Incoming>| async move {
+                        let header = |name| {
+                            req.headers()
+                                .get(name)
+                                .and_then(|v| v.to_str().ok())
+                                .unwrap_or("")
+                                .to_string()
+                        };
+                        let body = format!(
+                            "{} {} {}",
+                            header("host"),
+                            header("x-forwarded-for"),
+                            header("x-forwarded-proto")
+                        );
+                        Ok::<_, hyper::Error>(Response::new(Full::new(Bytes::from(body))))
+                    });
+                    let _ = hyper::server::conn::http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await;
+                });
+            }
+        });
+        (upstream_port, connections)
+    }
+
+    /// Serve `app-main` on `upstream_port` in single-port mode, returning the proxy's port.
+    async fn start_proxy(upstream_port: u16, recent: Option<Recent>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listen_port = listener.local_addr().unwrap().port();
+        let mut route_map = RouteMap::new();
+        route_map.insert("app-main".to_string(), "127.0.0.1".to_string());
+        let state = Arc::new(ProxyState {
+            routes: HashMap::from([(upstream_port, route_map)]),
+            single_port: Some(listen_port),
+            recent,
+            ..ProxyState::default()
+        });
+        tokio::spawn(async move {
+            loop {
+                let (stream, peer) = listener.accept().await.unwrap();
+                let conn = Connection {
+                    listen_port,
+                    peer,
+                    tls: false,
+                };
+                tokio::spawn(serve_http(stream, Arc::clone(&state), conn));
+            }
+        });
+        listen_port
+    }
+
+    /// GET `path` through the proxy with the given Host, returning the body.
+    async fn get(
+        client: &Client<HttpConnector, Full<Bytes>>,
+        listen_port: u16,
+        host: &str,
+        path: &str,
+    ) -> Bytes {
+        let req = Request::builder()
+            .uri(format!("http://127.0.0.1:{listen_port}{path}"))
+            .header(header::HOST, host)
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        resp.into_body().collect().await.unwrap().to_bytes()
+    }
+
+    #[tokio::test]
+    async fn upstream_connections_are_pooled() {
+        let (upstream_port, connections) = echo_upstream().await;
+        let listen_port = start_proxy(upstream_port, None).await;
+
+        let host = format!("{upstream_port}.app-main.localhost:{listen_port}");
+        let http1 = Client::builder(TokioExecutor::new()).build_http::<Full<Bytes>>();
+        let h2 = Client::builder(TokioExecutor::new())
+            .http2_only(true)
+            .build_http::<Full<Bytes>>();
+        for client in [&http1, &http1, &h2] {
+            let body = get(client, listen_port, &host, "/").await;
+            assert_eq!(body, format!("{host} 127.0.0.1 http"));
+        }
+        // HTTP/1 and HTTP/2 clients alike reuse one upstream connection
+        assert_eq!(connections.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn inspector_lists_recent_requests() {
+        let (upstream_port, _) = echo_upstream().await;
+        let listen_port = start_proxy(upstream_port, Some(Recent::new(10))).await;
+
+        let host = format!("{upstream_port}.app-main.localhost:{listen_port}");
+        let client = Client::builder(TokioExecutor::new()).build_http::<Full<Bytes>>();
+        let body = get(&client, listen_port, &host, "/callback?code=1").await;
+
+        // The entry is recorded once the response body has been sent
+        let inspector = format!("localhost:{listen_port}");
+        let mut entries = Vec::new();
+        for _ in 0..50 {
+            let json = get(&client, listen_port, &inspector, INSPECT_PATH).await;
+            entries = serde_json::from_slice::<Vec<AccessEntry>>(&json).unwrap();
+            if !entries.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].workspace.as_deref(), Some("app-main"));
+        assert_eq!(entries[0].method, "GET");
+        assert_eq!(entries[0].path, "/callback?code=1");
+        assert_eq!(entries[0].status, 200);
+        assert_eq!(entries[0].bytes, body.len() as u64);
+
+        // Other hosts pointed at the proxy can't read it
+        let req = Request::builder()
+            .uri(format!("http://127.0.0.1:{listen_port}{INSPECT_PATH}"))
+            .header(header::HOST, format!("evil.example:{listen_port}"))
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::MISDIRECTED_REQUEST);
+    }
+
+    #[test]
+    fn sleeping_workspaces_keep_their_ports() {
+        let mut routes = HashMap::new();
+        let mut route_map = RouteMap::new();
+        route_map.insert("app-main".to_string(), "172.18.0.2".to_string());
+        routes.insert(3000, route_map);
+        let sleeping = Sleeping {
+            entry: WorkspaceEntry {
+                repo: "app".to_string(),
+                url: "/tmp/app".to_string(),
+                branch: "feat".to_string(),
+                path: None,
+            },
+            workspace_dir: PathBuf::from("/tmp/app-feat"),
+            hints: RepoHints {
+                ports: vec![3000.into(), 4000.into()],
+                ..RepoHints::default()
+            },
+        };
+        let mut state = ProxyState {
+            routes,
+            sleeping: HashMap::from([("app-feat".to_string(), sleeping)]),
+            ..ProxyState::default()
+        };
+
+        let mut ports = state.ports();
+        ports.sort();
+        assert_eq!(ports, vec![3000, 4000]);
+        // Known but not routed until woken
+        assert_eq!(state.target(4000, "app-feat"), Some(("app-feat", 4000)));
+        assert_eq!(state.route(4000, "app-feat"), None);
+        assert!(state.sleeping["app-feat"].serves(4000));
+        assert!(!state.sleeping["app-feat"].serves(5000));
+
+        state.single_port = Some(8000);
+        assert_eq!(state.target(8000, "app-feat"), Some(("app-feat", 3000)));
+    }
+
+    #[tokio::test]
+    async fn woken_workspaces_run_until_forgotten() {
+        let state = ProxyState::default();
+        state
+            .woken
+            .lock()
+            .unwrap()
+            .insert("app-other".to_string(), Arc::new(OnceCell::new()));
+        // A wake still in progress doesn't count as running
+        assert!(!state.is_running("app-other"));
+
+        let cell = Arc::new(OnceCell::new());
+        cell.set("172.18.0.3".to_string()).unwrap();
+        state
+            .woken
+            .lock()
+            .unwrap()
+            .insert("app-feat".to_string(), cell);
+        assert!(state.is_running("app-feat"));
+
+        state.forget("app-feat");
+        assert!(!state.is_running("app-feat"));
+    }
+
+    #[test]
+    fn status_page_lists_workspaces() {
+        let mut routes = HashMap::new();
+        let mut route_map = RouteMap::new();
+        route_map.insert("app-main".to_string(), "172.18.0.2".to_string());
+        routes.insert(3000, route_map);
+        let workspace = |id: &str, url: &str| WorkspaceUrls {
+            workspace_id: id.to_string(),
+            running: false,
+            services: vec![ServiceUrl {
+                name: Some("web".to_string()),
+                port: 3000,
+                url: url.to_string(),
+                open: true,
+            }],
+            tcp: Vec::new(),
+            health: HashMap::new(),
+        };
+        let state = ProxyState {
+            routes,
+            workspaces: vec![
+                workspace("app-main", "http://web.app-main.localhost:3000"),
+                workspace("app-feat", "http://web.app-feat.localhost:3000"),
+            ],
+            ..ProxyState::default()
+        };
+
+        let html = state.status_page("No workspace at <x>.localhost");
+        assert!(html.contains("<h1>No workspace at &lt;x&gt;.localhost</h1>"));
+        assert!(html.contains("<h2>app-main <small>(running)</small></h2>"));
+        assert!(html.contains("<h2 class=\"stopped\">app-feat <small>(stopped)</small></h2>"));
+        assert!(html.contains(
+            "<li>web <a href=\"http://web.app-main.localhost:3000\">http://web.app-main.localhost:3000</a></li>"
+        ));
+    }
+
+    #[tokio::test]
+    async fn wait_for_port_sees_listener() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        assert!(wait_for_port(&addr, Duration::from_secs(1)).await);
+
+        drop(listener);
+        assert!(!wait_for_port(&addr, Duration::ZERO).await);
+    }
+
+    #[tokio::test]
+    async fn tcp_forward_pipes_bytes() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // A one-shot echo server standing in for the container
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = upstream.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut conn, _) = upstream.accept().await.unwrap();
+            let mut buf = [0u8; 4];
+            conn.read_exact(&mut buf).await.unwrap();
+            conn.write_all(&buf).await.unwrap();
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(forward_tcp(listener, target));
+
+        let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"ping").await.unwrap();
+        let mut reply = [0u8; 4];
+        client.read_exact(&mut reply).await.unwrap();
+        assert_eq!(&reply, b"ping");
+    }
 }
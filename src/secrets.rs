@@ -0,0 +1,311 @@
+use age::secrecy::{ExposeSecret, SecretString};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+
+/// Environment variable holding a passphrase that protects the key file.
+pub const PASSPHRASE_ENV: &str = "DUAL_SECRETS_PASSPHRASE";
+
+/// Where the store key comes from.
+///
+/// Both providers hold an age X25519 identity; they differ in how it is kept
+/// on disk. The key is generated on first use, unless the other provider
+/// already has one (see `load`).
+pub enum KeyProvider {
+    /// Identity stored in plain text in a 0600 file (~/.dual/secrets.key).
+    File(PathBuf),
+
+    /// Identity stored age-encrypted with a passphrase (~/.dual/secrets.key.age).
+    Passphrase {
+        path: PathBuf,
+        passphrase: SecretString,
+    },
+}
+
+impl KeyProvider {
+    /// Passphrase provider if `DUAL_SECRETS_PASSPHRASE` is set, else the key file.
+    pub fn from_env() -> Result<Self, SecretsError> {
+        let path = config::secrets_key_path().ok_or(SecretsError::NoHomeDir)?;
+        match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) if !passphrase.is_empty() => Ok(KeyProvider::Passphrase {
+                path: path.with_extension("key.age"),
+                passphrase: SecretString::from(passphrase),
+            }),
+            _ => Ok(KeyProvider::File(path)),
+        }
+    }
+
+    /// Where this provider keeps the key.
+    fn path(&self) -> &PathBuf {
+        match self {
+            KeyProvider::File(path) | KeyProvider::Passphrase { path, .. } => path,
+        }
+    }
+
+    /// Where the other provider would keep its key.
+    fn other_path(&self) -> PathBuf {
+        match self {
+            KeyProvider::File(path) => path.with_extension("key.age"),
+            KeyProvider::Passphrase { path, .. } => path.with_extension(""),
+        }
+    }
+
+    /// Load the key, generating and saving a new one if none exists yet.
+    ///
+    /// Fails instead if only the other provider's key exists, as a new key
+    /// couldn't decrypt anything already in the store.
+    pub fn load(&self) -> Result<SecretKey, SecretsError> {
+        let path = self.path();
+
+        if !path.exists() {
+            let other = self.other_path();
+            if other.exists() {
+                return Err(SecretsError::OtherProvider(other));
+            }
+
+            let identity = age::x25519::Identity::generate();
+            let encoded = identity.to_string();
+            let contents = match self {
+                KeyProvider::File(_) => encoded.expose_secret().as_bytes().to_vec(),
+                KeyProvider::Passphrase { passphrase, .. } => age::encrypt(
+                    &age::scrypt::Recipient::new(passphrase.clone()),
+                    encoded.expose_secret().as_bytes(),
+                )
+                .map_err(|e| SecretsError::Crypto(e.to_string()))?,
+            };
+            write_private(path, &contents)?;
+            return Ok(SecretKey(identity));
+        }
+
+        let contents =
+            std::fs::read(path).map_err(|e| SecretsError::Filesystem(path.clone(), e))?;
+        let encoded = match self {
+            KeyProvider::File(_) => contents,
+            KeyProvider::Passphrase { passphrase, .. } => {
+                age::decrypt(&age::scrypt::Identity::new(passphrase.clone()), &contents)
+                    .map_err(|e| SecretsError::Crypto(format!("could not unlock key: {e}")))?
+            }
+        };
+
+        String::from_utf8_lossy(&encoded)
+            .trim()
+            .parse::<age::x25519::Identity>()
+            .map(SecretKey)
+            .map_err(|e| SecretsError::InvalidKey(path.clone(), e.to_string()))
+    }
+}
+
+/// The key that encrypts the shared store and secret variables.
+pub struct SecretKey(age::x25519::Identity);
+
+impl SecretKey {
+    /// Encrypt data to this key.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, SecretsError> {
+        age::encrypt(&self.0.to_public(), plaintext)
+            .map_err(|e| SecretsError::Crypto(e.to_string()))
+    }
+
+    /// Decrypt data encrypted to this key.
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, SecretsError> {
+        age::decrypt(&self.0, ciphertext).map_err(|e| SecretsError::Crypto(e.to_string()))
+    }
+}
+
+/// Path of a repo's secret variables: ~/.dual/secrets/{repo}.age
+pub fn vars_path(repo: &str) -> Option<PathBuf> {
+    config::secrets_dir().map(|dir| dir.join(format!("{repo}.age")))
+}
+
+/// Load secret variables. A missing file means no variables.
+pub fn load_vars(path: &Path, key: &SecretKey) -> Result<BTreeMap<String, String>, SecretsError> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let ciphertext =
+        std::fs::read(path).map_err(|e| SecretsError::Filesystem(path.to_path_buf(), e))?;
+    let plaintext = key.decrypt(&ciphertext)?;
+    let contents = String::from_utf8_lossy(&plaintext);
+    toml::from_str(&contents).map_err(|e| SecretsError::Parse(path.to_path_buf(), e))
+}
+
+/// Encrypt and save secret variables (0600, written atomically).
+pub fn save_vars(
+    path: &Path,
+    key: &SecretKey,
+    vars: &BTreeMap<String, String>,
+) -> Result<(), SecretsError> {
+    let contents = toml::to_string(vars).map_err(SecretsError::Serialize)?;
+    let ciphertext = key.encrypt(contents.as_bytes())?;
+
+    let tmp = path.with_extension("age.tmp");
+    write_private(&tmp, &ciphertext)?;
+    std::fs::rename(&tmp, path).map_err(|e| SecretsError::Filesystem(path.to_path_buf(), e))
+}
+
+/// Whether a variable name suggests a secret value (for masking in output).
+pub fn looks_secret(name: &str) -> bool {
+    const MARKERS: &[&str] = &[
+        "SECRET",
+        "TOKEN",
+        "PASSWORD",
+        "PASSWD",
+        "KEY",
+        "CREDENTIAL",
+        "PRIVATE",
+        "AUTH",
+    ];
+    let upper = name.to_ascii_uppercase();
+    MARKERS.iter().any(|m| upper.contains(m))
+}
+
+/// Write a file readable only by the owner, creating parent directories.
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), SecretsError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| SecretsError::Filesystem(parent.to_path_buf(), e))?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    use std::io::Write;
+    options
+        .open(path)
+        .and_then(|mut f| f.write_all(contents))
+        .map_err(|e| SecretsError::Filesystem(path.to_path_buf(), e))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SecretsError {
+    #[error("Could not determine home directory")]
+    NoHomeDir,
+
+    #[error("Filesystem error at {path}: {err}", path = .0.display(), err = .1)]
+    Filesystem(PathBuf, std::io::Error),
+
+    #[error("Invalid key in {path}: {err}", path = .0.display(), err = .1)]
+    InvalidKey(PathBuf, String),
+
+    #[error(
+        "Secrets key is in {path}; set or unset {PASSPHRASE_ENV} to match how it was created",
+        path = .0.display()
+    )]
+    OtherProvider(PathBuf),
+
+    #[error("Encryption error: {0}")]
+    Crypto(String),
+
+    #[error("Failed to parse secrets in {path}: {err}", path = .0.display(), err = .1)]
+    Parse(PathBuf, toml::de::Error),
+
+    #[error("Failed to serialize secrets: {0}")]
+    Serialize(toml::ser::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_dir(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dual-test-secrets-{test_name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn file_key_is_created_once_and_reloaded() {
+        let dir = temp_dir("file-key");
+        let provider = KeyProvider::File(dir.join("secrets.key"));
+
+        let key = provider.load().unwrap();
+        let ciphertext = key.encrypt(b"hello").unwrap();
+        assert_ne!(ciphertext, b"hello");
+
+        // A second load reads the same key back
+        let reloaded = provider.load().unwrap();
+        assert_eq!(reloaded.decrypt(&ciphertext).unwrap(), b"hello");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.join("secrets.key"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn key_is_not_forked_across_providers() {
+        let dir = temp_dir("fork");
+        let file = KeyProvider::File(dir.join("secrets.key"));
+        let passphrase = KeyProvider::Passphrase {
+            path: dir.join("secrets.key.age"),
+            passphrase: SecretString::from("hunter2".to_string()),
+        };
+
+        file.load().unwrap();
+        assert!(matches!(
+            passphrase.load(),
+            Err(SecretsError::OtherProvider(p)) if p == dir.join("secrets.key")
+        ));
+        assert!(!dir.join("secrets.key.age").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn other_key_cannot_decrypt() {
+        let dir = temp_dir("other-key");
+        let a = KeyProvider::File(dir.join("a.key")).load().unwrap();
+        let b = KeyProvider::File(dir.join("b.key")).load().unwrap();
+
+        let ciphertext = a.encrypt(b"secret").unwrap();
+        assert!(matches!(
+            b.decrypt(&ciphertext),
+            Err(SecretsError::Crypto(_))
+        ));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn vars_roundtrip_encrypted() {
+        let dir = temp_dir("vars");
+        let key = KeyProvider::File(dir.join("secrets.key")).load().unwrap();
+        let path = dir.join("app.age");
+
+        assert!(load_vars(&path, &key).unwrap().is_empty());
+
+        let mut vars = BTreeMap::new();
+        vars.insert("API_TOKEN".to_string(), "s3cr3t".to_string());
+        save_vars(&path, &key, &vars).unwrap();
+
+        // Nothing readable at rest
+        let raw = fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("s3cr3t"));
+        assert_eq!(load_vars(&path, &key).unwrap(), vars);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn secret_looking_names() {
+        assert!(looks_secret("API_TOKEN"));
+        assert!(looks_secret("stripe_secret_key"));
+        assert!(looks_secret("DATABASE_PASSWORD"));
+        assert!(!looks_secret("NODE_ENV"));
+        assert!(!looks_secret("PORT"));
+    }
+}
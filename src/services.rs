@@ -0,0 +1,280 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+use tracing::{info, warn};
+
+use crate::compose;
+use crate::config::{self, RepoHints, ServiceConfig};
+use crate::container::{self, ContainerError, ContainerStatus, ManagedContainer};
+use crate::state::WorkspaceEntry;
+
+/// Name of a service's sidecar container: `{container}-{service}`.
+pub fn sidecar_name(container: &str, service: &str) -> String {
+    format!("{container}-{service}")
+}
+
+/// The services a workspace runs: those imported from `compose_file`, with
+/// `[services]` entries replacing imported ones of the same name.
+///
+/// Also returns what the import skipped or why it failed, for the caller to report.
+pub fn resolve(
+    hints: &RepoHints,
+    workspace_dir: &Path,
+) -> (BTreeMap<String, ServiceConfig>, Vec<String>) {
+    let mut services = BTreeMap::new();
+    let mut notes = Vec::new();
+
+    if let Some(ref file) = hints.compose_file {
+        match compose::import(&workspace_dir.join(file)) {
+            Ok(import) => {
+                services = import.services;
+                notes = import.skipped;
+            }
+            Err(e) => notes.push(e.to_string()),
+        }
+    }
+    for (name, service) in &hints.services {
+        services.insert(name.clone(), service.clone());
+    }
+
+    (services, notes)
+}
+
+/// Start a workspace's sidecar services, creating missing ones. With `restart`,
+/// running ones are restarted too: a sidecar loses its network when the
+/// workspace container stops.
+pub fn start_all(entry: &WorkspaceEntry, hints: &RepoHints, workspace_dir: &Path, restart: bool) {
+    let (services, notes) = resolve(hints, workspace_dir);
+    for note in &notes {
+        warn!("compose: {note}");
+    }
+
+    let container_name = config::container_name(&entry.repo, &entry.branch);
+    let labels = container::workspace_labels(&entry.repo, &entry.branch);
+    for (service, svc) in &services {
+        let sidecar = sidecar_name(&container_name, service);
+        let create_sidecar = || {
+            info!("Starting service {service}...");
+            create(&container_name, service, svc, &labels, &[])
+        };
+        let result = match container::status(&sidecar) {
+            ContainerStatus::Missing => create_sidecar(),
+            ContainerStatus::Running if !restart => Ok(()),
+            status => {
+                if status == ContainerStatus::Running {
+                    let _ = container::stop(&sidecar);
+                }
+                // A sidecar of a since-replaced container can't rejoin its
+                // network; recreate it (with the same volumes) instead
+                container::start(&sidecar).or_else(|_| create_sidecar())
+            }
+        };
+        if let Err(e) = result {
+            warn!("service {service} failed to start: {e}");
+        }
+    }
+}
+
+/// Sidecar containers of a workspace, found by their labels.
+pub fn sidecars(workspace_id: &str) -> Vec<ManagedContainer> {
+    container::list_all()
+        .into_iter()
+        .filter(|c| c.workspace_id == workspace_id && c.service.is_some())
+        .collect()
+}
+
+/// Create a service's sidecar for `container` and start it.
+///
+/// Its volumes are the sidecar's named volumes, except those in `reuse`
+/// (volume, path) pairs, e.g. taken from a sidecar removed by a rename, which
+/// are mounted at the same paths so a database keeps its data.
+pub fn create(
+    container_name: &str,
+    service: &str,
+    config: &ServiceConfig,
+    labels: &[(String, String)],
+    reuse: &[(String, String)],
+) -> Result<(), ContainerError> {
+    let name = sidecar_name(container_name, service);
+
+    let mut volumes = Vec::new();
+    for path in &config.volumes {
+        let volume = match reuse.iter().find(|(_, dest)| dest == path) {
+            Some((volume, _)) => volume.clone(),
+            None => {
+                let volume = container::volume_name(&name, path);
+                let dir = format!("{service}:{path}");
+                container::create_volume(
+                    &volume,
+                    &[
+                        (container::VOLUME_CONTAINER_LABEL, container_name),
+                        (container::VOLUME_DIR_LABEL, &dir),
+                    ],
+                )?;
+                volume
+            }
+        };
+        volumes.push((volume, path.clone()));
+    }
+
+    // A stale sidecar under this name (e.g. from an interrupted run) would block create
+    if container::status(&name) != ContainerStatus::Missing {
+        remove(&name)?;
+    }
+
+    let output = Command::new("docker")
+        .args(build_create_args(
+            container_name,
+            service,
+            config,
+            labels,
+            &volumes,
+        ))
+        .output()
+        .map_err(|e| ContainerError::DockerNotFound(e.to_string()))?;
+    if !output.status.success() {
+        return Err(ContainerError::Failed {
+            operation: "create".to_string(),
+            name,
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+
+    container::start(&name)
+}
+
+/// Stop and remove a sidecar container, keeping its volumes.
+pub fn remove(name: &str) -> Result<(), ContainerError> {
+    if container::status(name) == ContainerStatus::Running {
+        container::stop(name)?;
+    }
+    container::destroy(name)
+}
+
+/// Build the docker create arguments for a sidecar (for testing).
+///
+/// The sidecar joins the workspace container's network namespace, so the
+/// service listens on the workspace's `localhost`.
+pub fn build_create_args(
+    container_name: &str,
+    service: &str,
+    config: &ServiceConfig,
+    labels: &[(String, String)],
+    volumes: &[(String, String)],
+) -> Vec<String> {
+    let mut args = vec![
+        "create".to_string(),
+        "--name".to_string(),
+        sidecar_name(container_name, service),
+    ];
+    for (key, value) in labels {
+        args.push("--label".to_string());
+        args.push(format!("{key}={value}"));
+    }
+    args.push("--label".to_string());
+    args.push(format!("{}={service}", container::SERVICE_LABEL));
+    args.push("--network".to_string());
+    args.push(format!("container:{container_name}"));
+
+    let mut env: Vec<_> = config.env.iter().collect();
+    env.sort();
+    for (key, value) in env {
+        args.push("-e".to_string());
+        args.push(format!("{key}={value}"));
+    }
+    for (volume, path) in volumes {
+        args.push("-v".to_string());
+        args.push(format!("{volume}:{path}"));
+    }
+
+    args.push(config.image.clone());
+    args.extend(config.command.iter().cloned());
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+
+    fn service(image: &str) -> ServiceConfig {
+        ServiceConfig {
+            image: image.to_string(),
+            env: HashMap::new(),
+            command: Vec::new(),
+            volumes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn sidecar_shares_workspace_network() {
+        let mut pg = service("postgres:16");
+        pg.env
+            .insert("POSTGRES_PASSWORD".to_string(), "dev".to_string());
+        pg.command = vec![
+            "postgres".to_string(),
+            "-c".to_string(),
+            "fsync=off".to_string(),
+        ];
+        let labels = container::workspace_labels("app", "main");
+        let volumes = vec![(
+            "dual-app-main-postgres-var_lib_postgresql_data".to_string(),
+            "/var/lib/postgresql/data".to_string(),
+        )];
+
+        let args = build_create_args("dual-app-main", "postgres", &pg, &labels, &volumes);
+        assert_eq!(&args[..3], &["create", "--name", "dual-app-main-postgres"]);
+        assert!(args.contains(&"dev.dual.workspace_id=app-main".to_string()));
+        assert!(args.contains(&"dev.dual.service=postgres".to_string()));
+        assert!(
+            args.windows(2)
+                .any(|w| w == ["--network", "container:dual-app-main"])
+        );
+        assert!(
+            args.windows(2)
+                .any(|w| w == ["-e", "POSTGRES_PASSWORD=dev"])
+        );
+        assert!(args.contains(
+            &"dual-app-main-postgres-var_lib_postgresql_data:/var/lib/postgresql/data".to_string()
+        ));
+        assert_eq!(
+            &args[args.len() - 4..],
+            &["postgres:16", "postgres", "-c", "fsync=off"]
+        );
+    }
+
+    #[test]
+    fn config_services_override_compose() {
+        let dir = std::env::temp_dir().join("dual-test-services-resolve");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("docker-compose.yml"),
+            "services:\n  db:\n    image: postgres:15\n  cache:\n    image: redis:7\n",
+        )
+        .unwrap();
+
+        let mut hints = RepoHints {
+            compose_file: Some("docker-compose.yml".to_string()),
+            ..RepoHints::default()
+        };
+        hints
+            .services
+            .insert("db".to_string(), service("postgres:16"));
+
+        let (services, notes) = resolve(&hints, &dir);
+        assert!(notes.is_empty());
+        assert_eq!(services["db"].image, "postgres:16");
+        assert_eq!(services["cache"].image, "redis:7");
+
+        // A missing compose file is reported, not fatal
+        hints.compose_file = Some("missing.yml".to_string());
+        let (services, notes) = resolve(&hints, &dir);
+        assert_eq!(services.len(), 1);
+        assert_eq!(notes.len(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
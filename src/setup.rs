@@ -0,0 +1,322 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::{self, SetupStep};
+use crate::container::{self, ContainerError};
+
+const STATUS_FILENAME: &str = "setup.toml";
+
+/// How a setup step's last run ended.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum StepState {
+    Succeeded,
+    Failed,
+}
+
+/// The recorded outcome of a setup step's last run.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct StepStatus {
+    pub state: StepState,
+
+    /// Hash of the step's command and watched files when it ran
+    pub hash: String,
+
+    /// When the run finished (seconds since the Unix epoch)
+    pub finished_at: u64,
+
+    /// How long the run took, in seconds
+    pub duration_secs: u64,
+}
+
+/// Setup status of a workspace: ~/.dual/logs/{workspace}/setup.toml
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct SetupStatus {
+    /// Docker ID of the container the steps ran in. A different container
+    /// (e.g. after `dual recreate`) starts over with every step pending.
+    #[serde(default)]
+    pub container: String,
+
+    #[serde(default)]
+    pub steps: BTreeMap<String, StepStatus>,
+
+    /// Whether the `post_create` hooks have succeeded in this container;
+    /// until they have, every launch runs them again
+    #[serde(default)]
+    pub post_create: bool,
+}
+
+impl SetupStatus {
+    /// Load the status from `path`. A missing file means nothing has run.
+    pub fn load(path: &Path) -> Result<Self, SetupError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| SetupError::Filesystem(path.to_path_buf(), e))?;
+        toml::from_str(&contents).map_err(|e| SetupError::Parse(path.to_path_buf(), e))
+    }
+
+    /// Save the status to `path`, creating its directory.
+    pub fn save(&self, path: &Path) -> Result<(), SetupError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| SetupError::Filesystem(parent.to_path_buf(), e))?;
+        }
+        let contents = toml::to_string_pretty(self).map_err(SetupError::Serialize)?;
+        std::fs::write(path, contents).map_err(|e| SetupError::Filesystem(path.to_path_buf(), e))
+    }
+
+    /// Forget every step (and `post_create`) if it ran in a different container.
+    pub fn for_container(mut self, container_id: &str) -> Self {
+        if self.container != container_id {
+            self.container = container_id.to_string();
+            self.steps.clear();
+            self.post_create = false;
+        }
+        self
+    }
+
+    /// Steps that need to run: never run, failed last time, or changed since.
+    pub fn pending<'a>(&self, steps: &'a [SetupStep], workspace_dir: &Path) -> Vec<&'a SetupStep> {
+        steps
+            .iter()
+            .filter(|step| match self.steps.get(&step.name) {
+                Some(status) => {
+                    status.state == StepState::Failed
+                        || status.hash != step_hash(step, workspace_dir)
+                }
+                None => true,
+            })
+            .collect()
+    }
+}
+
+/// Log directory of a workspace: ~/.dual/logs/{workspace_id}/
+pub fn log_dir(workspace_id: &str) -> Option<PathBuf> {
+    config::logs_dir().map(|dir| dir.join(workspace_id))
+}
+
+/// Path of the status file in a workspace's log directory.
+pub fn status_path(log_dir: &Path) -> PathBuf {
+    log_dir.join(STATUS_FILENAME)
+}
+
+/// Path of a step's log in a workspace's log directory:
+/// setup-{index}-{step}.log, where `index` is the step's position in the plan
+/// (so names that sanitize alike, like "db:migrate" and "db_migrate", don't
+/// share a file).
+pub fn step_log_path(log_dir: &Path, index: usize, step: &str) -> PathBuf {
+    let name: String = step
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    log_dir.join(format!("setup-{index}-{name}.log"))
+}
+
+/// Hash of a step's command and the current contents of its watched files.
+pub fn step_hash(step: &SetupStep, workspace_dir: &Path) -> String {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(step.run.as_bytes());
+    for file in &step.watch {
+        hasher.update([0]);
+        hasher.update(file.as_bytes());
+        hasher.update([0]);
+        if let Ok(contents) = std::fs::read(workspace_dir.join(file)) {
+            hasher.update(&contents);
+        }
+    }
+    hasher
+        .finalize()
+        .iter()
+        .take(8)
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Run a step in a container, streaming its output to the terminal and
+/// `log_path`, and return the status to record.
+///
+/// A command that exits non-zero is a `Failed` status, not an error; errors
+/// are for problems running it at all.
+pub fn run_step(
+    container_name: &str,
+    step: &SetupStep,
+    workspace_dir: &Path,
+    log_path: &Path,
+) -> Result<StepStatus, SetupError> {
+    if let Some(log_dir) = log_path.parent() {
+        std::fs::create_dir_all(log_dir)
+            .map_err(|e| SetupError::Filesystem(log_dir.to_path_buf(), e))?;
+    }
+    let log = std::fs::File::create(log_path)
+        .map_err(|e| SetupError::Filesystem(log_path.to_path_buf(), e))?;
+
+    // Hash before running, so edits made while it runs count as a change
+    let hash = step_hash(step, workspace_dir);
+    let started = Instant::now();
+    let state = match container::exec_setup(container_name, &step.run, log) {
+        Ok(()) => StepState::Succeeded,
+        Err(ContainerError::Failed { .. }) => StepState::Failed,
+        Err(e) => return Err(SetupError::Container(e)),
+    };
+
+    Ok(StepStatus {
+        state,
+        hash,
+        finished_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        duration_secs: started.elapsed().as_secs(),
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SetupError {
+    #[error("Filesystem error at {path}: {err}", path = .0.display(), err = .1)]
+    Filesystem(PathBuf, std::io::Error),
+
+    #[error("Failed to parse setup status {path}: {err}", path = .0.display(), err = .1)]
+    Parse(PathBuf, toml::de::Error),
+
+    #[error("Failed to serialize setup status: {0}")]
+    Serialize(toml::ser::Error),
+
+    #[error("{0}")]
+    Container(ContainerError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_dir(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dual-test-setup-{test_name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn step(name: &str, run: &str, watch: &[&str]) -> SetupStep {
+        SetupStep {
+            name: name.to_string(),
+            run: run.to_string(),
+            watch: watch.iter().map(|w| w.to_string()).collect(),
+        }
+    }
+
+    fn status(state: StepState, hash: String) -> StepStatus {
+        StepStatus {
+            state,
+            hash,
+            finished_at: 0,
+            duration_secs: 0,
+        }
+    }
+
+    #[test]
+    fn only_failed_new_or_changed_steps_are_pending() {
+        let dir = temp_dir("pending");
+        fs::write(dir.join("pnpm-lock.yaml"), "v1").unwrap();
+        let steps = vec![
+            step("install", "pnpm install", &["pnpm-lock.yaml"]),
+            step("build", "pnpm build", &[]),
+            step("seed", "pnpm seed", &[]),
+            step("lint", "pnpm lint", &[]),
+        ];
+
+        let mut st = SetupStatus::default();
+        for s in &steps[..2] {
+            st.steps.insert(
+                s.name.clone(),
+                status(StepState::Succeeded, step_hash(s, &dir)),
+            );
+        }
+        st.steps.insert(
+            "seed".to_string(),
+            status(StepState::Failed, step_hash(&steps[2], &dir)),
+        );
+
+        let names = |pending: Vec<&SetupStep>| -> Vec<String> {
+            pending.iter().map(|s| s.name.clone()).collect()
+        };
+        assert_eq!(names(st.pending(&steps, &dir)), vec!["seed", "lint"]);
+
+        // Editing a watched file re-runs its step
+        fs::write(dir.join("pnpm-lock.yaml"), "v2").unwrap();
+        assert_eq!(
+            names(st.pending(&steps, &dir)),
+            vec!["install", "seed", "lint"]
+        );
+
+        // So does changing the command
+        let changed = vec![step("build", "pnpm build --prod", &[])];
+        assert_eq!(names(st.pending(&changed, &dir)), vec!["build"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn new_container_resets_status() {
+        let mut st = SetupStatus {
+            container: "abc".to_string(),
+            ..Default::default()
+        };
+        st.steps.insert(
+            "setup".to_string(),
+            status(StepState::Succeeded, "h".to_string()),
+        );
+        st.post_create = true;
+
+        let st = st.for_container("abc");
+        assert_eq!(st.steps.len(), 1);
+        assert!(st.post_create);
+        let st = st.for_container("def");
+        assert!(st.steps.is_empty());
+        assert!(!st.post_create);
+        assert_eq!(st.container, "def");
+    }
+
+    #[test]
+    fn status_roundtrip() {
+        let dir = temp_dir("roundtrip");
+        let path = status_path(&dir.join("app-main"));
+
+        let mut st = SetupStatus {
+            container: "abc".to_string(),
+            ..Default::default()
+        };
+        st.steps.insert(
+            "install".to_string(),
+            status(StepState::Failed, "0123456789abcdef".to_string()),
+        );
+        st.save(&path).unwrap();
+        assert_eq!(SetupStatus::load(&path).unwrap(), st);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn step_logs_are_named_after_steps() {
+        let dir = Path::new("/home/u/.dual/logs/app-main");
+        assert_eq!(
+            step_log_path(dir, 1, "db:migrate"),
+            dir.join("setup-1-db_migrate.log")
+        );
+        assert_ne!(
+            step_log_path(dir, 1, "db:migrate"),
+            step_log_path(dir, 2, "db_migrate")
+        );
+    }
+}
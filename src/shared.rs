@@ -1,4 +1,17 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::{SharedConfig, SharedMode};
+use crate::container::BindMount;
+use crate::secrets::{SecretKey, SecretsError};
+
+/// Directory inside the shared store holding per-workspace sync records.
+const SYNC_RECORD_DIR: &str = ".dual-sync";
+
+/// What every age-encrypted file starts with.
+const AGE_HEADER: &[u8] = b"age-encryption.org/";
 
 /// Ensure the shared directory exists for a repo.
 pub fn ensure_shared_dir(repo: &str) -> Result<PathBuf, SharedError> {
@@ -7,6 +20,131 @@ pub fn ensure_shared_dir(repo: &str) -> Result<PathBuf, SharedError> {
     Ok(dir)
 }
 
+/// A repo's shared store: a directory, optionally encrypted at rest.
+///
+/// With a key, every file in the store is age-encrypted and only decrypted
+/// when materialized into a workspace. Hashes are always of the plaintext, so
+/// sync records compare the same way in both modes.
+#[derive(Clone, Copy)]
+pub struct Store<'a> {
+    pub dir: &'a Path,
+    key: Option<&'a SecretKey>,
+}
+
+impl<'a> Store<'a> {
+    pub fn new(dir: &'a Path, key: Option<&'a SecretKey>) -> Self {
+        Self { dir, key }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Hash of a store entry's plaintext, or None if it doesn't exist.
+    pub fn hash(&self, file: &str) -> Option<String> {
+        hash_with(&self.dir.join(file), &|path| {
+            let data = std::fs::read(path)?;
+            match self.key {
+                Some(key) => key.decrypt(&data).map_err(std::io::Error::other),
+                None => Ok(data),
+            }
+        })
+    }
+
+    /// Plaintext contents of a single store file, or None if it doesn't exist.
+    pub fn read(&self, file: &str) -> Result<Option<Vec<u8>>, SharedError> {
+        let path = self.dir.join(file);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let data = std::fs::read(&path).map_err(|e| SharedError::Filesystem(path, e))?;
+        match self.key {
+            Some(key) => Ok(Some(key.decrypt(&data)?)),
+            None => Ok(Some(data)),
+        }
+    }
+
+    /// Copy a store entry into a workspace, decrypting it. False if missing.
+    fn pull(&self, workspace_dir: &Path, file: &str) -> Result<bool, SharedError> {
+        match self.key {
+            Some(key) => copy_entry(
+                self.dir,
+                workspace_dir,
+                file,
+                Some(&|data| Ok(key.decrypt(&data)?)),
+            ),
+            None => copy_entry(self.dir, workspace_dir, file, None),
+        }
+    }
+
+    /// Files under the store entries for `files` whose format at rest doesn't
+    /// match the store: plaintext in an encrypted store, or the reverse.
+    fn mismatched(&self, files: &[String]) -> Result<Vec<PathBuf>, SharedError> {
+        fn walk(path: &Path, out: &mut Vec<PathBuf>) -> Result<(), SharedError> {
+            let metadata = path
+                .symlink_metadata()
+                .map_err(|e| SharedError::Filesystem(path.to_path_buf(), e))?;
+            if metadata.is_dir() {
+                for entry in std::fs::read_dir(path)
+                    .map_err(|e| SharedError::Filesystem(path.to_path_buf(), e))?
+                {
+                    let entry =
+                        entry.map_err(|e| SharedError::Filesystem(path.to_path_buf(), e))?;
+                    walk(&entry.path(), out)?;
+                }
+            } else if metadata.is_file() {
+                out.push(path.to_path_buf());
+            }
+            Ok(())
+        }
+
+        let mut paths = Vec::new();
+        for file in resolve_files(&[self.dir], files)? {
+            let path = self.dir.join(&file);
+            if path.symlink_metadata().is_ok() {
+                walk(&path, &mut paths)?;
+            }
+        }
+
+        let mut mismatched = Vec::new();
+        for path in paths {
+            let mut head = [0u8; AGE_HEADER.len()];
+            let n = std::fs::File::open(&path)
+                .and_then(|mut f| std::io::Read::read(&mut f, &mut head))
+                .map_err(|e| SharedError::Filesystem(path.clone(), e))?;
+            if (&head[..n] == AGE_HEADER) != self.is_encrypted() {
+                mismatched.push(path);
+            }
+        }
+        Ok(mismatched)
+    }
+
+    /// Fail if the store's files aren't in the format its config says.
+    fn check_format(&self, files: &[String]) -> Result<(), SharedError> {
+        match self.mismatched(files)?.is_empty() {
+            true => Ok(()),
+            false if self.is_encrypted() => Err(SharedError::PlaintextStore),
+            false => Err(SharedError::EncryptedStore),
+        }
+    }
+
+    /// Copy a workspace entry into the store, encrypting it. False if missing.
+    fn push(&self, workspace_dir: &Path, file: &str) -> Result<bool, SharedError> {
+        match self.key {
+            Some(key) => copy_entry(
+                workspace_dir,
+                self.dir,
+                file,
+                Some(&|data| Ok(key.encrypt(&data)?)),
+            ),
+            None => copy_entry(workspace_dir, self.dir, file, None),
+        }
+    }
+}
+
+/// Transformation applied to file contents while copying.
+type Transform<'a> = dyn Fn(Vec<u8>) -> Result<Vec<u8>, SharedError> + 'a;
+
 /// Initialize shared directory from the main workspace.
 ///
 /// For each file in `files`:
@@ -15,15 +153,56 @@ pub fn ensure_shared_dir(repo: &str) -> Result<PathBuf, SharedError> {
 /// - If file is already a symlink to shared dir: skip
 /// - If file doesn't exist in workspace: skip
 ///
+/// An encrypted store can't be linked to, so the main workspace keeps its
+/// plaintext files and only those missing from the store are encrypted into
+/// it; after that the main workspace syncs like a branch. A store that was
+/// plaintext until `encrypted` was turned on is migrated first: the main
+/// workspace's links into it become plaintext copies, then its files are
+/// encrypted in place.
+///
 /// Returns list of files that were moved.
 pub fn init_from_main(
     workspace_dir: &Path,
-    shared_dir: &Path,
+    store: Store,
     files: &[String],
 ) -> Result<Vec<String>, SharedError> {
+    let shared_dir = store.dir;
     let mut moved = Vec::new();
 
-    for file in files {
+    if let Some(key) = store.key {
+        let plaintext = store.mismatched(files)?;
+        if !plaintext.is_empty() {
+            for file in resolve_files(&[shared_dir], files)? {
+                if is_linked(workspace_dir, shared_dir, &file) {
+                    let link = workspace_dir.join(&file);
+                    remove_recursive(&link)?;
+                    copy_recursive_with(&shared_dir.join(&file), &link, None)?;
+                }
+            }
+            for path in plaintext {
+                let data =
+                    std::fs::read(&path).map_err(|e| SharedError::Filesystem(path.clone(), e))?;
+                std::fs::write(&path, key.encrypt(&data)?)
+                    .map_err(|e| SharedError::Filesystem(path.clone(), e))?;
+            }
+        }
+    } else {
+        store.check_format(files)?;
+    }
+
+    if store.is_encrypted() {
+        for file in resolve_files(&[workspace_dir], files)? {
+            let src = workspace_dir.join(&file);
+            let is_link = src.symlink_metadata().is_ok_and(|m| m.is_symlink());
+            if src.exists() && !is_link && !shared_dir.join(&file).exists() {
+                store.push(workspace_dir, &file)?;
+                moved.push(file);
+            }
+        }
+        return Ok(moved);
+    }
+
+    for file in &resolve_files(&[workspace_dir], files)? {
         let src = workspace_dir.join(file);
         let dst = shared_dir.join(file);
 
@@ -50,7 +229,7 @@ pub fn init_from_main(
 
         // Move file/dir to shared dir
         // Use copy + remove for cross-device moves (rename fails across filesystems)
-        copy_recursive(&src, &dst)?;
+        overwrite_recursive(&src, &dst, None)?;
         remove_recursive(&src)?;
 
         // Create symlink back (Unix) or copy back (Windows)
@@ -79,39 +258,675 @@ pub fn init_from_main(
 /// Returns list of files that were copied.
 pub fn copy_to_branch(
     workspace_dir: &Path,
-    shared_dir: &Path,
+    store: Store,
     files: &[String],
 ) -> Result<Vec<String>, SharedError> {
     let mut copied = Vec::new();
 
-    for file in files {
+    for file in resolve_files(&[store.dir], files)? {
+        if store.pull(workspace_dir, &file)? {
+            copied.push(file);
+        }
+    }
+
+    Ok(copied)
+}
+
+/// Copy `{from}/{file}` over `{to}/{file}` in place (see `overwrite_recursive`),
+/// passing each file's contents through `transform` if given. Returns false if
+/// the source is missing.
+fn copy_entry(
+    from: &Path,
+    to: &Path,
+    file: &str,
+    transform: Option<&Transform>,
+) -> Result<bool, SharedError> {
+    let src = from.join(file);
+    let dst = to.join(file);
+
+    if !src.exists() {
+        return Ok(false); // Not in shared dir yet, skip
+    }
+
+    // Ensure parent dir exists in workspace
+    if let Some(parent) = dst.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| SharedError::Filesystem(parent.to_path_buf(), e))?;
+    }
+
+    overwrite_recursive(&src, &dst, transform)?;
+    Ok(true)
+}
+
+/// Directories never searched when expanding glob patterns.
+const SKIP_DIRS: &[&str] = &[".git", "node_modules", SYNC_RECORD_DIR];
+
+/// Expand `shared.files` entries into concrete relative paths.
+///
+/// Entries may be literal paths or glob patterns (`*`, `?`, `[..]`, `{a,b}`,
+/// and `**` across directories). Entries starting with `!` exclude matching
+/// paths from every other entry, regardless of order. Globs are matched
+/// against what exists under any of `roots`; literal paths are kept even if
+/// missing so callers can report them. A matched directory is shared as a
+/// whole and not searched further; `.git`/`node_modules` and excluded
+/// directories are never matched or searched, and excluding a directory
+/// excludes everything under it.
+pub fn resolve_files(roots: &[&Path], patterns: &[String]) -> Result<Vec<String>, SharedError> {
+    use globset::{GlobBuilder, GlobSetBuilder};
+
+    let glob = |pattern: &str| {
+        GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| SharedError::InvalidPattern(pattern.to_string(), e))
+    };
+
+    let mut literals = Vec::new();
+    let mut includes = GlobSetBuilder::new();
+    let mut excludes = GlobSetBuilder::new();
+    let mut walk_from = Vec::new();
+
+    for pattern in patterns {
+        let pattern = pattern.trim_end_matches('/');
+        if let Some(excluded) = pattern.strip_prefix('!') {
+            excludes.add(glob(excluded.trim_end_matches('/'))?);
+        } else if is_glob(pattern) {
+            includes.add(glob(pattern)?);
+            walk_from.push(literal_prefix(pattern));
+        } else {
+            literals.push(pattern.to_string());
+        }
+    }
+
+    let includes = includes
+        .build()
+        .map_err(|e| SharedError::InvalidPattern(patterns.join(", "), e))?;
+    let excludes = excludes
+        .build()
+        .map_err(|e| SharedError::InvalidPattern(patterns.join(", "), e))?;
+
+    let mut resolved = std::collections::BTreeSet::new();
+    resolved.extend(literals);
+
+    for root in roots {
+        for prefix in &walk_from {
+            collect_matches(
+                root,
+                &root.join(prefix),
+                &includes,
+                &excludes,
+                &mut resolved,
+            );
+        }
+    }
+
+    Ok(resolved
+        .into_iter()
+        .filter(|f| !is_excluded(&excludes, f))
+        .collect())
+}
+
+/// Whether `rel` or any directory containing it matches an exclusion, so
+/// `!dir` also excludes everything under `dir`.
+fn is_excluded(excludes: &globset::GlobSet, rel: &str) -> bool {
+    let mut path = Some(Path::new(rel));
+    while let Some(p) = path.filter(|p| !p.as_os_str().is_empty()) {
+        if excludes.is_match(p) {
+            return true;
+        }
+        path = p.parent();
+    }
+    false
+}
+
+/// Whether a `shared.files` entry contains glob syntax.
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+/// The leading directories of a pattern that contain no glob syntax,
+/// e.g. `apps/*/.env` → `apps`. Searching starts there.
+fn literal_prefix(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .take_while(|c| !is_glob(c))
+        .collect()
+}
+
+/// Walk `dir`, adding paths (relative to `root`) that match `set`. Skipped
+/// and excluded directories are pruned: neither matched nor searched.
+fn collect_matches(
+    root: &Path,
+    dir: &Path,
+    set: &globset::GlobSet,
+    excludes: &globset::GlobSet,
+    out: &mut std::collections::BTreeSet<String>,
+) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let Ok(rel) = path.strip_prefix(root) else {
+            continue;
+        };
+        let rel = rel.to_string_lossy().replace('\\', "/");
+
+        let name = entry.file_name();
+        if SKIP_DIRS.iter().any(|s| name == *s) || excludes.is_match(&rel) {
+            continue;
+        }
+        if set.is_match(&rel) {
+            out.insert(rel);
+            continue;
+        }
+
+        // Don't follow symlinks (shared files in the main workspace are links)
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            collect_matches(root, &path, set, excludes, out);
+        }
+    }
+}
+
+/// Content hashes of shared files as of a workspace's last sync.
+///
+/// This is the common ancestor for three-way comparison: a file whose
+/// workspace copy differs from its recorded hash was edited in the workspace,
+/// and one whose shared copy differs was updated in the store.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+pub struct SyncRecord {
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+}
+
+impl SyncRecord {
+    /// Load the record for a workspace. Missing or unreadable records are empty.
+    pub fn load(shared_dir: &Path, workspace_id: &str) -> Self {
+        std::fs::read_to_string(record_path(shared_dir, workspace_id))
+            .ok()
+            .and_then(|s| toml::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save the record for a workspace.
+    pub fn save(&self, shared_dir: &Path, workspace_id: &str) -> Result<(), SharedError> {
+        let path = record_path(shared_dir, workspace_id);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| SharedError::Filesystem(parent.to_path_buf(), e))?;
+        }
+        let contents = toml::to_string_pretty(self).map_err(SharedError::Serialize)?;
+        std::fs::write(&path, contents).map_err(|e| SharedError::Filesystem(path, e))
+    }
+}
+
+/// Path of the sync record for a workspace: {shared_dir}/.dual-sync/{workspace_id}.toml
+fn record_path(shared_dir: &Path, workspace_id: &str) -> PathBuf {
+    shared_dir
+        .join(SYNC_RECORD_DIR)
+        .join(format!("{workspace_id}.toml"))
+}
+
+/// How a workspace's copy of a shared file relates to the shared store.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileStatus {
+    /// Both sides have the same content.
+    InSync,
+    /// Only the shared store changed since the last sync (safe to pull).
+    SharedChanged,
+    /// Only the workspace copy changed since the last sync (can be pushed).
+    LocalChanged,
+    /// Both sides changed since the last sync.
+    Conflict,
+    /// Present in the store but not in the workspace.
+    MissingLocal,
+    /// Present in the workspace but not in the store.
+    LocalOnly,
+    /// Present on neither side.
+    Absent,
+}
+
+impl FileStatus {
+    /// Short label for `dual sync --status`.
+    pub fn label(self) -> &'static str {
+        match self {
+            FileStatus::InSync => "in sync",
+            FileStatus::SharedChanged => "out of date",
+            FileStatus::LocalChanged => "changed locally",
+            FileStatus::Conflict => "conflict",
+            FileStatus::MissingLocal => "missing",
+            FileStatus::LocalOnly => "local only",
+            FileStatus::Absent => "absent",
+        }
+    }
+}
+
+/// Compare a workspace copy against the shared store and the last-sync hash.
+pub fn file_status(
+    workspace_dir: &Path,
+    store: Store,
+    file: &str,
+    base: Option<&str>,
+) -> FileStatus {
+    let local = content_hash(&workspace_dir.join(file));
+    let shared = store.hash(file);
+
+    match (local.as_deref(), shared.as_deref()) {
+        (None, None) => FileStatus::Absent,
+        (None, Some(_)) => FileStatus::MissingLocal,
+        (Some(_), None) => FileStatus::LocalOnly,
+        (Some(l), Some(s)) if l == s => FileStatus::InSync,
+        (Some(l), Some(s)) => match base {
+            Some(b) if b == l => FileStatus::SharedChanged,
+            Some(b) if b == s => FileStatus::LocalChanged,
+            _ => FileStatus::Conflict,
+        },
+    }
+}
+
+/// What `sync_branch` did with a file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncAction {
+    /// Already identical; nothing copied.
+    Unchanged,
+    /// Copied from the shared store into the workspace.
+    Pulled,
+    /// Copied from the workspace into the shared store.
+    Pushed,
+    /// Workspace has local edits that were not pushed.
+    SkippedLocal,
+    /// Both sides changed; left untouched.
+    Conflict,
+}
+
+/// Options for `sync_branch`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SyncOptions {
+    /// Push local-only edits back to the shared store.
+    pub push: bool,
+    /// Resolve conflicts instead of skipping them: towards the workspace if
+    /// `push` is set, otherwise towards the shared store.
+    pub force: bool,
+}
+
+/// Two-way sync of shared files for a branch workspace.
+///
+/// Uses the workspace's `SyncRecord` to tell which side changed:
+/// - store changed → pulled into the workspace
+/// - workspace changed → pushed to the store if `push`, otherwise skipped
+/// - both changed → conflict, left untouched unless `force`
+///
+/// The record is updated for every file that ends up identical on both sides.
+pub fn sync_branch(
+    workspace_dir: &Path,
+    store: Store,
+    workspace_id: &str,
+    files: &[String],
+    options: SyncOptions,
+) -> Result<Vec<(String, SyncAction)>, SharedError> {
+    store.check_format(files)?;
+    let mut record = SyncRecord::load(store.dir, workspace_id);
+    let mut results = Vec::new();
+
+    for file in &resolve_files(&[store.dir, workspace_dir], files)? {
+        let base = record.files.get(file).map(String::as_str);
+        let status = file_status(workspace_dir, store, file, base);
+
+        let pull = || store.pull(workspace_dir, file);
+        let push = || store.push(workspace_dir, file);
+
+        let action = match status {
+            FileStatus::Absent => continue,
+            FileStatus::InSync => SyncAction::Unchanged,
+            FileStatus::SharedChanged | FileStatus::MissingLocal => {
+                pull()?;
+                SyncAction::Pulled
+            }
+            FileStatus::LocalChanged | FileStatus::LocalOnly if options.push => {
+                push()?;
+                SyncAction::Pushed
+            }
+            FileStatus::LocalChanged | FileStatus::LocalOnly => SyncAction::SkippedLocal,
+            FileStatus::Conflict if options.force && options.push => {
+                push()?;
+                SyncAction::Pushed
+            }
+            FileStatus::Conflict if options.force => {
+                pull()?;
+                SyncAction::Pulled
+            }
+            FileStatus::Conflict => SyncAction::Conflict,
+        };
+
+        if matches!(
+            action,
+            SyncAction::Unchanged | SyncAction::Pulled | SyncAction::Pushed
+        ) && let Some(hash) = content_hash(&workspace_dir.join(file))
+        {
+            record.files.insert(file.clone(), hash);
+        }
+
+        results.push((file.clone(), action));
+    }
+
+    record.save(store.dir, workspace_id)?;
+    Ok(results)
+}
+
+/// Symlink shared files into a branch workspace (`symlink` mode).
+///
+/// Existing copies identical to the shared file are replaced by the link;
+/// copies that differ are left alone and reported as conflicts so local edits
+/// aren't lost. On Windows, files are copied instead.
+pub fn link_to_branch(
+    workspace_dir: &Path,
+    shared_dir: &Path,
+    files: &[String],
+) -> Result<Vec<(String, SyncAction)>, SharedError> {
+    let mut results = Vec::new();
+
+    for file in &resolve_files(&[shared_dir], files)? {
         let src = shared_dir.join(file);
         let dst = workspace_dir.join(file);
 
         if !src.exists() {
-            continue; // Not in shared dir yet, skip
+            continue;
+        }
+
+        if is_linked(workspace_dir, shared_dir, file) {
+            results.push((file.clone(), SyncAction::Unchanged));
+            continue;
+        }
+
+        if dst.symlink_metadata().is_ok() {
+            if content_hash(&dst) != content_hash(&src) {
+                results.push((file.clone(), SyncAction::Conflict));
+                continue;
+            }
+            remove_recursive(&dst)?;
         }
 
-        // Ensure parent dir exists in workspace
         if let Some(parent) = dst.parent() {
             std::fs::create_dir_all(parent)
                 .map_err(|e| SharedError::Filesystem(parent.to_path_buf(), e))?;
         }
 
-        // Remove existing destination to handle overwrite cleanly
-        if dst.exists() || dst.symlink_metadata().is_ok() {
-            remove_recursive(&dst)?;
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&src, &dst)
+                .map_err(|e| SharedError::Filesystem(dst.clone(), e))?;
+        }
+        #[cfg(windows)]
+        {
+            copy_recursive(&src, &dst)?;
         }
 
-        copy_recursive(&src, &dst)?;
-        copied.push(file.clone());
+        results.push((file.clone(), SyncAction::Pulled));
     }
 
-    Ok(copied)
+    Ok(results)
+}
+
+/// Whether a workspace path is a symlink to its shared copy.
+pub fn is_linked(workspace_dir: &Path, shared_dir: &Path, file: &str) -> bool {
+    std::fs::read_link(workspace_dir.join(file)).is_ok_and(|target| target == shared_dir.join(file))
+}
+
+/// Bring a branch workspace's shared files up to date, per file mode:
+/// `copy` files go through `sync_branch`, `symlink` files through
+/// `link_to_branch`. `mount` files live in the container and need nothing.
+/// An encrypted store only supports copies, so every file is synced that way.
+pub fn sync_workspace(
+    workspace_dir: &Path,
+    store: Store,
+    workspace_id: &str,
+    shared: &SharedConfig,
+    options: SyncOptions,
+) -> Result<Vec<(String, SyncAction)>, SharedError> {
+    if store.is_encrypted() {
+        return sync_branch(workspace_dir, store, workspace_id, &shared.files, options);
+    }
+
+    store.check_format(&shared.files)?;
+    let mut results = sync_branch(
+        workspace_dir,
+        store,
+        workspace_id,
+        &shared.files_with_mode(SharedMode::Copy),
+        options,
+    )?;
+    results.extend(link_to_branch(
+        workspace_dir,
+        store.dir,
+        &shared.files_with_mode(SharedMode::Symlink),
+    )?);
+    Ok(results)
+}
+
+/// Where one shared file of a branch workspace stands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileReport {
+    pub file: String,
+    pub mode: SharedMode,
+    pub state: &'static str,
+    /// False if `dual sync` has something to do (or a conflict to resolve).
+    pub clean: bool,
 }
 
-/// Recursively copy a file or directory.
+/// Resolve a branch workspace's shared files and report each one's state,
+/// without changing anything. Used by `dual sync --status` and `--dry-run`.
+pub fn report(
+    workspace_dir: &Path,
+    store: Store,
+    workspace_id: &str,
+    shared: &SharedConfig,
+) -> Result<Vec<FileReport>, SharedError> {
+    let shared_dir = store.dir;
+    let record = SyncRecord::load(shared_dir, workspace_id);
+    let mut reports = Vec::new();
+
+    for mode in [SharedMode::Copy, SharedMode::Symlink, SharedMode::Mount] {
+        let files = if store.is_encrypted() {
+            // Everything is copied out of an encrypted store
+            if mode != SharedMode::Copy {
+                continue;
+            }
+            shared.files.clone()
+        } else if shared.uses_mode(mode) {
+            shared.files_with_mode(mode)
+        } else {
+            continue;
+        };
+        for file in resolve_files(&[shared_dir, workspace_dir], &files)? {
+            let in_store = shared_dir.join(&file).exists();
+            let (state, clean) = match mode {
+                SharedMode::Copy => {
+                    let base = record.files.get(&file).map(String::as_str);
+                    match file_status(workspace_dir, store, &file, base) {
+                        FileStatus::Absent => continue,
+                        status => (status.label(), status == FileStatus::InSync),
+                    }
+                }
+                SharedMode::Symlink if is_linked(workspace_dir, shared_dir, &file) => {
+                    ("linked", true)
+                }
+                SharedMode::Symlink if in_store => ("not linked", false),
+                SharedMode::Mount if in_store => ("mounted", true),
+                SharedMode::Symlink | SharedMode::Mount => ("local only", false),
+            };
+            reports.push(FileReport {
+                file,
+                mode,
+                state,
+                clean,
+            });
+        }
+    }
+
+    Ok(reports)
+}
+
+/// Bind mounts a workspace container needs for its shared files.
+///
+/// Symlinks (the main workspace's, and `symlink`-mode files in branches) point
+/// at absolute host paths, so the shared dir is mounted at the same path in the
+/// container. `mount`-mode files are mounted read-only at /workspace/<file>;
+/// updates to the store rewrite them in place, so the mounts stay current.
+pub fn container_mounts(
+    shared: Option<&SharedConfig>,
+    shared_dir: &Path,
+    is_main: bool,
+) -> Vec<BindMount> {
+    // Encrypted stores hold ciphertext; workspaces get decrypted copies instead
+    let Some(shared) = shared.filter(|s| !s.files.is_empty() && !s.encrypted) else {
+        return Vec::new();
+    };
+
+    let mut mounts = Vec::new();
+    if is_main || shared.uses_mode(SharedMode::Symlink) {
+        mounts.push(BindMount {
+            source: shared_dir.to_path_buf(),
+            target: shared_dir.display().to_string(),
+            read_only: false,
+        });
+    }
+
+    if !is_main {
+        let files = shared.files_with_mode(SharedMode::Mount);
+        for file in resolve_files(&[shared_dir], &files).unwrap_or_default() {
+            let source = shared_dir.join(&file);
+            // Docker would create a missing source as an empty directory
+            if source.exists() {
+                mounts.push(BindMount::in_workspace(source, &file, true));
+            }
+        }
+    }
+
+    mounts
+}
+
+/// Unified diff from the shared copy to the workspace copy of a file.
+///
+/// Uses `git diff --no-index`, which handles both files and directories.
+/// Encrypted entries are decrypted into a private scratch directory for the
+/// duration of the diff. A side that doesn't exist diffs as empty.
+pub fn diff(workspace_dir: &Path, store: Store, file: &str) -> Result<String, SharedError> {
+    // Decrypted plaintext goes in a fresh directory only the user can read
+    // (created exclusively, mode 0700), removed when `scratch` drops
+    let scratch = if store.is_encrypted() {
+        let dir = tempfile::Builder::new()
+            .prefix("dual-diff-")
+            .tempdir()
+            .map_err(|e| SharedError::Filesystem(std::env::temp_dir(), e))?;
+        store.pull(dir.path(), file)?;
+        Some(dir)
+    } else {
+        None
+    };
+    let shared_root = scratch.as_ref().map_or(store.dir, |dir| dir.path());
+    let (shared_path, workspace_path) = (shared_root.join(file), workspace_dir.join(file));
+
+    // git can't read a missing path: compare against /dev/null instead, or an
+    // empty directory when the other side is a directory
+    let empty_dir = if shared_path.is_dir() != workspace_path.is_dir()
+        && !(shared_path.exists() && workspace_path.exists())
+    {
+        Some(
+            tempfile::Builder::new()
+                .prefix("dual-diff-")
+                .tempdir()
+                .map_err(|e| SharedError::Filesystem(std::env::temp_dir(), e))?,
+        )
+    } else {
+        None
+    };
+    let side = |path: PathBuf| match empty_dir {
+        _ if path.exists() => path,
+        Some(ref dir) => dir.path().to_path_buf(),
+        None => PathBuf::from("/dev/null"),
+    };
+
+    let output = Command::new("git")
+        .args(["diff", "--no-index", "--no-color", "--"])
+        .arg(side(shared_path))
+        .arg(side(workspace_path))
+        .output()
+        .map_err(|e| SharedError::Filesystem(workspace_dir.join(file), e))?;
+
+    // Exit code 1 means "differences found", unless there is no diff to show
+    // (git also exits 1 when it can't read a path)
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    match output.status.code() {
+        Some(0) => Ok(stdout),
+        Some(1) if !stdout.is_empty() => Ok(stdout),
+        _ => Err(SharedError::Diff(
+            file.to_string(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )),
+    }
+}
+
+/// SHA-256 of a file, or of a directory's relative paths and contents.
+/// Returns None if the path doesn't exist.
+pub fn content_hash(path: &Path) -> Option<String> {
+    hash_with(path, &|p| std::fs::read(p))
+}
+
+/// `content_hash` with a custom reader for file contents.
+fn hash_with(path: &Path, read: &dyn Fn(&Path) -> std::io::Result<Vec<u8>>) -> Option<String> {
+    use sha2::{Digest, Sha256};
+
+    fn feed(
+        hasher: &mut Sha256,
+        root: &Path,
+        path: &Path,
+        read: &dyn Fn(&Path) -> std::io::Result<Vec<u8>>,
+    ) -> std::io::Result<()> {
+        let metadata = std::fs::metadata(path)?;
+        if metadata.is_dir() {
+            let mut children: Vec<_> = std::fs::read_dir(path)?
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .collect();
+            children.sort();
+            for child in children {
+                feed(hasher, root, &child, read)?;
+            }
+        } else {
+            let rel = path.strip_prefix(root).unwrap_or(path);
+            hasher.update(rel.to_string_lossy().as_bytes());
+            hasher.update([0]);
+            hasher.update(read(path)?);
+            hasher.update([0]);
+        }
+        Ok(())
+    }
+
+    let mut hasher = Sha256::new();
+    feed(&mut hasher, path, path, read).ok()?;
+    Some(
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect(),
+    )
+}
+
+/// Recursively copy a file or directory (the Windows stand-in for symlinks).
+#[cfg(windows)]
 fn copy_recursive(src: &Path, dst: &Path) -> Result<(), SharedError> {
+    copy_recursive_with(src, dst, None)
+}
+
+/// Recursively copy a file or directory, transforming each file's contents.
+fn copy_recursive_with(
+    src: &Path,
+    dst: &Path,
+    transform: Option<&Transform>,
+) -> Result<(), SharedError> {
     let metadata = src
         .symlink_metadata()
         .map_err(|e| SharedError::Filesystem(src.to_path_buf(), e))?;
@@ -125,8 +940,12 @@ fn copy_recursive(src: &Path, dst: &Path) -> Result<(), SharedError> {
             let entry = entry.map_err(|e| SharedError::Filesystem(src.to_path_buf(), e))?;
             let child_src = entry.path();
             let child_dst = dst.join(entry.file_name());
-            copy_recursive(&child_src, &child_dst)?;
+            copy_recursive_with(&child_src, &child_dst, transform)?;
         }
+    } else if let Some(transform) = transform {
+        let data = std::fs::read(src).map_err(|e| SharedError::Filesystem(src.to_path_buf(), e))?;
+        std::fs::write(dst, transform(data)?)
+            .map_err(|e| SharedError::Filesystem(dst.to_path_buf(), e))?;
     } else {
         std::fs::copy(src, dst).map_err(|e| SharedError::Filesystem(src.to_path_buf(), e))?;
     }
@@ -134,6 +953,54 @@ fn copy_recursive(src: &Path, dst: &Path) -> Result<(), SharedError> {
     Ok(())
 }
 
+/// Copy `src` over `dst`, updating existing files and directories in place
+/// rather than replacing them.
+///
+/// Running containers bind-mount `mount`-mode files by inode, so a file that
+/// is removed and recreated would leave them with the stale copy. Files are
+/// truncated and rewritten instead, and entries of `dst` missing from `src`
+/// are removed. Only a destination of a different kind (or a symlink) is
+/// replaced.
+fn overwrite_recursive(
+    src: &Path,
+    dst: &Path,
+    transform: Option<&Transform>,
+) -> Result<(), SharedError> {
+    let Ok(dst_metadata) = dst.symlink_metadata() else {
+        return copy_recursive_with(src, dst, transform);
+    };
+    let src_metadata = src
+        .symlink_metadata()
+        .map_err(|e| SharedError::Filesystem(src.to_path_buf(), e))?;
+
+    if src_metadata.is_dir() && dst_metadata.is_dir() {
+        let read_names = |dir: &Path| -> Result<Vec<std::ffi::OsString>, SharedError> {
+            std::fs::read_dir(dir)
+                .map_err(|e| SharedError::Filesystem(dir.to_path_buf(), e))?
+                .map(|entry| {
+                    entry
+                        .map(|e| e.file_name())
+                        .map_err(|e| SharedError::Filesystem(dir.to_path_buf(), e))
+                })
+                .collect()
+        };
+        let names = read_names(src)?;
+        for stale in read_names(dst)?.iter().filter(|n| !names.contains(n)) {
+            remove_recursive(&dst.join(stale))?;
+        }
+        for name in &names {
+            overwrite_recursive(&src.join(name), &dst.join(name), transform)?;
+        }
+        Ok(())
+    } else if !src_metadata.is_dir() && dst_metadata.is_file() {
+        // Both `fs::copy` and `fs::write` truncate an existing file
+        copy_recursive_with(src, dst, transform)
+    } else {
+        remove_recursive(dst)?;
+        copy_recursive_with(src, dst, transform)
+    }
+}
+
 /// Remove a file or directory.
 fn remove_recursive(path: &Path) -> Result<(), SharedError> {
     let metadata = path
@@ -157,6 +1024,26 @@ pub enum SharedError {
 
     #[error("Filesystem error at {path}: {err}", path = .0.display(), err = .1)]
     Filesystem(PathBuf, std::io::Error),
+
+    #[error("Failed to serialize sync record: {0}")]
+    Serialize(toml::ser::Error),
+
+    #[error("Could not diff {0}: {1}")]
+    Diff(String, String),
+
+    #[error(
+        "Shared store holds plaintext files but is configured as encrypted; run `dual sync` in the main workspace to encrypt it"
+    )]
+    PlaintextStore,
+
+    #[error("Shared store is encrypted; set `encrypted = true` under [shared] in .dual.toml")]
+    EncryptedStore,
+
+    #[error("Invalid shared file pattern '{0}': {1}")]
+    InvalidPattern(String, globset::Error),
+
+    #[error(transparent)]
+    Secrets(#[from] SecretsError),
 }
 
 #[cfg(test)]
@@ -186,7 +1073,12 @@ mod tests {
         // Create a file in workspace
         fs::write(workspace.join(".env.local"), "SECRET=abc123").unwrap();
 
-        let result = init_from_main(&workspace, &shared, &[".env.local".to_string()]).unwrap();
+        let result = init_from_main(
+            &workspace,
+            Store::new(&shared, None),
+            &[".env.local".to_string()],
+        )
+        .unwrap();
 
         assert_eq!(result, vec![".env.local"]);
         // File exists in shared dir
@@ -217,7 +1109,12 @@ mod tests {
         fs::write(workspace.join(".vercel/project.json"), r#"{"orgId":"123"}"#).unwrap();
         fs::write(workspace.join(".vercel/README.txt"), "vercel config").unwrap();
 
-        let result = init_from_main(&workspace, &shared, &[".vercel".to_string()]).unwrap();
+        let result = init_from_main(
+            &workspace,
+            Store::new(&shared, None),
+            &[".vercel".to_string()],
+        )
+        .unwrap();
 
         assert_eq!(result, vec![".vercel"]);
         // Directory exists in shared dir with all files
@@ -238,7 +1135,12 @@ mod tests {
     fn init_from_main_skips_missing_files() {
         let (workspace, shared) = setup_temp_dirs("init-missing");
 
-        let result = init_from_main(&workspace, &shared, &["nonexistent".to_string()]).unwrap();
+        let result = init_from_main(
+            &workspace,
+            Store::new(&shared, None),
+            &["nonexistent".to_string()],
+        )
+        .unwrap();
 
         assert!(result.is_empty());
 
@@ -255,7 +1157,8 @@ mod tests {
         // Create symlink in workspace → shared dir
         std::os::unix::fs::symlink(shared.join(".env"), workspace.join(".env")).unwrap();
 
-        let result = init_from_main(&workspace, &shared, &[".env".to_string()]).unwrap();
+        let result =
+            init_from_main(&workspace, Store::new(&shared, None), &[".env".to_string()]).unwrap();
 
         assert!(result.is_empty()); // Nothing moved
 
@@ -269,7 +1172,12 @@ mod tests {
         // Create a file in shared dir
         fs::write(shared.join(".env.local"), "SECRET=xyz").unwrap();
 
-        let result = copy_to_branch(&workspace, &shared, &[".env.local".to_string()]).unwrap();
+        let result = copy_to_branch(
+            &workspace,
+            Store::new(&shared, None),
+            &[".env.local".to_string()],
+        )
+        .unwrap();
 
         assert_eq!(result, vec![".env.local"]);
         assert!(workspace.join(".env.local").exists());
@@ -292,7 +1200,12 @@ mod tests {
         fs::create_dir_all(shared.join(".vercel")).unwrap();
         fs::write(shared.join(".vercel/project.json"), r#"{"orgId":"456"}"#).unwrap();
 
-        let result = copy_to_branch(&workspace, &shared, &[".vercel".to_string()]).unwrap();
+        let result = copy_to_branch(
+            &workspace,
+            Store::new(&shared, None),
+            &[".vercel".to_string()],
+        )
+        .unwrap();
 
         assert_eq!(result, vec![".vercel"]);
         assert!(workspace.join(".vercel").is_dir());
@@ -310,7 +1223,8 @@ mod tests {
         // Create file with content "new" in shared dir
         fs::write(shared.join(".env"), "new").unwrap();
 
-        let result = copy_to_branch(&workspace, &shared, &[".env".to_string()]).unwrap();
+        let result =
+            copy_to_branch(&workspace, Store::new(&shared, None), &[".env".to_string()]).unwrap();
 
         assert_eq!(result, vec![".env"]);
         assert_eq!(fs::read_to_string(workspace.join(".env")).unwrap(), "new");
@@ -322,7 +1236,12 @@ mod tests {
     fn copy_to_branch_skips_missing_shared_files() {
         let (workspace, shared) = setup_temp_dirs("copy-missing");
 
-        let result = copy_to_branch(&workspace, &shared, &["nonexistent".to_string()]).unwrap();
+        let result = copy_to_branch(
+            &workspace,
+            Store::new(&shared, None),
+            &["nonexistent".to_string()],
+        )
+        .unwrap();
 
         assert!(result.is_empty());
 
@@ -342,11 +1261,518 @@ mod tests {
 
         fs::write(workspace.join(".env"), "VALUE=test").unwrap();
 
-        init_from_main(&workspace, &shared, &[".env".to_string()]).unwrap();
+        init_from_main(&workspace, Store::new(&shared, None), &[".env".to_string()]).unwrap();
 
         let target = fs::read_link(workspace.join(".env")).unwrap();
         assert_eq!(target, shared.join(".env"));
 
         cleanup("unix-symlink");
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn store_updates_keep_mounted_inodes() {
+        use std::os::unix::fs::MetadataExt;
+
+        let (workspace, shared) = setup_temp_dirs("in-place");
+        let ino = |path: &Path| fs::metadata(path).unwrap().ino();
+        fs::write(shared.join(".env"), "A=1").unwrap();
+        fs::create_dir_all(shared.join("certs")).unwrap();
+        fs::write(shared.join("certs/a.pem"), "old").unwrap();
+        fs::write(shared.join("certs/stale.pem"), "old").unwrap();
+        let (env_ino, pem_ino) = (ino(&shared.join(".env")), ino(&shared.join("certs/a.pem")));
+
+        fs::write(workspace.join(".env"), "A=2").unwrap();
+        fs::create_dir_all(workspace.join("certs")).unwrap();
+        fs::write(workspace.join("certs/a.pem"), "new").unwrap();
+        let store = Store::new(&shared, None);
+        store.push(&workspace, ".env").unwrap();
+        store.push(&workspace, "certs").unwrap();
+
+        assert_eq!(fs::read_to_string(shared.join(".env")).unwrap(), "A=2");
+        assert_eq!(ino(&shared.join(".env")), env_ino);
+        assert_eq!(
+            fs::read_to_string(shared.join("certs/a.pem")).unwrap(),
+            "new"
+        );
+        assert_eq!(ino(&shared.join("certs/a.pem")), pem_ino);
+        assert!(!shared.join("certs/stale.pem").exists());
+
+        cleanup("in-place");
+    }
+
+    #[test]
+    fn sync_branch_pulls_then_tracks_local_edits() {
+        let (workspace, shared) = setup_temp_dirs("sync-two-way");
+        let files = vec![".env".to_string()];
+        fs::write(shared.join(".env"), "A=1").unwrap();
+
+        // First sync pulls the file
+        let result = sync_branch(
+            &workspace,
+            Store::new(&shared, None),
+            "ws",
+            &files,
+            SyncOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result, vec![(".env".to_string(), SyncAction::Pulled)]);
+
+        // A local edit is detected and not clobbered
+        fs::write(workspace.join(".env"), "A=2").unwrap();
+        let base = SyncRecord::load(&shared, "ws");
+        assert_eq!(
+            file_status(
+                &workspace,
+                Store::new(&shared, None),
+                ".env",
+                base.files.get(".env").map(String::as_str)
+            ),
+            FileStatus::LocalChanged
+        );
+        let result = sync_branch(
+            &workspace,
+            Store::new(&shared, None),
+            "ws",
+            &files,
+            SyncOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result[0].1, SyncAction::SkippedLocal);
+        assert_eq!(fs::read_to_string(workspace.join(".env")).unwrap(), "A=2");
+
+        // Pushing sends it back to the store
+        let push = SyncOptions {
+            push: true,
+            force: false,
+        };
+        let result =
+            sync_branch(&workspace, Store::new(&shared, None), "ws", &files, push).unwrap();
+        assert_eq!(result[0].1, SyncAction::Pushed);
+        assert_eq!(fs::read_to_string(shared.join(".env")).unwrap(), "A=2");
+
+        cleanup("sync-two-way");
+    }
+
+    #[test]
+    fn sync_branch_detects_conflicts() {
+        let (workspace, shared) = setup_temp_dirs("sync-conflict");
+        let files = vec![".env".to_string()];
+        fs::write(shared.join(".env"), "A=1").unwrap();
+        sync_branch(
+            &workspace,
+            Store::new(&shared, None),
+            "ws",
+            &files,
+            SyncOptions::default(),
+        )
+        .unwrap();
+
+        fs::write(workspace.join(".env"), "A=local").unwrap();
+        fs::write(shared.join(".env"), "A=shared").unwrap();
+
+        let result = sync_branch(
+            &workspace,
+            Store::new(&shared, None),
+            "ws",
+            &files,
+            SyncOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result[0].1, SyncAction::Conflict);
+        assert_eq!(
+            fs::read_to_string(workspace.join(".env")).unwrap(),
+            "A=local"
+        );
+
+        let patch = diff(&workspace, Store::new(&shared, None), ".env").unwrap();
+        assert!(patch.contains("-A=shared"));
+        assert!(patch.contains("+A=local"));
+        // A side that doesn't exist diffs as empty
+        fs::write(shared.join(".only-shared"), "B=1").unwrap();
+        let patch = diff(&workspace, Store::new(&shared, None), ".only-shared").unwrap();
+        assert!(patch.contains("-B=1"));
+        fs::create_dir_all(workspace.join("only-local")).unwrap();
+        fs::write(workspace.join("only-local/c"), "C=1").unwrap();
+        let patch = diff(&workspace, Store::new(&shared, None), "only-local").unwrap();
+        assert!(patch.contains("+C=1"));
+
+        // Forcing without push takes the shared copy
+        let force = SyncOptions {
+            push: false,
+            force: true,
+        };
+        let result =
+            sync_branch(&workspace, Store::new(&shared, None), "ws", &files, force).unwrap();
+        assert_eq!(result[0].1, SyncAction::Pulled);
+        assert_eq!(
+            fs::read_to_string(workspace.join(".env")).unwrap(),
+            "A=shared"
+        );
+
+        cleanup("sync-conflict");
+    }
+
+    #[test]
+    fn content_hash_covers_directories() {
+        let (workspace, _) = setup_temp_dirs("hash-dir");
+        fs::create_dir_all(workspace.join(".vercel")).unwrap();
+        fs::write(workspace.join(".vercel/project.json"), "{}").unwrap();
+
+        let before = content_hash(&workspace.join(".vercel")).unwrap();
+        fs::write(workspace.join(".vercel/project.json"), "{\"a\":1}").unwrap();
+        let after = content_hash(&workspace.join(".vercel")).unwrap();
+        assert_ne!(before, after);
+        assert!(content_hash(&workspace.join("missing")).is_none());
+
+        cleanup("hash-dir");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn link_to_branch_replaces_identical_copies_only() {
+        let (workspace, shared) = setup_temp_dirs("link-branch");
+        fs::write(shared.join(".env"), "A=1").unwrap();
+        fs::write(shared.join(".env.local"), "B=1").unwrap();
+        fs::write(workspace.join(".env"), "A=1").unwrap();
+        fs::write(workspace.join(".env.local"), "B=edited").unwrap();
+
+        let files = vec![".env".to_string(), ".env.local".to_string()];
+        let result = link_to_branch(&workspace, &shared, &files).unwrap();
+        assert_eq!(
+            result,
+            vec![
+                (".env".to_string(), SyncAction::Pulled),
+                (".env.local".to_string(), SyncAction::Conflict),
+            ]
+        );
+        assert!(is_linked(&workspace, &shared, ".env"));
+        assert!(!is_linked(&workspace, &shared, ".env.local"));
+
+        // Second run sees the link
+        let result = link_to_branch(&workspace, &shared, &files[..1]).unwrap();
+        assert_eq!(result[0].1, SyncAction::Unchanged);
+
+        cleanup("link-branch");
+    }
+
+    #[test]
+    fn container_mounts_per_mode() {
+        let (_, shared) = setup_temp_dirs("mounts");
+        fs::write(shared.join(".env.local"), "A=1").unwrap();
+
+        let mut config = SharedConfig {
+            files: vec![".env.local".to_string(), ".vercel".to_string()],
+            ..Default::default()
+        };
+
+        // Copy mode: branches need no mounts; main needs its symlink target
+        assert!(container_mounts(Some(&config), &shared, false).is_empty());
+        let main = container_mounts(Some(&config), &shared, true);
+        assert_eq!(main.len(), 1);
+        assert_eq!(main[0].target, shared.display().to_string());
+
+        // Mount mode: only files present in the store are mounted, read-only
+        config.mode = SharedMode::Mount;
+        let branch = container_mounts(Some(&config), &shared, false);
+        assert_eq!(
+            branch,
+            vec![BindMount::in_workspace(
+                shared.join(".env.local"),
+                ".env.local",
+                true
+            )]
+        );
+
+        // Symlink mode: the shared dir at its own path
+        config
+            .modes
+            .insert(".vercel".to_string(), SharedMode::Symlink);
+        let branch = container_mounts(Some(&config), &shared, false);
+        assert_eq!(branch.len(), 2);
+        assert!(!branch[0].read_only);
+
+        assert!(container_mounts(None, &shared, false).is_empty());
+
+        cleanup("mounts");
+    }
+
+    #[test]
+    fn resolve_files_expands_globs_with_exclusions() {
+        let (workspace, _) = setup_temp_dirs("resolve-globs");
+        for app in ["web", "api", "legacy"] {
+            fs::create_dir_all(workspace.join("apps").join(app)).unwrap();
+            fs::write(workspace.join("apps").join(app).join(".env.local"), "A=1").unwrap();
+        }
+        fs::create_dir_all(workspace.join("packages/ui/.vercel")).unwrap();
+        fs::write(workspace.join("packages/ui/.vercel/project.json"), "{}").unwrap();
+        fs::create_dir_all(workspace.join("node_modules/pkg/.vercel")).unwrap();
+
+        let patterns = vec![
+            ".env".to_string(),
+            "apps/*/.env.local".to_string(),
+            "!apps/legacy/**".to_string(),
+            "**/.vercel".to_string(),
+        ];
+        let resolved = resolve_files(&[&workspace], &patterns).unwrap();
+        assert_eq!(
+            resolved,
+            vec![
+                ".env",
+                "apps/api/.env.local",
+                "apps/web/.env.local",
+                "packages/ui/.vercel",
+            ]
+        );
+
+        cleanup("resolve-globs");
+    }
+
+    #[test]
+    fn resolve_files_prunes_skipped_and_excluded_dirs() {
+        let (workspace, _) = setup_temp_dirs("resolve-prune");
+        for dir in [
+            "node_modules/pkg",
+            ".git/hooks",
+            "apps/web",
+            "apps/legacy/nested",
+        ] {
+            fs::create_dir_all(workspace.join(dir)).unwrap();
+            fs::write(workspace.join(dir).join(".env"), "A=1").unwrap();
+        }
+
+        // `**/*` would match the skipped directories themselves
+        let all = resolve_files(&[&workspace], &["**/*".to_string()]).unwrap();
+        assert!(all.iter().all(|f| f.starts_with("apps")), "{all:?}");
+
+        // `!dir` excludes files under it, literal ones included
+        let patterns = vec![
+            "**/.env".to_string(),
+            "apps/legacy/nested/.env".to_string(),
+            "!apps/legacy".to_string(),
+        ];
+        let resolved = resolve_files(&[&workspace], &patterns).unwrap();
+        assert_eq!(resolved, vec!["apps/web/.env"]);
+
+        cleanup("resolve-prune");
+    }
+
+    #[test]
+    fn resolve_files_single_star_stays_in_directory() {
+        let (workspace, _) = setup_temp_dirs("resolve-star");
+        fs::create_dir_all(workspace.join("apps/web/nested")).unwrap();
+        fs::write(workspace.join("apps/web/nested/.env"), "").unwrap();
+        fs::write(workspace.join("apps/web/.env"), "").unwrap();
+
+        let resolved = resolve_files(&[&workspace], &["apps/*/.env".to_string()]).unwrap();
+        assert_eq!(resolved, vec!["apps/web/.env"]);
+
+        let err = resolve_files(&[&workspace], &["apps/[/.env".to_string()]);
+        assert!(matches!(err, Err(SharedError::InvalidPattern(..))));
+
+        cleanup("resolve-star");
+    }
+
+    #[test]
+    fn copy_to_branch_resolves_globs_against_shared_dir() {
+        let (workspace, shared) = setup_temp_dirs("copy-globs");
+        fs::create_dir_all(shared.join("apps/web")).unwrap();
+        fs::write(shared.join("apps/web/.env.local"), "A=1").unwrap();
+
+        let copied = copy_to_branch(
+            &workspace,
+            Store::new(&shared, None),
+            &["apps/*/.env.local".to_string()],
+        )
+        .unwrap();
+        assert_eq!(copied, vec!["apps/web/.env.local"]);
+        assert!(workspace.join("apps/web/.env.local").exists());
+
+        cleanup("copy-globs");
+    }
+
+    #[test]
+    fn report_covers_every_mode() {
+        let (workspace, shared) = setup_temp_dirs("report");
+        fs::write(shared.join(".env"), "A=1").unwrap();
+        fs::write(shared.join(".env.local"), "B=1").unwrap();
+        fs::write(shared.join("secrets.json"), "{}").unwrap();
+
+        let mut config = SharedConfig {
+            files: vec![
+                ".env".to_string(),
+                ".env.local".to_string(),
+                "secrets.json".to_string(),
+            ],
+            ..Default::default()
+        };
+        config
+            .modes
+            .insert(".env.local".to_string(), SharedMode::Symlink);
+        config
+            .modes
+            .insert("secrets.json".to_string(), SharedMode::Mount);
+
+        let reports = report(&workspace, Store::new(&shared, None), "ws", &config).unwrap();
+        let states: Vec<_> = reports.iter().map(|r| (r.file.as_str(), r.state)).collect();
+        assert_eq!(
+            states,
+            vec![
+                (".env", "missing"),
+                (".env.local", "not linked"),
+                ("secrets.json", "mounted"),
+            ]
+        );
+
+        sync_workspace(
+            &workspace,
+            Store::new(&shared, None),
+            "ws",
+            &config,
+            SyncOptions::default(),
+        )
+        .unwrap();
+        let reports = report(&workspace, Store::new(&shared, None), "ws", &config).unwrap();
+        assert!(reports.iter().all(|r| r.clean));
+
+        cleanup("report");
+    }
+
+    #[test]
+    fn turning_on_encryption_migrates_the_store() {
+        let (workspace, shared) = setup_temp_dirs("encrypt-migrate");
+        let base = shared.parent().unwrap().to_path_buf();
+        let main = base.join("main");
+        fs::create_dir_all(main.join(".vercel")).unwrap();
+        fs::write(main.join(".env.local"), "TOKEN=abc").unwrap();
+        fs::write(main.join(".vercel/project.json"), "{\"id\":1}").unwrap();
+        let files = vec![".env.local".to_string(), ".vercel".to_string()];
+
+        // A plaintext store that main links into
+        init_from_main(&main, Store::new(&shared, None), &files).unwrap();
+        assert!(main.join(".env.local").is_symlink());
+
+        let key = crate::secrets::KeyProvider::File(base.join("secrets.key"))
+            .load()
+            .unwrap();
+        let store = Store::new(&shared, Some(&key));
+
+        // Branches refuse to sync until main has migrated the store
+        let err = sync_branch(&workspace, store, "ws", &files, SyncOptions::default());
+        assert!(matches!(err, Err(SharedError::PlaintextStore)));
+
+        init_from_main(&main, store, &files).unwrap();
+        assert!(!main.join(".env.local").is_symlink());
+        assert_eq!(
+            fs::read_to_string(main.join(".env.local")).unwrap(),
+            "TOKEN=abc"
+        );
+        assert_eq!(
+            fs::read_to_string(main.join(".vercel/project.json")).unwrap(),
+            "{\"id\":1}"
+        );
+        for file in [".env.local", ".vercel/project.json"] {
+            assert!(fs::read(shared.join(file)).unwrap().starts_with(AGE_HEADER));
+        }
+        assert_eq!(
+            store.read(".env.local").unwrap().as_deref(),
+            Some(&b"TOKEN=abc"[..])
+        );
+
+        // Pushing from main now writes ciphertext to the store only
+        sync_branch(&main, store, "main", &files, SyncOptions::default()).unwrap();
+        fs::write(main.join(".env.local"), "TOKEN=xyz").unwrap();
+        let push = SyncOptions {
+            push: true,
+            force: false,
+        };
+        sync_branch(&main, store, "main", &files, push).unwrap();
+        assert_eq!(
+            fs::read_to_string(main.join(".env.local")).unwrap(),
+            "TOKEN=xyz"
+        );
+        assert_eq!(
+            store.read(".env.local").unwrap().as_deref(),
+            Some(&b"TOKEN=xyz"[..])
+        );
+
+        // Turning encryption off again is refused rather than leaking ciphertext
+        let err = sync_branch(&workspace, Store::new(&shared, None), "ws", &files, push);
+        assert!(matches!(err, Err(SharedError::EncryptedStore)));
+
+        cleanup("encrypt-migrate");
+    }
+
+    #[test]
+    fn encrypted_store_holds_ciphertext_and_syncs_plaintext() {
+        let (workspace, shared) = setup_temp_dirs("encrypted");
+        let base = shared.parent().unwrap().to_path_buf();
+        let main = base.join("main");
+        fs::create_dir_all(main.join(".vercel")).unwrap();
+        fs::write(main.join(".env.local"), "TOKEN=abc").unwrap();
+        fs::write(main.join(".vercel/project.json"), "{\"id\":1}").unwrap();
+
+        let key = crate::secrets::KeyProvider::File(base.join("secrets.key"))
+            .load()
+            .unwrap();
+        let store = Store::new(&shared, Some(&key));
+        let files = vec![".env.local".to_string(), ".vercel".to_string()];
+
+        // Main keeps its plaintext files; the store gets encrypted copies
+        let stored = init_from_main(&main, store, &files).unwrap();
+        assert_eq!(stored, vec![".env.local", ".vercel"]);
+        assert!(!main.join(".env.local").is_symlink());
+        let raw = fs::read(shared.join(".env.local")).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("TOKEN"));
+        assert_eq!(
+            store.hash(".env.local"),
+            content_hash(&main.join(".env.local"))
+        );
+
+        // Branches get decrypted copies
+        let config = SharedConfig {
+            files: files.clone(),
+            mode: SharedMode::Symlink,
+            encrypted: true,
+            ..Default::default()
+        };
+        sync_workspace(&workspace, store, "ws", &config, SyncOptions::default()).unwrap();
+        assert_eq!(
+            fs::read_to_string(workspace.join(".env.local")).unwrap(),
+            "TOKEN=abc"
+        );
+        assert_eq!(
+            fs::read_to_string(workspace.join(".vercel/project.json")).unwrap(),
+            "{\"id\":1}"
+        );
+        assert!(
+            report(&workspace, store, "ws", &config)
+                .unwrap()
+                .iter()
+                .all(|r| r.clean)
+        );
+
+        // Pushed edits are encrypted too, and diffs show plaintext
+        fs::write(workspace.join(".env.local"), "TOKEN=xyz").unwrap();
+        let patch = diff(&workspace, store, ".env.local").unwrap();
+        assert!(patch.contains("-TOKEN=abc"));
+        assert!(patch.contains("+TOKEN=xyz"));
+
+        let push = SyncOptions {
+            push: true,
+            force: false,
+        };
+        sync_workspace(&workspace, store, "ws", &config, push).unwrap();
+        let raw = fs::read(shared.join(".env.local")).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("xyz"));
+        assert_eq!(
+            store.hash(".env.local"),
+            content_hash(&workspace.join(".env.local"))
+        );
+
+        // Nothing is ever mounted from an encrypted store
+        assert!(container_mounts(Some(&config), &shared, true).is_empty());
+
+        cleanup("encrypted");
+    }
 }
@@ -94,10 +94,7 @@ pub fn write_rc_file(
     container_name: &str,
     extra_commands: &[String],
 ) -> Result<std::path::PathBuf, std::io::Error> {
-    let rc_dir = dirs::config_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from(".config"))
-        .join("dual")
-        .join("rc");
+    let rc_dir = rc_dir();
     std::fs::create_dir_all(&rc_dir)?;
 
     let rc_path = rc_dir.join(format!("{container_name}.sh"));
@@ -107,6 +104,23 @@ pub fn write_rc_file(
     Ok(rc_path)
 }
 
+/// Remove the RC file for a container, if present.
+pub fn remove_rc_file(container_name: &str) -> Result<(), std::io::Error> {
+    let rc_path = rc_dir().join(format!("{container_name}.sh"));
+    match std::fs::remove_file(rc_path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Directory holding generated RC files: ~/.config/dual/rc/
+fn rc_dir() -> std::path::PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from(".config"))
+        .join("dual")
+        .join("rc")
+}
+
 /// Get the source command for an RC file path.
 pub fn source_file_command(rc_path: &std::path::Path) -> String {
     format!("source {}", rc_path.display())
@@ -225,6 +239,15 @@ mod tests {
         let _ = std::fs::remove_file(&path);
     }
 
+    #[test]
+    fn remove_rc_file_deletes_and_tolerates_missing() {
+        let path = write_rc_file("dual-test-remove-rc", &[]).unwrap();
+        remove_rc_file("dual-test-remove-rc").unwrap();
+        assert!(!path.exists());
+        // Removing again is not an error
+        remove_rc_file("dual-test-remove-rc").unwrap();
+    }
+
     #[test]
     fn source_file_command_format() {
         let path = std::path::Path::new("/home/user/.config/dual/rc/dual-test.sh");
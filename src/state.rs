@@ -1,5 +1,6 @@
 use fs2::FileExt;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -11,16 +12,69 @@ const STATE_DIR: &str = ".dual";
 const STATE_FILENAME: &str = "workspaces.toml";
 const DEFAULT_WORKSPACE_ROOT: &str = ".dual/workspaces";
 
+/// Lowest host port handed out for `tcp_ports`; port P is offered 15000 + P first.
+const TCP_PORT_BASE: u16 = 15000;
+
+/// Highest host port handed out for `tcp_ports`, just below the ephemeral
+/// range (32768+) the OS picks outgoing connection ports from.
+const TCP_PORT_MAX: u16 = 32767;
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct WorkspaceState {
     /// Root directory for all workspace clones (default: ~/.dual/workspaces)
     pub workspace_root: Option<String>,
 
+    /// Default container resource limits, overridden per repo by `[resources]` in .dual.toml
+    #[serde(default, skip_serializing_if = "config::Resources::is_empty")]
+    pub resources: config::Resources,
+
+    /// Repos whose `.dual.toml` hooks may run on the host (`target = "host"`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub host_hooks: Vec<String>,
+
+    /// Reverse proxy settings
+    #[serde(default, skip_serializing_if = "ProxyConfig::is_default")]
+    pub proxy: ProxyConfig,
+
+    /// Host ports allocated for `tcp_ports`: workspace id → (container port → host port)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tcp_ports: BTreeMap<String, BTreeMap<String, u16>>,
+
     /// Active workspace entries
     #[serde(default)]
     pub workspaces: Vec<WorkspaceEntry>,
 }
 
+/// Reverse proxy settings: `[proxy]` in ~/.dual/workspaces.toml
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ProxyConfig {
+    /// Also accept HTTPS on every proxied port, with certificates from a
+    /// local CA in ~/.dual/certs
+    #[serde(default)]
+    pub https: bool,
+
+    /// Serve every workspace port on this one port, routing
+    /// `{port}.{workspace}.localhost` to the container's port
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+
+    /// Start a stopped workspace when a request arrives for it, holding the
+    /// request until its port accepts connections
+    #[serde(default)]
+    pub wake: bool,
+
+    /// Keep recent requests in memory, served as JSON at
+    /// `localhost:{port}/_dual/requests`
+    #[serde(default)]
+    pub inspect: bool,
+}
+
+impl ProxyConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct WorkspaceEntry {
     /// Short repo name (e.g. "lightfast")
@@ -43,6 +97,10 @@ impl WorkspaceState {
     pub fn new() -> Self {
         Self {
             workspace_root: None,
+            resources: config::Resources::default(),
+            host_hooks: Vec::new(),
+            proxy: ProxyConfig::default(),
+            tcp_ports: BTreeMap::new(),
             workspaces: Vec::new(),
         }
     }
@@ -97,12 +155,80 @@ impl WorkspaceState {
 
     /// Remove a workspace entry by repo + branch. Returns true if found.
     pub fn remove_workspace(&mut self, repo: &str, branch: &str) -> bool {
+        self.tcp_ports.remove(&config::workspace_id(repo, branch));
         let before = self.workspaces.len();
         self.workspaces
             .retain(|ws| !(ws.repo == repo && ws.branch == branch));
         self.workspaces.len() < before
     }
 
+    /// Change the branch of a workspace entry. Returns Err if the target already exists.
+    pub fn rename_workspace(
+        &mut self,
+        repo: &str,
+        branch: &str,
+        new_branch: &str,
+    ) -> Result<(), StateError> {
+        if self.has_workspace(repo, new_branch) {
+            return Err(StateError::DuplicateWorkspace(
+                repo.to_string(),
+                new_branch.to_string(),
+            ));
+        }
+        let entry = self
+            .workspaces
+            .iter_mut()
+            .find(|ws| ws.repo == repo && ws.branch == branch)
+            .ok_or_else(|| StateError::UnknownWorkspace(repo.to_string(), branch.to_string()))?;
+        entry.branch = new_branch.to_string();
+        if let Some(ports) = self.tcp_ports.remove(&config::workspace_id(repo, branch)) {
+            self.tcp_ports
+                .insert(config::workspace_id(repo, new_branch), ports);
+        }
+        Ok(())
+    }
+
+    /// The host port allocated to a workspace's container port, if any.
+    pub fn tcp_port(&self, workspace_id: &str, port: u16) -> Option<u16> {
+        self.tcp_ports
+            .get(workspace_id)
+            .and_then(|ports| ports.get(&port.to_string()))
+            .copied()
+    }
+
+    /// Allocate a host port for a workspace's container port, keeping an
+    /// existing allocation. New ones skip ports allocated to any workspace and
+    /// those `is_free` rejects (e.g. already bound by another program).
+    /// Returns None when every port from 15000 to 32767 is taken.
+    pub fn allocate_tcp_port(
+        &mut self,
+        workspace_id: &str,
+        port: u16,
+        is_free: impl Fn(u16) -> bool,
+    ) -> Option<u16> {
+        if let Some(host_port) = self.tcp_port(workspace_id, port) {
+            return Some(host_port);
+        }
+
+        let span = u32::from(TCP_PORT_MAX - TCP_PORT_BASE) + 1;
+        let first = u32::from(port) % span;
+        let host_port = (0..span)
+            .map(|i| TCP_PORT_BASE + ((first + i) % span) as u16)
+            .find(|candidate| {
+                !self
+                    .tcp_ports
+                    .values()
+                    .any(|ports| ports.values().any(|p| p == candidate))
+                    && is_free(*candidate)
+            })?;
+
+        self.tcp_ports
+            .entry(workspace_id.to_string())
+            .or_default()
+            .insert(port.to_string(), host_port);
+        Some(host_port)
+    }
+
     /// Find all workspaces for a given repo name.
     pub fn workspaces_for_repo(&self, repo: &str) -> Vec<&WorkspaceEntry> {
         self.workspaces
@@ -287,6 +413,9 @@ pub enum StateError {
 
     #[error("Workspace {0}/{1} already exists")]
     DuplicateWorkspace(String, String),
+
+    #[error("Workspace {0}/{1} does not exist")]
+    UnknownWorkspace(String, String),
 }
 
 #[cfg(test)]
@@ -344,6 +473,22 @@ path = "/local/path/to/agent-os"
         assert_eq!(state.workspaces[2].repo, "agent-os");
     }
 
+    #[test]
+    fn parse_proxy_settings() {
+        let state =
+            parse("[proxy]\nhttps = true\nport = 8000\nwake = true\ninspect = true\n").unwrap();
+        assert!(state.proxy.https);
+        assert_eq!(state.proxy.port, Some(8000));
+        assert!(state.proxy.wake);
+        assert!(state.proxy.inspect);
+
+        // Defaults are left out of the file
+        let state = parse("").unwrap();
+        assert!(!state.proxy.https);
+        assert!(!state.proxy.wake);
+        assert!(!toml::to_string_pretty(&state).unwrap().contains("[proxy]"));
+    }
+
     #[test]
     fn serialize_deserialize_roundtrip() {
         let mut state = WorkspaceState::new();
@@ -430,6 +575,80 @@ path = "/local/path/to/agent-os"
         assert_eq!(state.workspaces[0].branch, "feat/auth");
     }
 
+    #[test]
+    fn rename_workspace() {
+        let mut state = WorkspaceState::new();
+        for branch in ["main", "feat/auth"] {
+            state
+                .add_workspace(WorkspaceEntry {
+                    repo: "lightfast".to_string(),
+                    url: "url".to_string(),
+                    branch: branch.to_string(),
+                    path: None,
+                })
+                .unwrap();
+        }
+
+        state
+            .rename_workspace("lightfast", "feat/auth", "feat/login")
+            .unwrap();
+        assert!(state.has_workspace("lightfast", "feat/login"));
+        assert!(!state.has_workspace("lightfast", "feat/auth"));
+
+        // Renaming onto an existing branch is rejected
+        assert!(matches!(
+            state.rename_workspace("lightfast", "feat/login", "main"),
+            Err(StateError::DuplicateWorkspace(..))
+        ));
+        assert!(matches!(
+            state.rename_workspace("lightfast", "nope", "other"),
+            Err(StateError::UnknownWorkspace(..))
+        ));
+    }
+
+    #[test]
+    fn tcp_ports_are_stable_and_unique() {
+        let mut state = WorkspaceState::new();
+        for branch in ["main", "feat/auth"] {
+            state
+                .add_workspace(WorkspaceEntry {
+                    repo: "lightfast".to_string(),
+                    url: "url".to_string(),
+                    branch: branch.to_string(),
+                    path: None,
+                })
+                .unwrap();
+        }
+
+        let main = state.allocate_tcp_port("lightfast-main", 5432, |_| true);
+        assert_eq!(main, Some(20432));
+        // The next workspace gets the next free port; a port in use is skipped
+        let auth_id = config::workspace_id("lightfast", "feat/auth");
+        let auth = state.allocate_tcp_port(&auth_id, 5432, |p| p != 20433);
+        assert_eq!(auth, Some(20434));
+        // Existing allocations are kept
+        assert_eq!(
+            state.allocate_tcp_port("lightfast-main", 5432, |_| false),
+            Some(20432)
+        );
+
+        // Survives a save and follows renames; removed with the workspace
+        let state_str = toml::to_string_pretty(&state).unwrap();
+        let mut state = parse(&state_str).unwrap();
+        state
+            .rename_workspace("lightfast", "main", "trunk")
+            .unwrap();
+        assert_eq!(state.tcp_port("lightfast-trunk", 5432), Some(20432));
+        assert_eq!(state.tcp_port("lightfast-main", 5432), None);
+        state.remove_workspace("lightfast", "trunk");
+        assert!(!state.tcp_ports.contains_key("lightfast-trunk"));
+
+        // Ports past the range wrap around, staying out of the ephemeral range
+        let high = state.allocate_tcp_port("lightfast-main", 65535, |_| true);
+        assert_eq!(high, Some(15000 + 65535 % 17768));
+        assert!(high.unwrap() < 32768);
+    }
+
     #[test]
     fn remove_nonexistent_workspace() {
         let mut state = WorkspaceState::new();
@@ -460,7 +679,9 @@ path = "/local/path/to/agent-os"
         assert_eq!(ws.repo, "lightfast");
         assert_eq!(ws.branch, "main");
 
-        let ws = state.resolve_workspace("lightfast-feat__auth").unwrap();
+        let ws = state
+            .resolve_workspace(&config::workspace_id("lightfast", "feat/auth"))
+            .unwrap();
         assert_eq!(ws.repo, "lightfast");
         assert_eq!(ws.branch, "feat/auth");
     }
@@ -588,7 +809,10 @@ path = "/local/path/to/agent-os"
             .unwrap();
 
         let dir = state.workspace_dir(&state.workspaces[0]);
-        assert_eq!(dir, PathBuf::from("/tmp/ws/lightfast/feat__auth"));
+        assert_eq!(
+            dir,
+            PathBuf::from("/tmp/ws/lightfast").join(config::encode_branch("feat/auth"))
+        );
     }
 
     #[test]
@@ -0,0 +1,477 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use rcgen::{
+    BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, GeneralSubtree, IsCa,
+    Issuer, KeyPair, KeyUsagePurpose, NameConstraints,
+};
+use time::{Duration, OffsetDateTime};
+use tokio_rustls::rustls::crypto::ring::sign::any_supported_type;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use tracing::{debug, info, warn};
+
+const CA_CERT_FILENAME: &str = "ca.pem";
+const CA_KEY_FILENAME: &str = "ca-key.pem";
+const INDEX_FILENAME: &str = "certs.toml";
+
+/// Key of the CA in the index; leaves are keyed by workspace id.
+const CA_ENTRY: &str = "ca";
+
+const CA_VALIDITY_DAYS: i64 = 3650;
+const LEAF_VALIDITY_DAYS: i64 = 90;
+
+/// Certificates expiring within this many days are replaced.
+const RENEW_BEFORE_DAYS: i64 = 30;
+
+/// Expiry of every certificate in the store: ~/.dual/certs/certs.toml
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+struct CertIndex {
+    /// Certificate name → not-after time (seconds since the Unix epoch)
+    #[serde(default)]
+    expires: BTreeMap<String, i64>,
+}
+
+/// A local CA and the workspace certificates it issues, cached on disk.
+///
+/// The CA may only sign names under `localhost`. Each workspace gets a leaf
+/// for `{workspace}.localhost` and `*.{workspace}.localhost`, issued on first
+/// use and replaced, like the CA, once it is within 30 days of expiring.
+/// Handshakes only get leaves for the workspaces set with `with_workspaces`.
+pub struct CertStore {
+    dir: PathBuf,
+    issuer: Issuer<'static, KeyPair>,
+    ca_expires: i64,
+    workspaces: HashSet<String>,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    index: CertIndex,
+    leaves: HashMap<String, (i64, Arc<CertifiedKey>)>,
+}
+
+impl CertStore {
+    /// Open the store in `dir`, creating the CA if it is missing or expiring.
+    ///
+    /// Also returns whether a new CA was created, which has to be trusted again.
+    pub fn open(dir: &Path) -> Result<(Self, bool), TlsError> {
+        std::fs::create_dir_all(dir).map_err(|e| TlsError::Filesystem(dir.to_path_buf(), e))?;
+        let index_path = dir.join(INDEX_FILENAME);
+        let mut index = load_index(&index_path)?;
+
+        let ca_cert = dir.join(CA_CERT_FILENAME);
+        let ca_key = dir.join(CA_KEY_FILENAME);
+        let current =
+            index.expires.get(CA_ENTRY).copied().filter(|&expires| {
+                !due_for_renewal(expires) && ca_cert.exists() && ca_key.exists()
+            });
+
+        let (issuer, ca_expires, created) = match current {
+            Some(expires) => {
+                let key = KeyPair::from_pem(&read(&ca_key)?).map_err(TlsError::Generate)?;
+                let issuer =
+                    Issuer::from_ca_cert_pem(&read(&ca_cert)?, key).map_err(TlsError::Generate)?;
+                (issuer, expires, false)
+            }
+            None => {
+                let key = KeyPair::generate().map_err(TlsError::Generate)?;
+                let params = ca_params();
+                let expires = params.not_after.unix_timestamp();
+                let cert = params.self_signed(&key).map_err(TlsError::Generate)?;
+                write_private(&ca_key, key.serialize_pem().as_bytes())?;
+                write(&ca_cert, cert.pem().as_bytes())?;
+
+                // Leaves of the old CA are no longer trusted
+                for name in std::mem::take(&mut index.expires).into_keys() {
+                    if name != CA_ENTRY {
+                        let (cert, key) = leaf_paths(dir, &name);
+                        let _ = std::fs::remove_file(cert);
+                        let _ = std::fs::remove_file(key);
+                    }
+                }
+                index.expires.insert(CA_ENTRY.to_string(), expires);
+                save_index(&index_path, &index)?;
+                (Issuer::new(params, key), expires, true)
+            }
+        };
+
+        let store = CertStore {
+            dir: dir.to_path_buf(),
+            issuer,
+            ca_expires,
+            workspaces: HashSet::new(),
+            inner: Mutex::new(Inner {
+                index,
+                leaves: HashMap::new(),
+            }),
+        };
+        Ok((store, created))
+    }
+
+    /// Path of the CA certificate, for users to add to their trust store.
+    pub fn ca_path(&self) -> PathBuf {
+        self.dir.join(CA_CERT_FILENAME)
+    }
+
+    /// Serve handshakes for these workspace ids only, so arbitrary names under
+    /// `.localhost` can't make the proxy issue (and store) certificates.
+    pub fn with_workspaces(mut self, workspace_ids: impl IntoIterator<Item = String>) -> Self {
+        self.workspaces = workspace_ids.into_iter().collect();
+        self
+    }
+
+    /// Whether the CA itself needs replacing, which takes a restart of the proxy.
+    pub fn ca_due_for_renewal(&self) -> bool {
+        due_for_renewal(self.ca_expires)
+    }
+
+    /// The certificate for a workspace: cached, read from disk, or issued now.
+    pub fn leaf(&self, workspace_id: &str) -> Result<Arc<CertifiedKey>, TlsError> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((expires, key)) = inner.leaves.get(workspace_id)
+            && !due_for_renewal(*expires)
+        {
+            return Ok(Arc::clone(key));
+        }
+
+        let (cert_path, key_path) = leaf_paths(&self.dir, workspace_id);
+        let on_disk = inner
+            .index
+            .expires
+            .get(workspace_id)
+            .copied()
+            .filter(|&expires| !due_for_renewal(expires) && cert_path.exists());
+        let (expires, key) = match on_disk {
+            Some(expires) => {
+                let cert = CertificateDer::from_pem_file(&cert_path)
+                    .map_err(|e| TlsError::Pem(cert_path.clone(), e.to_string()))?;
+                let key = PrivateKeyDer::from_pem_file(&key_path)
+                    .map_err(|e| TlsError::Pem(key_path.clone(), e.to_string()))?;
+                (expires, certified_key(cert, &key)?)
+            }
+            None => self.issue(workspace_id, &cert_path, &key_path, &mut inner)?,
+        };
+
+        let key = Arc::new(key);
+        inner
+            .leaves
+            .insert(workspace_id.to_string(), (expires, Arc::clone(&key)));
+        Ok(key)
+    }
+
+    /// Issue and save a workspace's leaf certificate.
+    fn issue(
+        &self,
+        workspace_id: &str,
+        cert_path: &Path,
+        key_path: &Path,
+        inner: &mut Inner,
+    ) -> Result<(i64, CertifiedKey), TlsError> {
+        let key = KeyPair::generate().map_err(TlsError::Generate)?;
+        let params = leaf_params(workspace_id)?;
+        let expires = params.not_after.unix_timestamp();
+        let cert = params
+            .signed_by(&key, &self.issuer)
+            .map_err(TlsError::Generate)?;
+        write_private(key_path, key.serialize_pem().as_bytes())?;
+        write(cert_path, cert.pem().as_bytes())?;
+
+        inner
+            .index
+            .expires
+            .insert(workspace_id.to_string(), expires);
+        save_index(&self.dir.join(INDEX_FILENAME), &inner.index)?;
+        info!("Issued certificate for {workspace_id}.localhost");
+
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der()));
+        Ok((expires, certified_key(cert.der().clone(), &key)?))
+    }
+}
+
+impl std::fmt::Debug for CertStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CertStore")
+            .field("dir", &self.dir)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ResolvesServerCert for CertStore {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let workspace_id = workspace_from_host(client_hello.server_name()?)?;
+        if !self.workspaces.contains(workspace_id) {
+            debug!("no certificate for unknown workspace {workspace_id}");
+            return None;
+        }
+        match self.leaf(workspace_id) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                warn!("no certificate for {workspace_id}: {e}");
+                None
+            }
+        }
+    }
+}
+
+/// The workspace a TLS server name belongs to: the label just before
+/// `.localhost` ("3000.app-main.localhost" → "app-main").
+pub fn workspace_from_host(host: &str) -> Option<&str> {
+    let workspace_id = host.strip_suffix(".localhost")?.rsplit('.').next()?;
+    let valid = !workspace_id.is_empty()
+        && workspace_id
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+    valid.then_some(workspace_id)
+}
+
+fn due_for_renewal(expires: i64) -> bool {
+    expires <= (OffsetDateTime::now_utc() + Duration::days(RENEW_BEFORE_DAYS)).unix_timestamp()
+}
+
+fn ca_params() -> CertificateParams {
+    let now = OffsetDateTime::now_utc();
+    let mut params = CertificateParams::default();
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "dual local development CA");
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params.key_usages = vec![
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+        KeyUsagePurpose::DigitalSignature,
+    ];
+    // Trusting this CA must not let it vouch for real domains
+    params.name_constraints = Some(NameConstraints {
+        permitted_subtrees: vec![GeneralSubtree::DnsName("localhost".to_string())],
+        excluded_subtrees: Vec::new(),
+    });
+    params.not_before = now - Duration::days(1);
+    params.not_after = now + Duration::days(CA_VALIDITY_DAYS);
+    params
+}
+
+fn leaf_params(workspace_id: &str) -> Result<CertificateParams, TlsError> {
+    let now = OffsetDateTime::now_utc();
+    let host = format!("{workspace_id}.localhost");
+    let mut params = CertificateParams::new(vec![host.clone(), format!("*.{host}")])
+        .map_err(TlsError::Generate)?;
+    params.distinguished_name.push(DnType::CommonName, host);
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+    params.use_authority_key_identifier_extension = true;
+    params.not_before = now - Duration::days(1);
+    params.not_after = now + Duration::days(LEAF_VALIDITY_DAYS);
+    Ok(params)
+}
+
+fn certified_key(
+    cert: CertificateDer<'static>,
+    key: &PrivateKeyDer<'static>,
+) -> Result<CertifiedKey, TlsError> {
+    let signing_key = any_supported_type(key).map_err(|e| TlsError::Key(e.to_string()))?;
+    Ok(CertifiedKey::new(vec![cert], signing_key))
+}
+
+/// Paths of a workspace's certificate and key: {workspace}.pem, {workspace}-key.pem
+fn leaf_paths(dir: &Path, workspace_id: &str) -> (PathBuf, PathBuf) {
+    (
+        dir.join(format!("{workspace_id}.pem")),
+        dir.join(format!("{workspace_id}-key.pem")),
+    )
+}
+
+fn load_index(path: &Path) -> Result<CertIndex, TlsError> {
+    if !path.exists() {
+        return Ok(CertIndex::default());
+    }
+    toml::from_str(&read(path)?).map_err(|e| TlsError::Parse(path.to_path_buf(), e))
+}
+
+fn save_index(path: &Path, index: &CertIndex) -> Result<(), TlsError> {
+    let contents = toml::to_string_pretty(index).map_err(TlsError::Serialize)?;
+    write(path, contents.as_bytes())
+}
+
+fn read(path: &Path) -> Result<String, TlsError> {
+    std::fs::read_to_string(path).map_err(|e| TlsError::Filesystem(path.to_path_buf(), e))
+}
+
+fn write(path: &Path, contents: &[u8]) -> Result<(), TlsError> {
+    std::fs::write(path, contents).map_err(|e| TlsError::Filesystem(path.to_path_buf(), e))
+}
+
+/// Write a file readable only by the owner (private keys).
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), TlsError> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    use std::io::Write;
+    options
+        .open(path)
+        .and_then(|mut f| f.write_all(contents))
+        .map_err(|e| TlsError::Filesystem(path.to_path_buf(), e))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsError {
+    #[error("Filesystem error at {path}: {err}", path = .0.display(), err = .1)]
+    Filesystem(PathBuf, std::io::Error),
+
+    #[error("Failed to parse certificate index {path}: {err}", path = .0.display(), err = .1)]
+    Parse(PathBuf, toml::de::Error),
+
+    #[error("Failed to serialize certificate index: {0}")]
+    Serialize(toml::ser::Error),
+
+    #[error("Invalid PEM in {path}: {err}", path = .0.display(), err = .1)]
+    Pem(PathBuf, String),
+
+    #[error("Certificate generation failed: {0}")]
+    Generate(rcgen::Error),
+
+    #[error("Unusable private key: {0}")]
+    Key(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_dir(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dual-test-tls-{test_name}"));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn workspace_is_the_label_before_localhost() {
+        assert_eq!(
+            workspace_from_host("lightfast-main.localhost"),
+            Some("lightfast-main")
+        );
+        assert_eq!(
+            workspace_from_host("3000.lightfast-main.localhost"),
+            Some("lightfast-main")
+        );
+        assert_eq!(workspace_from_host("localhost"), None);
+        assert_eq!(workspace_from_host("example.com"), None);
+        assert_eq!(workspace_from_host("Evil_Name.localhost"), None);
+    }
+
+    #[test]
+    fn ca_and_leaves_are_cached() {
+        let dir = temp_dir("cache");
+
+        let (store, created) = CertStore::open(&dir).unwrap();
+        assert!(created);
+        assert!(store.ca_path().exists());
+        assert!(!store.ca_due_for_renewal());
+
+        store.leaf("app-main").unwrap();
+        let leaf = fs::read_to_string(dir.join("app-main.pem")).unwrap();
+
+        // Reopening keeps the CA and the issued leaf
+        let ca = fs::read_to_string(store.ca_path()).unwrap();
+        let (store, created) = CertStore::open(&dir).unwrap();
+        assert!(!created);
+        assert_eq!(fs::read_to_string(store.ca_path()).unwrap(), ca);
+        store.leaf("app-main").unwrap();
+        assert_eq!(fs::read_to_string(dir.join("app-main.pem")).unwrap(), leaf);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn expiring_certificates_are_rotated() {
+        let dir = temp_dir("rotate");
+        let (store, _) = CertStore::open(&dir).unwrap();
+        store.leaf("app-main").unwrap();
+        let leaf = fs::read_to_string(dir.join("app-main.pem")).unwrap();
+        drop(store);
+
+        // A leaf close to expiry is reissued
+        let index_path = dir.join(INDEX_FILENAME);
+        let mut index = load_index(&index_path).unwrap();
+        let soon = (OffsetDateTime::now_utc() + Duration::days(5)).unix_timestamp();
+        index.expires.insert("app-main".to_string(), soon);
+        save_index(&index_path, &index).unwrap();
+        let (store, created) = CertStore::open(&dir).unwrap();
+        assert!(!created);
+        store.leaf("app-main").unwrap();
+        assert_ne!(fs::read_to_string(dir.join("app-main.pem")).unwrap(), leaf);
+        drop(store);
+
+        // A CA close to expiry is replaced, and its leaves dropped
+        let mut index = load_index(&index_path).unwrap();
+        index.expires.insert(CA_ENTRY.to_string(), soon);
+        save_index(&index_path, &index).unwrap();
+        let (_, created) = CertStore::open(&dir).unwrap();
+        assert!(created);
+        assert!(!dir.join("app-main.pem").exists());
+        let index = load_index(&index_path).unwrap();
+        assert_eq!(index.expires.len(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn leaves_verify_against_the_ca() {
+        use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+        use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+        let dir = temp_dir("handshake");
+        let (store, _) = CertStore::open(&dir).unwrap();
+        let store = store.with_workspaces(["app-main".to_string()]);
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(CertificateDer::from_pem_file(store.ca_path()).unwrap())
+            .unwrap();
+
+        let provider = Arc::new(tokio_rustls::rustls::crypto::ring::default_provider());
+        let server = ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::new(store));
+        let client = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(server));
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let _ = acceptor.accept(stream).await;
+            }
+        });
+
+        let connector = TlsConnector::from(Arc::new(client));
+        for host in ["app-main.localhost", "3000.app-main.localhost"] {
+            let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            let name = host.to_string().try_into().unwrap();
+            connector.connect(name, stream).await.unwrap();
+        }
+
+        // Names of workspaces the proxy doesn't serve get no certificate
+        let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let name = "other-main.localhost".to_string().try_into().unwrap();
+        assert!(connector.connect(name, stream).await.is_err());
+        assert!(!dir.join("other-main.pem").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -3,8 +3,8 @@ use std::process::Command;
 
 use crate::backend::{BackendError, MultiplexerBackend};
 
-/// Session name prefix for dual-managed sessions.
-const SESSION_PREFIX: &str = "dual-";
+/// Session option holding the workspace id of a dual-managed session.
+const WORKSPACE_OPTION: &str = "@dual_workspace_id";
 
 /// Terminal multiplexer backend using tmux.
 pub struct TmuxBackend;
@@ -97,6 +97,10 @@ impl MultiplexerBackend for TmuxBackend {
         tmux_simple(&["kill-session", "-t", session_name])
     }
 
+    fn rename_session(&self, session_name: &str, new_name: &str) -> Result<(), BackendError> {
+        tmux_simple(&["rename-session", "-t", session_name, new_name])
+    }
+
     fn is_alive(&self, session_name: &str) -> bool {
         Command::new("tmux")
             .args(["has-session", "-t", session_name])
@@ -104,19 +108,25 @@ impl MultiplexerBackend for TmuxBackend {
             .is_ok_and(|o| o.status.success())
     }
 
-    fn list_sessions(&self) -> Vec<String> {
+    fn tag_session(&self, session_name: &str, workspace_id: &str) -> Result<(), BackendError> {
+        tmux_simple(&[
+            "set-option",
+            "-t",
+            session_name,
+            WORKSPACE_OPTION,
+            workspace_id,
+        ])
+    }
+
+    fn list_sessions(&self) -> Vec<(String, String)> {
+        let format = format!("#{{session_name}}\t#{{{WORKSPACE_OPTION}}}");
         let output = Command::new("tmux")
-            .args(["list-sessions", "-F", "#{session_name}"])
+            .args(["list-sessions", "-F", &format])
             .output();
 
         match output {
             Ok(out) if out.status.success() => {
-                let stdout = String::from_utf8_lossy(&out.stdout);
-                stdout
-                    .lines()
-                    .filter(|line| line.starts_with(SESSION_PREFIX))
-                    .map(|s| s.to_string())
-                    .collect()
+                parse_tagged_sessions(&String::from_utf8_lossy(&out.stdout))
             }
             _ => Vec::new(),
         }
@@ -143,6 +153,17 @@ pub fn build_new_session_args(session_name: &str, cwd: &Path) -> Vec<String> {
     ]
 }
 
+/// Parse `list-sessions` output ("name\tworkspace id"), keeping tagged sessions.
+fn parse_tagged_sessions(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, id) = line.split_once('\t')?;
+            (!id.is_empty()).then(|| (name.to_string(), id.to_string()))
+        })
+        .collect()
+}
+
 fn tmux_simple(args: &[&str]) -> Result<(), BackendError> {
     let output = Command::new("tmux")
         .args(args)
@@ -195,8 +216,15 @@ mod tests {
     }
 
     #[test]
-    fn session_prefix_is_dual() {
-        assert_eq!(SESSION_PREFIX, "dual-");
+    fn only_tagged_sessions_are_listed() {
+        let output = "dual-app-main\tapp-main\ndual-test-1234\t\nscratch\t\nwork\tapp-feat\n";
+        assert_eq!(
+            parse_tagged_sessions(output),
+            vec![
+                ("dual-app-main".to_string(), "app-main".to_string()),
+                ("work".to_string(), "app-feat".to_string()),
+            ]
+        );
     }
 
     #[test]
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use crate::backend::MultiplexerBackend;
 use crate::clone;
 use crate::config;
@@ -49,6 +51,12 @@ impl App {
     /// Build app state from workspace state and live backend status.
     pub fn new(state: &WorkspaceState, backend: &dyn MultiplexerBackend) -> Self {
         let workspace_root = state.workspace_root();
+        // Sessions tagged with their workspace are found even if renamed
+        let tagged: HashSet<String> = backend
+            .list_sessions()
+            .into_iter()
+            .map(|(_, workspace_id)| workspace_id)
+            .collect();
 
         // Group workspaces by repo (preserve insertion order)
         let mut repo_names: Vec<String> = Vec::new();
@@ -75,21 +83,23 @@ impl App {
                             clone::workspace_exists(&workspace_root, &ws.repo, &ws.branch)
                         };
 
-                        let status = if backend.is_alive(&session_name) {
-                            WorkspaceStatus::Running
-                        } else if matches!(
-                            container::status(&container_name),
-                            container::ContainerStatus::Stopped
-                        ) || clone_exists
-                        {
-                            WorkspaceStatus::Stopped
-                        } else {
-                            WorkspaceStatus::Lazy
-                        };
+                        let workspace_id = config::workspace_id(&ws.repo, &ws.branch);
+                        let status =
+                            if tagged.contains(&workspace_id) || backend.is_alive(&session_name) {
+                                WorkspaceStatus::Running
+                            } else if matches!(
+                                container::status(&container_name),
+                                container::ContainerStatus::Stopped
+                            ) || clone_exists
+                            {
+                                WorkspaceStatus::Stopped
+                            } else {
+                                WorkspaceStatus::Lazy
+                            };
 
                         WorkspaceItem {
                             branch: ws.branch.clone(),
-                            workspace_id: config::workspace_id(&ws.repo, &ws.branch),
+                            workspace_id,
                             status,
                         }
                     })
@@ -140,9 +150,8 @@ impl App {
                         WorkspaceStatus::Stopped => "stopped",
                         WorkspaceStatus::Lazy => "lazy",
                     };
-                    let branch_display = config::decode_branch(&config::encode_branch(&ws.branch));
                     items.push(DisplayItem {
-                        display: format!("  {branch_display:<24} {icon} {label}"),
+                        display: format!("  {:<24} {icon} {label}", ws.branch),
                         status: Some(ws.status),
                         workspace_id: Some(ws.workspace_id.clone()),
                     });
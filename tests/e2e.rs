@@ -96,9 +96,11 @@ fn container_lifecycle() {
     let args = dual::container::build_create_args(
         &container_name,
         &workspace_dir,
-        "node:20",
-        &std::collections::HashMap::new(),
-        &["node_modules".to_string()],
+        &dual::container::ContainerSpec {
+            image: "node:20".to_string(),
+            volumes: vec!["node_modules".to_string()],
+            ..Default::default()
+        },
     );
     let output = Command::new("docker").args(&args).output().unwrap();
     assert!(
@@ -156,9 +158,11 @@ fn container_exec_exit_codes() {
     let args = dual::container::build_create_args(
         &container_name,
         &workspace_dir,
-        "node:20",
-        &std::collections::HashMap::new(),
-        &["node_modules".to_string()],
+        &dual::container::ContainerSpec {
+            image: "node:20".to_string(),
+            volumes: vec!["node_modules".to_string()],
+            ..Default::default()
+        },
     );
     let output = Command::new("docker").args(&args).output().unwrap();
     assert!(output.status.success());
@@ -199,9 +203,11 @@ fn bind_mount_host_to_container() {
     let args = dual::container::build_create_args(
         &container_name,
         &clone_dir,
-        "node:20",
-        &std::collections::HashMap::new(),
-        &["node_modules".to_string()],
+        &dual::container::ContainerSpec {
+            image: "node:20".to_string(),
+            volumes: vec!["node_modules".to_string()],
+            ..Default::default()
+        },
     );
     let output = Command::new("docker").args(&args).output().unwrap();
     assert!(output.status.success());
@@ -254,12 +260,13 @@ fn network_isolation_same_port() {
     f1.register_container(name1.clone());
     f2.register_container(name2.clone());
 
-    let empty_env = std::collections::HashMap::new();
-    let default_vols = ["node_modules".to_string()];
-    let args1 =
-        dual::container::build_create_args(&name1, &clone1, "node:20", &empty_env, &default_vols);
-    let args2 =
-        dual::container::build_create_args(&name2, &clone2, "node:20", &empty_env, &default_vols);
+    let spec = dual::container::ContainerSpec {
+        image: "node:20".to_string(),
+        volumes: vec!["node_modules".to_string()],
+        ..Default::default()
+    };
+    let args1 = dual::container::build_create_args(&name1, &clone1, &spec);
+    let args2 = dual::container::build_create_args(&name2, &clone2, &spec);
 
     let out1 = Command::new("docker").args(&args1).output().unwrap();
     let out2 = Command::new("docker").args(&args2).output().unwrap();
@@ -352,6 +359,16 @@ fn tmux_session_lifecycle() {
     // Session should be alive
     assert!(backend.is_alive(&session_name));
 
+    // Tagged sessions are discovered by workspace id, not name
+    backend
+        .tag_session(&session_name, "e2e-tagged")
+        .expect("tag should succeed");
+    assert!(
+        backend
+            .list_sessions()
+            .contains(&(session_name.clone(), "e2e-tagged".to_string()))
+    );
+
     // Destroy session
     backend
         .destroy(&session_name)
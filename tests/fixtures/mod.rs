@@ -99,14 +99,25 @@ pub fn fixture_state(
 }
 
 /// Write .dual.toml hints into a workspace directory.
+#[allow(dead_code)]
 pub fn create_fixture_hints(repo_dir: &Path, ports: &[u16]) {
     let hints = dual::config::RepoHints {
         image: "node:20".to_string(),
-        ports: ports.to_vec(),
+        ports: ports.iter().map(|&port| port.into()).collect(),
+        tcp_ports: Vec::new(),
         setup: None,
+        setup_steps: Vec::new(),
+        dev: None,
         env: std::collections::HashMap::new(),
+        env_files: Vec::new(),
         extra_commands: Vec::new(),
         anonymous_volumes: vec!["node_modules".to_string()],
+        caches: Vec::new(),
+        cache_scope: dual::config::CacheScope::Global,
+        resources: dual::config::Resources::default(),
+        hooks: dual::config::Hooks::default(),
+        compose_file: None,
+        services: std::collections::BTreeMap::new(),
         shared: None,
     };
     dual::config::write_hints(repo_dir, &hints).expect("failed to write fixture hints");
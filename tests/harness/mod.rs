@@ -12,6 +12,7 @@ pub struct TestFixture {
     /// Unique identifier for this test run.
     pub id: String,
     /// Short ID for display (first 8 chars of UUID).
+    #[allow(dead_code)]
     pub short_id: String,
     /// Docker containers created by this fixture (cleaned up on Drop).
     containers: Vec<String>,
@@ -37,12 +38,14 @@ impl TestFixture {
 
     /// Generate a unique container name for this test.
     /// Pattern: `dual-test-{uuid}` (46 chars, within Docker's 63-char limit).
+    #[allow(dead_code)]
     pub fn container_name(&self) -> String {
         format!("dual-test-{}", self.id)
     }
 
     /// Generate a unique tmux session name for this test.
     /// Pattern: `dual-test-{uuid}` (no length limit for tmux).
+    #[allow(dead_code)]
     pub fn session_name(&self) -> String {
         format!("dual-test-{}", self.id)
     }
@@ -57,6 +60,7 @@ impl TestFixture {
 
     /// Create a named subdirectory under a parent directory.
     /// Not registered separately — parent dir cleanup handles it.
+    #[allow(dead_code)]
     pub fn temp_subdir(parent: &std::path::Path, name: &str) -> PathBuf {
         let dir = parent.join(name);
         std::fs::create_dir_all(&dir).expect("failed to create temp subdir");
@@ -64,16 +68,19 @@ impl TestFixture {
     }
 
     /// Register a container name for RAII cleanup.
+    #[allow(dead_code)]
     pub fn register_container(&mut self, name: String) {
         self.containers.push(name);
     }
 
     /// Register a tmux session for RAII cleanup.
+    #[allow(dead_code)]
     pub fn register_tmux_session(&mut self, name: String) {
         self.tmux_sessions.push(name);
     }
 
     /// Create a WorkspaceState with workspace_root set to a test directory.
+    #[allow(dead_code)]
     pub fn test_state(
         workspace_root: &std::path::Path,
         workspaces: &[(&str, &str, &str)],
@@ -119,6 +126,7 @@ impl Drop for TestFixture {
 ///
 /// Run before/after test suites to clean up orphaned resources from
 /// SIGKILL or other abnormal termination where Drop didn't fire.
+#[allow(dead_code)]
 pub fn cleanup_sweep() {
     // Remove all test containers
     if let Ok(output) = Command::new("docker")